@@ -0,0 +1,555 @@
+// the [[account]] entries in moneyd-config.toml, used to work out whose
+// statement a file is from what's inside it rather than where it was found,
+// and which of an institution's accounts a statement belongs to
+use crate::{
+    csvstatement::parser::header_signature,
+    service::accountservice::{create_account, get_accounts},
+    utils::{
+        accounttransporter::{AccountResponse, AccountTransport},
+        globalutil::AuthorizationData,
+        logintransporter::LoginResponse,
+        paths::state_file,
+        statementmodel::AccountIdentity,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, io::ErrorKind, path::Path};
+use tracing::{debug, warn};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    Checking,
+    Savings,
+    CreditCard,
+    Loan,
+}
+
+impl fmt::Display for AccountKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AccountKind::Checking => "checking",
+            AccountKind::Savings => "savings",
+            AccountKind::CreditCard => "credit_card",
+            AccountKind::Loan => "loan",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AccountConfig {
+    // same names as [[directory]], e.g. "chase"
+    pub institution: String,
+    // e.g. "sapphire" or "joint checking"
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<AccountKind>,
+    // the full ACCTID, or its last few digits, masked or not:
+    // "1234", "XXXXXXXXXXX1234" and "4111-...-1234" all work
+    pub account_id: String,
+    // optional hints, checked when the file carries them
+    pub bank_id: Option<String>,
+    pub org: Option<String>,
+    pub intu_bid: Option<String>,
+    // the header row of the bank's csv export. csv files rarely carry an
+    // account number, so for those this is what they're matched on
+    pub csv_header: Option<String>,
+}
+
+pub struct AccountRegistry<'a> {
+    accounts: &'a [AccountConfig],
+}
+
+#[derive(Debug)]
+pub enum AccountMatch<'a> {
+    Found(&'a AccountConfig),
+    // several accounts fit the file equally well, and picking one
+    // would file the statement under the wrong account half the time
+    Ambiguous(Vec<&'a AccountConfig>),
+    Unmatched,
+}
+
+#[cfg(test)]
+impl<'a> AccountMatch<'a> {
+    pub fn found(&self) -> Option<&'a AccountConfig> {
+        match self {
+            AccountMatch::Found(account) => Some(account),
+            _ => None,
+        }
+    }
+}
+
+// too few digits and two cards could share a suffix
+const MIN_SUFFIX_LEN: usize = 4;
+
+impl AccountConfig {
+    // last four of the account number, all we ever show or send
+    pub fn mask(&self) -> String {
+        account_suffix(&self.account_id)
+    }
+
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{} ...{}", self.institution, self.mask()),
+        }
+    }
+}
+
+impl<'a> AccountRegistry<'a> {
+    pub fn new(accounts: &'a [AccountConfig]) -> Self {
+        AccountRegistry { accounts }
+    }
+
+    // the account a statement belongs to. the account number has to match,
+    // or for a csv without one, the header row. bank id, org, intu.bid and
+    // the csv header rule an account out when both sides have them and they
+    // differ, and otherwise break ties. a tie that's left is ambiguous
+    pub fn identify(&self, identity: &AccountIdentity) -> AccountMatch<'a> {
+        self.best_match(identity, |_| true)
+    }
+
+    // like identify, but only among one institution's accounts, for
+    // files that already got their institution from their directory
+    pub fn identify_for_institution(
+        &self,
+        institution: &str,
+        identity: &AccountIdentity,
+    ) -> AccountMatch<'a> {
+        self.best_match(identity, |a| a.institution == institution)
+    }
+
+    fn best_match(
+        &self,
+        identity: &AccountIdentity,
+        candidate: impl Fn(&AccountConfig) -> bool,
+    ) -> AccountMatch<'a> {
+        let mut best_score = 0;
+        let mut best: Vec<&'a AccountConfig> = Vec::new();
+        for account in self.accounts.iter().filter(|a| candidate(a)) {
+            let Some(score) = match_score(account, identity) else {
+                continue;
+            };
+            if best.is_empty() || score > best_score {
+                best_score = score;
+                best = vec![account];
+            } else if score == best_score {
+                best.push(account);
+            }
+        }
+        match best.len() {
+            0 => AccountMatch::Unmatched,
+            1 => AccountMatch::Found(best[0]),
+            _ => AccountMatch::Ambiguous(best),
+        }
+    }
+}
+
+// how many hints agree, or none if the account is ruled out
+fn match_score(account: &AccountConfig, identity: &AccountIdentity) -> Option<usize> {
+    match identity.account_id.as_deref() {
+        Some(account_id) if !account_ids_match(&account.account_id, account_id) => return None,
+        Some(_) => {}
+        // a csv without an account number, only its header to go on
+        None if account.csv_header.is_none() || identity.csv_header.is_none() => return None,
+        None => {}
+    }
+    let csv_header = account.csv_header.as_deref().map(header_signature);
+    let hints = [
+        (account.bank_id.as_deref(), identity.bank_id.as_deref()),
+        (account.org.as_deref(), identity.fi_org.as_deref()),
+        (account.intu_bid.as_deref(), identity.intu_bid.as_deref()),
+        (csv_header.as_deref(), identity.csv_header.as_deref()),
+    ];
+    let mut score = 0;
+    for (configured, found) in hints {
+        if let (Some(configured), Some(found)) = (configured, found) {
+            if !configured.eq_ignore_ascii_case(found.trim()) {
+                return None;
+            }
+            score += 1;
+        }
+    }
+    Some(score)
+}
+
+// backend account ids we've already matched, so a run only asks the
+// backend about accounts it hasn't seen. kept in the state dir
+pub const SYNCED_ACCOUNTS_FILE: &str = "synced-accounts.json";
+
+// everything the backend knows an account by. the last four alone
+// aren't enough, a checking and a savings account can share them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccountKey {
+    pub banking_user_id: i32,
+    pub institution_id: i32,
+    pub account_mask: String,
+    pub account_type: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SyncedAccount {
+    #[serde(flatten)]
+    key: AccountKey,
+    account_id: i32,
+}
+
+impl AccountKey {
+    pub fn new(banking_user_id: i32, institution_id: i32, account: &AccountConfig) -> Self {
+        AccountKey {
+            banking_user_id,
+            institution_id,
+            account_mask: account.mask(),
+            account_type: account
+                .kind
+                .map(|k| k.to_string())
+                .unwrap_or_else(|| String::from("unknown")),
+            name: account.display_name(),
+        }
+    }
+
+    fn of(remote: &AccountResponse) -> Self {
+        AccountKey {
+            banking_user_id: remote.banking_user_id,
+            institution_id: remote.institution_id,
+            account_mask: remote.account_mask.clone(),
+            account_type: remote.account_type.clone(),
+            name: remote.name.clone(),
+        }
+    }
+}
+
+// backend account ids for the given accounts. ones matched on an earlier
+// run come from the state dir; only when some aren't there is the backend
+// asked, and whatever it doesn't have yet is created. an older backend
+// without account support leaves those out, and their statements go up
+// attributed to the institution alone
+pub async fn sync_accounts(
+    accounts: &[(i32, AccountConfig)],
+    login_data: &LoginResponse,
+    auth_data: &AuthorizationData,
+) -> HashMap<AccountKey, i32> {
+    let user_id = login_data.user.id;
+    let path = match state_file(SYNCED_ACCOUNTS_FILE) {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("Could not open the synced accounts file: {}", e);
+            return HashMap::new();
+        }
+    };
+    let mut synced = load_synced_accounts(&path).unwrap_or_else(|e| {
+        warn!("Could not read {}: {}", path.display(), e);
+        HashMap::new()
+    });
+    let unmatched: Vec<(AccountKey, &AccountConfig)> = accounts
+        .iter()
+        .map(|(institution_id, account)| {
+            (AccountKey::new(user_id, *institution_id, account), account)
+        })
+        .filter(|(key, _)| !synced.contains_key(key))
+        .collect();
+    if unmatched.is_empty() {
+        return synced;
+    }
+
+    let remote = match get_accounts(auth_data).await {
+        Ok(r) => r,
+        Err(e) => {
+            warn!(
+                "Account sync unavailable ({}), uploading statements without accounts",
+                e
+            );
+            return synced;
+        }
+    };
+    for r in remote.iter().filter(|r| r.banking_user_id == user_id) {
+        synced.insert(AccountKey::of(r), r.account_id);
+    }
+
+    for (key, account) in unmatched {
+        if synced.contains_key(&key) {
+            continue;
+        }
+        let account_data = AccountTransport {
+            banking_user_id: key.banking_user_id,
+            institution_id: key.institution_id,
+            name: key.name.clone(),
+            account_mask: key.account_mask.clone(),
+            account_type: key.account_type.clone(),
+        };
+        match create_account(&account_data, auth_data).await {
+            Ok(created) => {
+                debug!(account = %account.display_name(), "Created account");
+                synced.insert(key, created.account_id);
+            }
+            Err(e) => warn!("Could not create account {}: {}", account.display_name(), e),
+        }
+    }
+    if let Err(e) = save_synced_accounts(&path, &synced) {
+        warn!("Could not write {}: {}", path.display(), e);
+    }
+    synced
+}
+
+pub fn load_synced_accounts(
+    path: &Path,
+) -> Result<HashMap<AccountKey, i32>, Box<dyn std::error::Error>> {
+    let synced: Vec<SyncedAccount> = match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(synced.into_iter().map(|s| (s.key, s.account_id)).collect())
+}
+
+fn save_synced_accounts(
+    path: &Path,
+    synced: &HashMap<AccountKey, i32>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut accounts: Vec<SyncedAccount> = synced
+        .iter()
+        .map(|(key, account_id)| SyncedAccount {
+            key: key.clone(),
+            account_id: *account_id,
+        })
+        .collect();
+    accounts.sort_by_key(|a| a.account_id);
+    fs::write(path, serde_json::to_string_pretty(&accounts)?)?;
+    Ok(())
+}
+
+// banks mask all but the last four with X or *, and punctuate freely
+fn normalize_account_id(account_id: &str) -> String {
+    account_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .trim_start_matches(['X', 'x'])
+        .to_uppercase()
+}
+
+fn account_ids_match(configured: &str, found: &str) -> bool {
+    let configured = normalize_account_id(configured);
+    let found = normalize_account_id(found);
+    if configured.is_empty() || found.is_empty() {
+        return false;
+    }
+    let (short, long) = if configured.len() <= found.len() {
+        (&configured, &found)
+    } else {
+        (&found, &configured)
+    };
+    short == long || (short.len() >= MIN_SUFFIX_LEN && long.ends_with(short.as_str()))
+}
+
+// "XXXXXXXXXXX1234" -> "1234"
+pub fn account_suffix(account_id: &str) -> String {
+    let normalized = normalize_account_id(account_id);
+    normalized
+        .chars()
+        .skip(normalized.len().saturating_sub(MIN_SUFFIX_LEN))
+        .collect()
+}
+
+// "XXXXXXXXXXX1234" -> "...1234", for reports that shouldn't
+// print whole account numbers
+pub fn mask_account_id(account_id: &str) -> String {
+    format!("...{}", account_suffix(account_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(institution: &str, account_id: &str, org: Option<&str>) -> AccountConfig {
+        AccountConfig {
+            institution: institution.to_string(),
+            name: None,
+            kind: None,
+            account_id: account_id.to_string(),
+            bank_id: None,
+            org: org.map(|o| o.to_string()),
+            intu_bid: None,
+            csv_header: None,
+        }
+    }
+
+    fn identity(account_id: &str, org: Option<&str>) -> AccountIdentity {
+        AccountIdentity {
+            account_id: Some(account_id.to_string()),
+            fi_org: org.map(|o| o.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identify_by_masked_account_id() {
+        let accounts = vec![
+            account("chase", "1234", None),
+            account("amex", "XXXX-XXXXXX-91005", Some("AMEX")),
+        ];
+        let registry = AccountRegistry::new(&accounts);
+
+        let found = registry.identify(&identity("XXXXXXXXXXXX1234", None));
+        assert_eq!(found.found().unwrap().institution, "chase");
+        let found = registry.identify(&identity("379912345691005", Some("amex")));
+        assert_eq!(found.found().unwrap().institution, "amex");
+
+        // right digits, wrong bank
+        assert!(
+            registry
+                .identify(&identity("379912345691005", Some("Citi")))
+                .found()
+                .is_none()
+        );
+        assert!(registry.identify(&identity("5678", None)).found().is_none());
+        assert!(
+            registry
+                .identify(&AccountIdentity::default())
+                .found()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_hints_break_ties() {
+        let accounts = vec![
+            account("citizens", "1234", None),
+            account("chase", "1234", Some("B1")),
+        ];
+        let registry = AccountRegistry::new(&accounts);
+        let found = registry.identify(&identity("1234", Some("B1")));
+        assert_eq!(found.found().unwrap().institution, "chase");
+
+        // nothing to tell them apart, so neither is guessed
+        match registry.identify(&identity("1234", None)) {
+            AccountMatch::Ambiguous(accounts) => assert_eq!(accounts.len(), 2),
+            other => panic!("expected an ambiguous match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_identify_csv_by_header() {
+        let mut card = account("chase", "1234", None);
+        card.csv_header = Some(String::from(
+            "Transaction Date,Post Date,Description,Category,Type,Amount,Memo",
+        ));
+        let mut checking = account("citizens", "5678", None);
+        checking.csv_header = Some(String::from("Date,Payee,Withdrawal,Deposit"));
+        let accounts = vec![card, checking];
+        let registry = AccountRegistry::new(&accounts);
+
+        let csv = AccountIdentity {
+            csv_header: Some(header_signature(
+                "\"Date\", \"Payee\",\"Withdrawal\",\"Deposit\"",
+            )),
+            ..Default::default()
+        };
+        assert_eq!(
+            registry.identify(&csv).found().unwrap().institution,
+            "citizens"
+        );
+        let unknown = AccountIdentity {
+            csv_header: Some(header_signature("Date,Amount")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            registry.identify(&unknown),
+            AccountMatch::Unmatched
+        ));
+    }
+
+    #[test]
+    fn test_several_accounts_at_one_institution() {
+        let accounts: Vec<AccountConfig> = toml::from_str::<HashMap<String, Vec<AccountConfig>>>(
+            r#"
+            [[account]]
+            institution = "chase"
+            name = "sapphire"
+            type = "credit_card"
+            account_id = "1111"
+
+            [[account]]
+            institution = "chase"
+            name = "freedom"
+            type = "credit_card"
+            account_id = "2222"
+
+            [[account]]
+            institution = "chase"
+            type = "checking"
+            account_id = "3333"
+
+            [[account]]
+            institution = "citizens"
+            account_id = "2222"
+            "#,
+        )
+        .unwrap()
+        .remove("account")
+        .unwrap();
+        let registry = AccountRegistry::new(&accounts);
+
+        let found = registry
+            .identify_for_institution("chase", &identity("XXXX2222", None))
+            .found()
+            .unwrap();
+        assert_eq!(found.display_name(), "freedom");
+        assert_eq!(found.kind, Some(AccountKind::CreditCard));
+        let found = registry
+            .identify_for_institution("citizens", &identity("XXXX2222", None))
+            .found()
+            .unwrap();
+        assert_eq!(found.display_name(), "citizens ...2222");
+        let found = registry
+            .identify_for_institution("chase", &identity("000003333", None))
+            .found()
+            .unwrap();
+        assert_eq!(found.kind.unwrap().to_string(), "checking");
+        assert!(
+            registry
+                .identify_for_institution("amex", &identity("1111", None))
+                .found()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_account_key() {
+        let mut checking = account("chase", "XXXX1234", None);
+        checking.kind = Some(AccountKind::Checking);
+        let mut savings = checking.clone();
+        savings.kind = Some(AccountKind::Savings);
+        let key = AccountKey::new(7, 5, &checking);
+        assert_ne!(key, AccountKey::new(7, 5, &savings));
+        assert_ne!(key, AccountKey::new(8, 5, &checking));
+
+        let remote = AccountResponse {
+            account_id: 41,
+            banking_user_id: 7,
+            institution_id: 5,
+            name: String::from("chase ...1234"),
+            account_mask: String::from("1234"),
+            account_type: String::from("checking"),
+        };
+        assert_eq!(AccountKey::of(&remote), key);
+
+        let dir = std::env::temp_dir().join(format!("moneyd-accounts-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SYNCED_ACCOUNTS_FILE);
+        assert!(load_synced_accounts(&path).unwrap().is_empty());
+        let synced = HashMap::from([(key.clone(), 41)]);
+        save_synced_accounts(&path, &synced).unwrap();
+        assert_eq!(load_synced_accounts(&path).unwrap().get(&key), Some(&41));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mask_account_id() {
+        assert_eq!(mask_account_id("4111-2222-3333-1234"), "...1234");
+        assert_eq!(mask_account_id("12"), "...12");
+    }
+}
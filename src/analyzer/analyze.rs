@@ -1,20 +1,150 @@
 use crate::{
-    service::api::{GET, api_call_no_body},
-    utils::transactiontransporter::TransactionResponse,
-    AuthorizationData
+    analyzer::dataset::{DatasetRow, Selection, fetch_dataset},
+    ingestion::INSTITUTIONS,
+    utils::{
+        globalutil::{AuthorizationData, short_date},
+        transactiontransporter::TransactionResponse,
+    },
 };
+use serde::Serialize;
+use std::collections::HashSet;
 
-pub async fn analyze_data(auth_data: &AuthorizationData, institution_id: &i32, user_id: &i32) {
-    let endpoint = format!("http://localhost:8085/api/transactions/by_institution/user/{user_id}/institution/{institution_id}");
-    let txn_resp = 
-        api_call_no_body::<Vec<TransactionResponse>, GET>(endpoint, &auth_data.auth_token, &auth_data.api_key)
-            .await
-            .unwrap_or(String::from("Invalid data returned to analyze."));
-    let txns: Vec<TransactionResponse> = serde_json::from_str(&txn_resp).unwrap_or(Vec::new());
+// what `moneyd analyze` reports for an institution, or "all" of them
+// together. amounts are in cents and signed, negative for money going
+// out, so the total is what's left over. the largest is the biggest one
+// either way
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Analysis {
+    pub institution: String,
+    pub statements: usize,
+    pub transactions: usize,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    pub total_amount: i64,
+    pub average_amount: i64,
+    pub largest_amount: Option<i64>,
+    pub largest_description: Option<String>,
+}
 
-    println!("{:?}", txns);
+pub async fn analyze_data(
+    auth_data: &AuthorizationData,
+    user_id: &i32,
+    selection: &Selection,
+) -> Result<Vec<Analysis>, Box<dyn std::error::Error>> {
+    let rows = fetch_dataset(*user_id, selection, auth_data).await?;
+    Ok(analyze_rows(&rows))
+}
 
+// one per institution that has anything, then the lot merged
+// when there's more than one
+pub fn analyze_rows(rows: &[DatasetRow]) -> Vec<Analysis> {
+    let mut analyses: Vec<Analysis> = Vec::new();
+    for (name, institution_id) in INSTITUTIONS.iter() {
+        let txns: Vec<TransactionResponse> = rows
+            .iter()
+            .filter(|r| r.institution_id == *institution_id)
+            .map(|r| r.transaction.clone())
+            .collect();
+        if !txns.is_empty() {
+            analyses.push(summarize(name, &txns));
+        }
+    }
+    if analyses.len() > 1 {
+        let txns: Vec<TransactionResponse> = rows.iter().map(|r| r.transaction.clone()).collect();
+        analyses.push(summarize("all", &txns));
+    }
+    analyses
+}
 
+pub fn summarize(institution: &str, txns: &[TransactionResponse]) -> Analysis {
+    let total_amount: i64 = txns.iter().map(|t| t.signed_amount()).sum();
+    let largest = txns.iter().max_by_key(|t| t.signed_amount().abs());
+    Analysis {
+        institution: institution.to_string(),
+        statements: txns
+            .iter()
+            .map(|t| t.statement_id)
+            .collect::<HashSet<_>>()
+            .len(),
+        transactions: txns.len(),
+        first_date: txns.iter().map(|t| short_date(&t.transaction_date)).min(),
+        last_date: txns.iter().map(|t| short_date(&t.transaction_date)).max(),
+        total_amount,
+        average_amount: total_amount.checked_div(txns.len() as i64).unwrap_or(0),
+        largest_amount: largest.map(|t| t.signed_amount()),
+        largest_description: largest.map(|t| t.description.clone()),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyzer::dataset::DatasetRow,
+        utils::transactiontransporter::{CREDIT_TYPE_CODE, DEBIT_TYPE_CODE},
+    };
+
+    // unsigned with a type code, the way the backend hands them back
+    fn transaction(
+        statement_id: i32,
+        description: &str,
+        amount: i32,
+        code: i32,
+        date: &str,
+    ) -> TransactionResponse {
+        TransactionResponse {
+            transaction_id: 0,
+            statement_id,
+            description: description.to_string(),
+            amount,
+            transaction_date: format!("{}T00:00:00+00:00", date),
+            transaction_type_lookup_code: code,
+            category: None,
+        }
+    }
 
+    #[test]
+    fn test_summarize() {
+        let txns = vec![
+            transaction(1, "Coffee", 450, DEBIT_TYPE_CODE, "2025-11-03"),
+            transaction(1, "Rent", 150000, DEBIT_TYPE_CODE, "2025-11-01"),
+            transaction(2, "Refund", 8250, CREDIT_TYPE_CODE, "2025-12-02"),
+        ];
+        let analysis = summarize("chase", &txns);
+        assert_eq!(analysis.statements, 2);
+        assert_eq!(analysis.transactions, 3);
+        assert_eq!(analysis.first_date.as_deref(), Some("2025-11-01"));
+        assert_eq!(analysis.last_date.as_deref(), Some("2025-12-02"));
+        assert_eq!(analysis.total_amount, -142200);
+        assert_eq!(analysis.average_amount, -47400);
+        assert_eq!(analysis.largest_amount, Some(-150000));
+        assert_eq!(analysis.largest_description.as_deref(), Some("Rent"));
+
+        let empty = summarize("chase", &[]);
+        assert_eq!(empty.average_amount, 0);
+        assert!(empty.first_date.is_none());
+    }
+
+    #[test]
+    fn test_analyze_rows_merges_institutions() {
+        let row = |institution_id, amount, code| DatasetRow {
+            institution_id,
+            account_id: None,
+            transaction: transaction(1, "Coffee", amount, code, "2025-11-03"),
+        };
+        let analyses = analyze_rows(&[
+            row(1, 450, DEBIT_TYPE_CODE),
+            row(5, 550, DEBIT_TYPE_CODE),
+            row(1, 1000, CREDIT_TYPE_CODE),
+        ]);
+        let institutions: Vec<&str> = analyses.iter().map(|a| a.institution.as_str()).collect();
+        assert_eq!(institutions, vec!["amex", "chase", "all"]);
+        assert_eq!(analyses[0].total_amount, 550);
+        assert_eq!(analyses[1].total_amount, -550);
+        assert_eq!(analyses[2].transactions, 3);
+        assert_eq!(analyses[2].total_amount, 0);
+
+        assert_eq!(analyze_rows(&[row(5, 550, DEBIT_TYPE_CODE)]).len(), 1);
+        assert!(analyze_rows(&[]).is_empty());
+    }
+}
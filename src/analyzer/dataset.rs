@@ -0,0 +1,222 @@
+// everything the analyzer and reports work from: a user's transactions
+// across institutions, each tagged with where it came from, narrowed
+// down to the institutions, accounts and dates asked for
+use crate::{
+    accounts::account_suffix,
+    service::{
+        statementservice::{get_statements, statement_institutions},
+        transactionservice::get_transactions_by_institutions,
+    },
+    utils::{
+        accounttransporter::AccountResponse, globalutil::AuthorizationData,
+        transactiontransporter::TransactionResponse,
+    },
+};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+// empty lists mean all of them, both dates are included
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub institutions: Vec<i32>,
+    // backend account ids, see select_accounts
+    pub accounts: Vec<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatasetRow {
+    pub institution_id: i32,
+    // only known for statements uploaded with a synced [[account]]
+    pub account_id: Option<i32>,
+    pub transaction: TransactionResponse,
+}
+
+impl DatasetRow {
+    pub fn date(&self) -> Option<NaiveDate> {
+        let date = self.transaction.transaction_date.get(..10)?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    }
+}
+
+impl Selection {
+    fn matches(&self, row: &DatasetRow) -> bool {
+        let date = row.date();
+        (self.institutions.is_empty() || self.institutions.contains(&row.institution_id))
+            && (self.accounts.is_empty()
+                || row.account_id.is_some_and(|id| self.accounts.contains(&id)))
+            && self.from.is_none_or(|from| date.is_some_and(|d| d >= from))
+            && self.to.is_none_or(|to| date.is_some_and(|d| d <= to))
+    }
+
+    // the institutions worth asking the backend about, of the ones
+    // that have statements
+    fn institution_ids(&self, uploaded: &[i32]) -> Vec<i32> {
+        uploaded
+            .iter()
+            .copied()
+            .filter(|id| self.institutions.is_empty() || self.institutions.contains(id))
+            .collect()
+    }
+}
+
+// --account takes a name from the account list ("sapphire") or the
+// account number, whole or just its last four digits
+pub fn select_accounts<'a>(
+    accounts: &'a [AccountResponse],
+    selectors: &[String],
+) -> Result<Vec<&'a AccountResponse>, String> {
+    let mut selected: Vec<&AccountResponse> = Vec::new();
+    for selector in selectors {
+        let has_digits = selector.chars().any(|c| c.is_ascii_digit());
+        let found: Vec<&AccountResponse> = accounts
+            .iter()
+            .filter(|a| {
+                a.name.eq_ignore_ascii_case(selector.trim())
+                    || (has_digits && a.account_mask == account_suffix(selector))
+            })
+            .collect();
+        if found.is_empty() {
+            return Err(format!(
+                "No account matches '{}', expected one of: {}",
+                selector,
+                accounts
+                    .iter()
+                    .map(|a| format!("{} (...{})", a.name, a.account_mask))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        selected.extend(found);
+    }
+    selected.sort_by_key(|a| a.account_id);
+    selected.dedup_by_key(|a| a.account_id);
+    Ok(selected)
+}
+
+// one call per institution, merged into one list, oldest first
+pub async fn fetch_dataset(
+    user_id: i32,
+    selection: &Selection,
+    auth_data: &AuthorizationData,
+) -> Result<Vec<DatasetRow>, Box<dyn std::error::Error>> {
+    let statements = get_statements(user_id, auth_data).await?;
+    // transactions only know their statement, statements know the account
+    let accounts: HashMap<i32, Option<i32>> = statements
+        .iter()
+        .map(|s| (s.statement_id, s.account_id))
+        .collect();
+    let mut rows: Vec<DatasetRow> = Vec::new();
+    for institution_id in selection.institution_ids(&statement_institutions(&statements)) {
+        let txns = get_transactions_by_institutions(user_id, &[institution_id], auth_data).await?;
+        rows.extend(txns.into_iter().map(|transaction| DatasetRow {
+            institution_id,
+            account_id: accounts.get(&transaction.statement_id).copied().flatten(),
+            transaction,
+        }));
+    }
+    Ok(narrow(rows, selection))
+}
+
+fn narrow(mut rows: Vec<DatasetRow>, selection: &Selection) -> Vec<DatasetRow> {
+    rows.retain(|row| selection.matches(row));
+    rows.sort_by(|a, b| {
+        a.transaction
+            .transaction_date
+            .cmp(&b.transaction.transaction_date)
+    });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::transactiontransporter::DEBIT_TYPE_CODE;
+
+    fn account(account_id: i32, name: &str, mask: &str) -> AccountResponse {
+        AccountResponse {
+            account_id,
+            banking_user_id: 7,
+            institution_id: 5,
+            name: name.to_string(),
+            account_mask: mask.to_string(),
+            account_type: String::from("credit_card"),
+        }
+    }
+
+    fn coffee(institution_id: i32, account_id: Option<i32>, date: &str) -> DatasetRow {
+        DatasetRow {
+            institution_id,
+            account_id,
+            transaction: TransactionResponse {
+                transaction_id: 0,
+                statement_id: 1,
+                description: String::from("Coffee"),
+                amount: 450,
+                transaction_date: format!("{}T00:00:00+00:00", date),
+                transaction_type_lookup_code: DEBIT_TYPE_CODE,
+                category: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_narrow() {
+        let rows = vec![
+            coffee(1, Some(10), "2025-12-01"),
+            coffee(5, Some(11), "2025-11-15"),
+            coffee(5, None, "2025-10-31"),
+        ];
+        let all = narrow(rows.clone(), &Selection::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(
+            all[0].transaction.transaction_date.get(..10),
+            Some("2025-10-31")
+        );
+
+        let selection = Selection {
+            institutions: vec![5],
+            from: NaiveDate::from_ymd_opt(2025, 11, 1),
+            to: NaiveDate::from_ymd_opt(2025, 11, 30),
+            ..Selection::default()
+        };
+        assert_eq!(narrow(rows.clone(), &selection).len(), 1);
+
+        let selection = Selection {
+            accounts: vec![10],
+            ..Selection::default()
+        };
+        let narrowed = narrow(rows, &selection);
+        assert_eq!(narrowed.len(), 1);
+        assert_eq!(narrowed[0].institution_id, 1);
+    }
+
+    #[test]
+    fn test_institution_ids_come_from_statements() {
+        let uploaded = [2, 9];
+        assert_eq!(Selection::default().institution_ids(&uploaded), vec![2, 9]);
+        let selection = Selection {
+            institutions: vec![1, 9],
+            ..Selection::default()
+        };
+        assert_eq!(selection.institution_ids(&uploaded), vec![9]);
+    }
+
+    #[test]
+    fn test_select_accounts() {
+        let accounts = vec![
+            account(1, "Sapphire", "1234"),
+            account(2, "Joint checking", "9876"),
+        ];
+        let ids = |selectors: &[&str]| {
+            let selectors: Vec<String> = selectors.iter().map(|s| s.to_string()).collect();
+            select_accounts(&accounts, &selectors)
+                .map(|found| found.iter().map(|a| a.account_id).collect::<Vec<_>>())
+        };
+        assert_eq!(ids(&["sapphire"]), Ok(vec![1]));
+        assert_eq!(ids(&["XXXXXXXX9876", "1234"]), Ok(vec![1, 2]));
+        assert_eq!(ids(&["1234", "Sapphire"]), Ok(vec![1]));
+        assert!(ids(&["5555"]).is_err());
+    }
+}
@@ -1 +1,3 @@
 pub mod analyze;
+pub mod dataset;
+pub mod monthly;
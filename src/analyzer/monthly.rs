@@ -0,0 +1,280 @@
+// `moneyd monthly`: money out and in per calendar month, for each
+// institution and all of them together, next to the month before
+use crate::{analyzer::dataset::DatasetRow, ingestion::INSTITUTIONS, utils::output::format_cents};
+use chrono::{Datelike, Months, NaiveDate};
+use serde::Serialize;
+
+// amounts in cents. debits are what went out, as a positive number,
+// so net is credits - debits. the changes are against the month before
+// for the same institution, none for the first month
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MonthlySummary {
+    pub month: String,
+    pub institution: String,
+    pub debits: i64,
+    pub credits: i64,
+    pub net: i64,
+    pub debit_count: usize,
+    pub credit_count: usize,
+    pub debits_change: Option<i64>,
+    pub credits_change: Option<i64>,
+    pub net_change: Option<i64>,
+    // biggest spend first
+    pub top_merchants: Vec<MerchantTotal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MerchantTotal {
+    pub merchant: String,
+    // cents, as a positive number
+    pub spent: i64,
+}
+
+// the same for the table and csv, which only take flat rows. the
+// merchants become "Rent 1500.00; Amazon 25.99"
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MonthlyRow {
+    pub month: String,
+    pub institution: String,
+    pub debits: i64,
+    pub credits: i64,
+    pub net: i64,
+    pub debit_count: usize,
+    pub credit_count: usize,
+    pub debits_change: Option<i64>,
+    pub credits_change: Option<i64>,
+    pub net_change: Option<i64>,
+    pub top_merchants: String,
+}
+
+impl From<&MonthlySummary> for MonthlyRow {
+    fn from(summary: &MonthlySummary) -> Self {
+        MonthlyRow {
+            month: summary.month.clone(),
+            institution: summary.institution.clone(),
+            debits: summary.debits,
+            credits: summary.credits,
+            net: summary.net,
+            debit_count: summary.debit_count,
+            credit_count: summary.credit_count,
+            debits_change: summary.debits_change,
+            credits_change: summary.credits_change,
+            net_change: summary.net_change,
+            top_merchants: summary
+                .top_merchants
+                .iter()
+                .map(|m| format!("{} {}", m.merchant, format_cents(m.spent)))
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+}
+
+pub fn monthly_rows(summaries: &[MonthlySummary]) -> Vec<MonthlyRow> {
+    summaries.iter().map(MonthlyRow::from).collect()
+}
+
+// every month from the first transaction to the last, quiet months
+// included so the changes are always against the month right before
+pub fn monthly_summary(rows: &[DatasetRow], top: usize) -> Vec<MonthlySummary> {
+    let months = month_range(rows);
+    let mut groups: Vec<(&str, Vec<&DatasetRow>)> = INSTITUTIONS
+        .iter()
+        .map(|(name, id)| {
+            let rows = rows.iter().filter(|r| r.institution_id == *id);
+            (*name, rows.collect::<Vec<_>>())
+        })
+        .filter(|(_, rows)| !rows.is_empty())
+        .collect();
+    if groups.len() > 1 {
+        groups.push(("all", rows.iter().collect()));
+    }
+
+    let mut summaries: Vec<MonthlySummary> = Vec::new();
+    for (institution, rows) in groups.iter() {
+        let mut previous: Option<(i64, i64, i64)> = None;
+        for month in months.iter() {
+            let in_month: Vec<&DatasetRow> = rows
+                .iter()
+                .copied()
+                .filter(|r| r.date().is_some_and(|d| first_of_month(d) == *month))
+                .collect();
+            let summary = summarize_month(*month, institution, &in_month, previous, top);
+            previous = Some((summary.debits, summary.credits, summary.net));
+            summaries.push(summary);
+        }
+    }
+    // by month, keeping the institution order within one
+    summaries.sort_by(|a, b| a.month.cmp(&b.month));
+    summaries
+}
+
+fn summarize_month(
+    month: NaiveDate,
+    institution: &str,
+    rows: &[&DatasetRow],
+    previous: Option<(i64, i64, i64)>,
+    top: usize,
+) -> MonthlySummary {
+    let amounts = rows.iter().map(|r| r.transaction.signed_amount());
+    let debits: i64 = amounts.clone().filter(|a| *a < 0).map(|a| -a).sum();
+    let credits: i64 = amounts.clone().filter(|a| *a > 0).sum();
+    MonthlySummary {
+        month: month.format("%Y-%m").to_string(),
+        institution: institution.to_string(),
+        debits,
+        credits,
+        net: credits - debits,
+        debit_count: amounts.clone().filter(|a| *a < 0).count(),
+        credit_count: amounts.filter(|a| *a > 0).count(),
+        debits_change: previous.map(|(d, _, _)| debits - d),
+        credits_change: previous.map(|(_, c, _)| credits - c),
+        net_change: previous.map(|(_, _, n)| credits - debits - n),
+        top_merchants: top_merchants(rows, top),
+    }
+}
+
+// spending grouped by description, ignoring case and extra spaces
+fn top_merchants(rows: &[&DatasetRow], top: usize) -> Vec<MerchantTotal> {
+    let mut merchants: Vec<(String, String, i64)> = Vec::new();
+    for row in rows.iter().filter(|r| r.transaction.is_debit()) {
+        let name = row
+            .transaction
+            .description
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let key = name.to_lowercase();
+        let spent = -row.transaction.signed_amount();
+        match merchants.iter_mut().find(|(k, _, _)| *k == key) {
+            Some((_, _, total)) => *total += spent,
+            None => merchants.push((key, name, spent)),
+        }
+    }
+    // the biggest first, ties alphabetically so it's stable
+    merchants.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    merchants
+        .into_iter()
+        .take(top)
+        .map(|(_, merchant, spent)| MerchantTotal { merchant, spent })
+        .collect()
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn month_range(rows: &[DatasetRow]) -> Vec<NaiveDate> {
+    let dates: Vec<NaiveDate> = rows.iter().filter_map(|r| r.date()).collect();
+    let (Some(first), Some(last)) = (dates.iter().min(), dates.iter().max()) else {
+        return Vec::new();
+    };
+    let mut months = Vec::new();
+    let mut month = first_of_month(*first);
+    while month <= *last {
+        months.push(month);
+        match month.checked_add_months(Months::new(1)) {
+            Some(next) => month = next,
+            None => break,
+        }
+    }
+    months
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::transactiontransporter::{
+        CREDIT_TYPE_CODE, DEBIT_TYPE_CODE, TransactionResponse,
+    };
+
+    // the way the backend hands them back, unsigned with a type code
+    fn row(
+        institution_id: i32,
+        description: &str,
+        amount: i32,
+        code: i32,
+        date: &str,
+    ) -> DatasetRow {
+        DatasetRow {
+            institution_id,
+            account_id: None,
+            transaction: TransactionResponse {
+                transaction_id: 0,
+                statement_id: 1,
+                description: description.to_string(),
+                amount,
+                transaction_date: format!("{}T00:00:00+00:00", date),
+                transaction_type_lookup_code: code,
+                category: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_monthly_summary() {
+        let rows = vec![
+            row(1, "Coffee", 450, DEBIT_TYPE_CODE, "2025-10-03"),
+            row(1, "COFFEE ", 550, DEBIT_TYPE_CODE, "2025-10-20"),
+            row(1, "Rent", 150000, DEBIT_TYPE_CODE, "2025-10-01"),
+            row(1, "Salary", 250000, CREDIT_TYPE_CODE, "2025-10-15"),
+            row(1, "Refund", 1999, CREDIT_TYPE_CODE, "2025-10-16"),
+            row(5, "Amazon", 2599, DEBIT_TYPE_CODE, "2025-12-20"),
+        ];
+        let summaries = monthly_summary(&rows, 2);
+        let keys: Vec<(&str, &str)> = summaries
+            .iter()
+            .map(|s| (s.month.as_str(), s.institution.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("2025-10", "amex"),
+                ("2025-10", "chase"),
+                ("2025-10", "all"),
+                ("2025-11", "amex"),
+                ("2025-11", "chase"),
+                ("2025-11", "all"),
+                ("2025-12", "amex"),
+                ("2025-12", "chase"),
+                ("2025-12", "all"),
+            ]
+        );
+
+        let october = &summaries[0];
+        assert_eq!(october.debits, 151000);
+        assert_eq!(october.credits, 251999);
+        assert_eq!(october.net, 100999);
+        assert_eq!((october.debit_count, october.credit_count), (3, 2));
+        assert_eq!(october.net_change, None);
+        assert_eq!(
+            october.top_merchants,
+            vec![
+                MerchantTotal {
+                    merchant: String::from("Rent"),
+                    spent: 150000
+                },
+                MerchantTotal {
+                    merchant: String::from("Coffee"),
+                    spent: 1000
+                },
+            ]
+        );
+        assert_eq!(
+            MonthlyRow::from(october).top_merchants,
+            "Rent 1500.00; Coffee 10.00"
+        );
+
+        let november = &summaries[3];
+        assert_eq!(november.debits, 0);
+        assert_eq!(november.debits_change, Some(-151000));
+        assert_eq!(november.net_change, Some(-100999));
+
+        let december = &summaries[8];
+        assert_eq!(december.debits, 2599);
+        assert_eq!(december.net_change, Some(-2599));
+        assert_eq!(MonthlyRow::from(december).top_merchants, "Amazon 25.99");
+
+        assert!(monthly_summary(&[], 3).is_empty());
+    }
+}
@@ -0,0 +1,668 @@
+// what each `moneyd <command>` actually does. main.rs only parses
+// the arguments and hands over to one of these
+use crate::{
+    Env,
+    analyzer::{
+        analyze::analyze_data,
+        dataset::{Selection, fetch_dataset, select_accounts},
+        monthly::{monthly_rows, monthly_summary},
+    },
+    doctor,
+    ingestion::{self, Config, INSTITUTIONS, IngestReport, institution_id_for},
+    service::{
+        accountservice::get_accounts,
+        loginservice::try_login,
+        statementservice::{
+            delete_statement_and_transactions, get_statement, get_statement_institutions,
+            get_statements, update_statement,
+        },
+        transactionservice::{
+            delete_transaction, get_transactions_by_institution, get_transactions_by_institutions,
+            update_transaction,
+        },
+    },
+    ui::{
+        dashboard::build_dashboard,
+        loginwindow::{LoginSession, build_login_window},
+        reviewscreen::{Review, build_review_screen},
+        setupwizard::{
+            DEFAULT_BASE_URL, SetupAnswers, build_setup_wizard, render_config, render_env,
+        },
+    },
+    utils::{
+        filescan::FileFilter,
+        globalutil::{AuthorizationData, parse_and_format_date, post_statements_and_transactions},
+        logging::redact,
+        logintransporter::{LoginRequest, LoginResponse},
+        output::{OutputFormat, note, parse_cents, print_outcome, print_records},
+        paths::{config_dir, config_file},
+        statementtransporter::{StatementResponse, StatementUpdate},
+        transactiontransporter::{TransactionResponse, TransactionUpdate, unsigned_amount},
+    },
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+pub fn api_key(env: &Env) -> Result<String, Box<dyn std::error::Error>> {
+    env.api_key
+        .clone()
+        .ok_or_else(|| "API_KEY is not set, add it to .env or the environment".into())
+}
+
+// BASE_URL without a trailing slash, the endpoints add their own
+pub fn base_url(env: &Env) -> String {
+    env.base_url
+        .as_deref()
+        .unwrap_or(DEFAULT_BASE_URL)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+// MONEYD_EMAIL/MONEYD_PASSWORD when they're set, the login window otherwise
+pub async fn authenticate(
+    env: &Env,
+) -> Result<(LoginResponse, AuthorizationData), Box<dyn std::error::Error>> {
+    let api_key = api_key(env)?;
+    let base_url = base_url(env);
+    let login_res = match env_credentials(env) {
+        Some(credentials) => try_login(&credentials, &base_url, &api_key)
+            .await
+            .map_err(|e| format!("Login failed, {}", e))?,
+        // the window logs in itself, and only closes with a token
+        // or when given up on
+        None => {
+            let mut siv = build_login_window(&base_url, &api_key);
+            siv.run();
+            siv.take_user_data::<LoginSession>()
+                .and_then(|session| session.response)
+                .ok_or("Login cancelled")?
+        }
+    };
+    debug!(
+        user = login_res.user.id,
+        token = %redact::token(&login_res.token),
+        "Logged in"
+    );
+    let auth_data = AuthorizationData {
+        auth_token: login_res.token.clone(),
+        api_key,
+        base_url,
+    };
+    Ok((login_res, auth_data))
+}
+
+fn env_credentials(env: &Env) -> Option<LoginRequest> {
+    Some(LoginRequest {
+        email: env.email.clone()?,
+        password: env.password.clone()?,
+    })
+}
+
+pub async fn login(env: &Env, output: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let (login_res, _) = authenticate(env).await?;
+
+    #[derive(Serialize)]
+    struct LoggedIn<'a> {
+        user_id: i32,
+        email: &'a str,
+        username: &'a str,
+    }
+    print_outcome(
+        output,
+        &LoggedIn {
+            user_id: login_res.user.id,
+            email: &login_res.user.email,
+            username: &login_res.user.username,
+        },
+        &format!(
+            "Logged in as {} <{}> (user {})",
+            login_res.user.username, login_res.user.email, login_res.user.id
+        ),
+    )
+}
+
+pub enum IngestSource {
+    // every configured source, or only what's under the given path
+    Configured(Option<PathBuf>),
+    // one file from anywhere
+    File(PathBuf),
+    Stdin,
+}
+
+pub async fn ingest(
+    env: &Env,
+    output: OutputFormat,
+    mut config: Config,
+    source: IngestSource,
+    institution: Option<&str>,
+    parser: Option<&str>,
+    review: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(institution) = institution {
+        check_institution(institution)?;
+    }
+
+    let mut run = match source {
+        IngestSource::Configured(path) => {
+            if parser.is_some() {
+                return Err(
+                    "--parser only applies to --file and stdin, set `parser` on the directory instead"
+                        .into(),
+                );
+            }
+            if let Some(institution) = institution {
+                config.directory.retain(|d| d.name == institution);
+                config.mailbox.retain(|m| m.name == institution);
+            }
+            let mut run = match path {
+                Some(path) => ingestion::ingest_files(&config, &files_under(&path)?)?,
+                None => ingestion::ingestinator(&config)?,
+            };
+            // inbox files only find out their institution once they're parsed
+            if let Some(institution) = institution {
+                run.holders.retain(|h| h.institution_name == institution);
+            }
+            run
+        }
+        IngestSource::File(file) => {
+            let bytes = fs::read(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            let file = fs::canonicalize(&file)?;
+            ingestion::ingest_content(&config, &file, &bytes, institution, parser)?
+        }
+        IngestSource::Stdin => {
+            if review {
+                return Err(
+                    "--review needs the terminal, it can't read the statement from stdin".into(),
+                );
+            }
+            if env.email.is_none() || env.password.is_none() {
+                return Err(
+                    "Reading a statement from stdin leaves no terminal for the login \
+                            window, set MONEYD_EMAIL and MONEYD_PASSWORD"
+                        .into(),
+                );
+            }
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            ingestion::ingest_content(&config, Path::new("-"), &bytes, institution, parser)?
+        }
+    };
+
+    if review && run.new_transactions() > 0 {
+        let mut siv = build_review_screen(Review::from_run(&run));
+        siv.run();
+        match siv.take_user_data::<Review>().filter(|r| r.confirmed) {
+            Some(review) => {
+                review.apply(&mut run);
+                run.settle_left_out()?;
+            }
+            None => {
+                note("Upload cancelled, nothing was uploaded.");
+                return Ok(());
+            }
+        }
+    }
+
+    // only log in once there's something to upload
+    let uploaded = if run.new_transactions() == 0 {
+        Ok(())
+    } else {
+        let (login_res, auth_data) = authenticate(env).await?;
+        post_statements_and_transactions(&mut run.holders, &login_res, &auth_data).await
+    };
+    print_ingest_report(output, &run.report())?;
+    uploaded
+}
+
+fn print_ingest_report(
+    output: OutputFormat,
+    report: &IngestReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        OutputFormat::Csv => print_records(output, &report.files)?,
+        OutputFormat::Table => {
+            let changed = report.changed_files();
+            if !changed.is_empty() {
+                print_records(output, &changed)?;
+            }
+            if report.new_transactions == 0 {
+                note("No new transactions.");
+            } else if report.failed == 0 {
+                note(&format!(
+                    "Execution successful. {} new transactions uploaded.",
+                    report.new_transactions
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// a file is just itself, a folder is every file under it
+fn files_under(path: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let path = fs::canonicalize(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if path.is_file() {
+        return Ok(vec![path]);
+    }
+    FileFilter::new(&path, true, &[], &[])?.scan()
+}
+
+fn check_institution(institution: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    match institution_id_for(institution) {
+        0 => Err(format!(
+            "Unknown institution '{}', expected one of: {}",
+            institution,
+            INSTITUTIONS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into()),
+        id => Ok(id),
+    }
+}
+
+// what to analyze, as given on the command line. nothing means everything
+pub struct AnalysisQuery<'a> {
+    pub institutions: &'a [String],
+    // names or account numbers, see select_accounts
+    pub accounts: &'a [String],
+    // "2025-11-01", both included
+    pub from: Option<&'a str>,
+    pub to: Option<&'a str>,
+}
+
+// the institutions and dates, checked before logging in so a typo
+// doesn't cost a login first
+fn selection(query: &AnalysisQuery<'_>) -> Result<Selection, Box<dyn std::error::Error>> {
+    let date = |date: Option<&str>| -> Result<Option<NaiveDate>, Box<dyn std::error::Error>> {
+        match date {
+            Some(date) => Ok(Some(
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("'{}' is not a date like 2025-11-30", date))?,
+            )),
+            None => Ok(None),
+        }
+    };
+    Ok(Selection {
+        institutions: query
+            .institutions
+            .iter()
+            .map(|i| check_institution(i))
+            .collect::<Result<_, _>>()?,
+        accounts: Vec::new(),
+        from: date(query.from)?,
+        to: date(query.to)?,
+    })
+}
+
+// the accounts need the backend's account list
+async fn select_account_ids(
+    selection: &mut Selection,
+    accounts: &[String],
+    auth_data: &AuthorizationData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    let known = get_accounts(auth_data).await?;
+    let selected = select_accounts(&known, accounts)?;
+    selection.accounts = selected.iter().map(|a| a.account_id).collect();
+    // no point asking about institutions none of the accounts are at
+    if selection.institutions.is_empty() {
+        selection.institutions = selected.iter().map(|a| a.institution_id).collect();
+    }
+    Ok(())
+}
+
+pub async fn analyze(
+    env: &Env,
+    output: OutputFormat,
+    query: &AnalysisQuery<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = selection(query)?;
+    let (login_res, auth_data) = authenticate(env).await?;
+    select_account_ids(&mut selection, query.accounts, &auth_data).await?;
+    let analyses = analyze_data(&auth_data, &login_res.user.id, &selection).await?;
+    print_records(output, &analyses)
+}
+
+pub async fn monthly(
+    env: &Env,
+    output: OutputFormat,
+    query: &AnalysisQuery<'_>,
+    top: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = selection(query)?;
+    let (login_res, auth_data) = authenticate(env).await?;
+    select_account_ids(&mut selection, query.accounts, &auth_data).await?;
+    let rows = fetch_dataset(login_res.user.id, &selection, &auth_data).await?;
+    let summaries = monthly_summary(&rows, top);
+    match output {
+        OutputFormat::Json => print_records(output, &summaries),
+        _ => print_records(output, &monthly_rows(&summaries)),
+    }
+}
+
+pub async fn list_statements(
+    env: &Env,
+    output: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (login_res, auth_data) = authenticate(env).await?;
+    let statements = get_statements(login_res.user.id, &auth_data).await?;
+    print_records(output, &statements)
+}
+
+pub async fn show_statement(
+    env: &Env,
+    output: OutputFormat,
+    statement_id: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (login_res, auth_data) = authenticate(env).await?;
+    let statement = get_statement(statement_id, &auth_data).await?;
+    let transactions: Vec<TransactionResponse> =
+        get_transactions_by_institution(login_res.user.id, statement.institution_id, &auth_data)
+            .await?
+            .into_iter()
+            .filter(|t| t.statement_id == statement_id)
+            .collect();
+
+    #[derive(Serialize)]
+    struct StatementWithTransactions<'a> {
+        statement: &'a StatementResponse,
+        transactions: &'a [TransactionResponse],
+    }
+    if output == OutputFormat::Json {
+        let shown = StatementWithTransactions {
+            statement: &statement,
+            transactions: &transactions,
+        };
+        println!("{}", serde_json::to_string_pretty(&shown)?);
+        return Ok(());
+    }
+    print_records(output, std::slice::from_ref(&statement))?;
+    if output == OutputFormat::Table {
+        println!();
+    }
+    print_records(output, &transactions)
+}
+
+pub struct StatementEdit<'a> {
+    pub account_id: Option<i32>,
+    // as typed, "2025-11-01"
+    pub period_start: Option<&'a str>,
+    pub period_end: Option<&'a str>,
+}
+
+pub async fn edit_statement(
+    env: &Env,
+    output: OutputFormat,
+    statement_id: i32,
+    edit: &StatementEdit<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let update = StatementUpdate {
+        account_id: edit.account_id,
+        period_start: edit.period_start.map(period_date).transpose()?,
+        period_end: edit.period_end.map(period_date).transpose()?,
+    };
+    if update.is_empty() {
+        return Err("Nothing to change, pass --account, --period-start or --period-end".into());
+    }
+    let (_, auth_data) = authenticate(env).await?;
+    let statement = update_statement(statement_id, &update, &auth_data).await?;
+    print_outcome(
+        output,
+        &statement,
+        &format!("Updated statement {}.", statement_id),
+    )
+}
+
+// "2025-11-01" -> "2025-11-01T00:00:00+00:00", the way periods are uploaded
+pub fn period_date(date: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("'{}' is not a date like 2025-11-30", date))?;
+    parse_and_format_date(&date.to_string())
+}
+
+pub async fn remove_statement(
+    env: &Env,
+    output: OutputFormat,
+    statement_id: i32,
+    yes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !yes
+        && !confirm(&format!(
+            "Delete statement {} and its transactions?",
+            statement_id
+        ))?
+    {
+        note("Nothing deleted.");
+        return Ok(());
+    }
+    let (login_res, auth_data) = authenticate(env).await?;
+    let transactions =
+        delete_statement_and_transactions(login_res.user.id, statement_id, &auth_data).await?;
+
+    #[derive(Serialize)]
+    struct Deleted {
+        statement_id: i32,
+        transactions: usize,
+        deleted: bool,
+    }
+    print_outcome(
+        output,
+        &Deleted {
+            statement_id,
+            transactions,
+            deleted: true,
+        },
+        &format!(
+            "Deleted statement {} and its {} transaction(s).",
+            statement_id, transactions
+        ),
+    )
+}
+
+pub struct TransactionEdit<'a> {
+    pub description: Option<&'a str>,
+    pub category: Option<&'a str>,
+    // as typed, "-12.50" for money going out
+    pub amount: Option<&'a str>,
+}
+
+pub async fn edit_transaction(
+    env: &Env,
+    output: OutputFormat,
+    transaction_id: i32,
+    edit: &TransactionEdit<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (amount, transaction_type_lookup_code) = match edit.amount {
+        Some(amount) => {
+            let (amount, code) = unsigned_amount(parse_cents(amount)?)?;
+            (Some(amount), Some(code))
+        }
+        None => (None, None),
+    };
+    let update = TransactionUpdate {
+        description: edit.description.map(str::to_string),
+        category: edit.category.map(str::to_string),
+        amount,
+        transaction_type_lookup_code,
+    };
+    if update.is_empty() {
+        return Err("Nothing to change, pass --description, --category or --amount".into());
+    }
+    let (_, auth_data) = authenticate(env).await?;
+    let transaction = update_transaction(transaction_id, &update, &auth_data).await?;
+    print_outcome(
+        output,
+        &transaction,
+        &format!("Updated transaction {}.", transaction_id),
+    )
+}
+
+pub async fn remove_transaction(
+    env: &Env,
+    output: OutputFormat,
+    transaction_id: i32,
+    yes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !yes && !confirm(&format!("Delete transaction {}?", transaction_id))? {
+        note("Nothing deleted.");
+        return Ok(());
+    }
+    let (_, auth_data) = authenticate(env).await?;
+    delete_transaction(transaction_id, &auth_data).await?;
+
+    #[derive(Serialize)]
+    struct Deleted {
+        transaction_id: i32,
+        deleted: bool,
+    }
+    print_outcome(
+        output,
+        &Deleted {
+            transaction_id,
+            deleted: true,
+        },
+        &format!("Deleted transaction {}.", transaction_id),
+    )
+}
+
+fn confirm(question: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+pub struct TransactionQuery<'a> {
+    pub institution: Option<&'a str>,
+    pub statement_id: Option<i32>,
+    // case-insensitive substring of the description
+    pub search: Option<&'a str>,
+    pub limit: Option<usize>,
+}
+
+pub async fn list_transactions(
+    env: &Env,
+    output: OutputFormat,
+    query: &TransactionQuery<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let institution = query.institution.map(check_institution).transpose()?;
+    let (login_res, auth_data) = authenticate(env).await?;
+    let institutions: Vec<i32> = get_statement_institutions(login_res.user.id, &auth_data)
+        .await?
+        .into_iter()
+        .filter(|id| institution.is_none_or(|i| i == *id))
+        .collect();
+
+    let mut transactions =
+        get_transactions_by_institutions(login_res.user.id, &institutions, &auth_data).await?;
+
+    let search = query.search.map(|s| s.to_lowercase());
+    transactions.retain(|t| {
+        query.statement_id.is_none_or(|id| t.statement_id == id)
+            && search
+                .as_deref()
+                .is_none_or(|s| t.description.to_lowercase().contains(s))
+    });
+    transactions.sort_by(|a, b| b.transaction_date.cmp(&a.transaction_date));
+    if let Some(limit) = query.limit {
+        transactions.truncate(limit);
+    }
+    print_records(output, &transactions)
+}
+
+// the setup wizard. the config goes wherever --config, MONEYD_CONFIG or
+// the default points, the .env into the config folder where it's picked up
+pub fn setup(env: &Env, config_path: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = config_file(config_path);
+    if config_path.exists()
+        && !confirm(&format!(
+            "{} already exists, replace it?",
+            config_path.display()
+        ))?
+    {
+        note("Nothing written.");
+        return Ok(());
+    }
+
+    let mut siv = build_setup_wizard(SetupAnswers {
+        base_url: env
+            .base_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+        api_key: env.api_key.clone().unwrap_or_default(),
+        ..Default::default()
+    });
+    siv.run();
+    let Some(answers) = siv
+        .take_user_data::<SetupAnswers>()
+        .filter(|answers| answers.finished)
+    else {
+        note("Setup cancelled, nothing written.");
+        return Ok(());
+    };
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&config_path, render_config(&answers))?;
+    let env_dir = config_dir();
+    fs::create_dir_all(&env_dir)?;
+    let env_path = env_dir.join(".env");
+    let existing = fs::read_to_string(&env_path).unwrap_or_default();
+    write_private(&env_path, &render_env(&existing, &answers))?;
+
+    note(&format!("Wrote {}", config_path.display()));
+    note(&format!("Wrote {}", env_path.display()));
+    note("Run `moneyd doctor` to check everything is in place.");
+    Ok(())
+}
+
+// the .env holds the api key, so only its owner gets to read it
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+// quick sanity check of the config file; `moneyd doctor` goes further
+pub fn check_config(
+    output: OutputFormat,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    doctor::report(output, &doctor::check_config(config), false)?;
+    note(&format!(
+        "Config OK: {} directories, {} inboxes, {} mailboxes, {} accounts.",
+        config.directory.len(),
+        config.inbox.len(),
+        config.mailbox.len(),
+        config.account.len()
+    ));
+    Ok(())
+}
+
+// everything else is started from the dashboard's menu once it's logged in
+pub fn dashboard(env: &Env, config_path: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut siv = build_dashboard(
+        &base_url(env),
+        &api_key(env)?,
+        env_credentials(env),
+        config_path.map(Path::to_path_buf),
+    );
+    siv.run();
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use chrono::DateTime;
+
+use crate::parsing::statementparser::{Confidence, ParsedFile, StatementParser, as_text};
+use crate::quickbooks::parser::TempTranFromXml;
+use crate::utils::globalutil::parse_and_format_date;
+use crate::utils::statementmodel::{ParsedStatement, normalize_amount};
+
+// bank csv exports. there's no standard, so columns are found by their
+// header names. the files rarely say whose account they're from, so the
+// header row itself is kept as a signature to match an [[account]] on
+pub struct CsvParser;
+
+// in order of preference, "transaction date" wins over "post date"
+const DATE_COLUMNS: [&str; 6] = [
+    "transaction date",
+    "trans. date",
+    "date",
+    "booking date",
+    "posting date",
+    "post date",
+];
+const DESCRIPTION_COLUMNS: [&str; 5] = ["description", "payee", "merchant", "name", "memo"];
+const AMOUNT_COLUMNS: [&str; 2] = ["amount", "transaction amount"];
+const DEBIT_COLUMNS: [&str; 3] = ["debit", "withdrawal", "withdrawals"];
+const CREDIT_COLUMNS: [&str; 3] = ["credit", "deposit", "deposits"];
+const REFERENCE_COLUMNS: [&str; 3] = ["reference", "transaction id", "check number"];
+
+struct Columns {
+    date: usize,
+    description: Option<usize>,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    reference: Option<usize>,
+}
+
+impl StatementParser for CsvParser {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn sniff(&self, bytes: &[u8]) -> Confidence {
+        let text = as_text(bytes);
+        let header = text
+            .lines()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or_default();
+        let fields = split_header(header);
+        match find_columns(&fields) {
+            Some(columns) if columns.description.is_some() => Confidence::Medium,
+            Some(_) => Confidence::Low,
+            None => Confidence::None,
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>> {
+        Ok(ParsedFile {
+            statements: vec![parse_csv(&as_text(bytes))?],
+            ..ParsedFile::default()
+        })
+    }
+}
+
+pub fn parse_csv(file_content: &str) -> Result<ParsedStatement, Box<dyn std::error::Error>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(
+        file_content
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .as_bytes(),
+    );
+    let header: Vec<String> = reader.headers()?.iter().map(|h| h.to_string()).collect();
+    let columns =
+        find_columns(&header).ok_or("No date and amount columns in the CSV header".to_string())?;
+
+    let mut statement = ParsedStatement {
+        csv_header: Some(header_signature(&header.join(","))),
+        ..ParsedStatement::default()
+    };
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        // the header is line 1
+        let line = i + 2;
+        let field =
+            |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or_default().trim();
+
+        let date = field(Some(columns.date));
+        // parse_and_format_date hands back what it can't read unchanged
+        let date_posted = parse_and_format_date(date)
+            .ok()
+            .filter(|d| DateTime::parse_from_rfc3339(d).is_ok())
+            .ok_or_else(|| format!("Invalid date '{}' on line {}", date, line))?;
+        let (amount, debit) = match columns.amount {
+            Some(_) => read_amount(field(columns.amount)),
+            None if !field(columns.debit).is_empty() => (clean_amount(field(columns.debit)), true),
+            None => (clean_amount(field(columns.credit)), false),
+        };
+        let transaction_amount = normalize_amount(&amount, debit)
+            .map_err(|_| format!("Invalid amount '{}' on line {}", amount, line))?;
+        let description = field(columns.description).to_string();
+
+        statement.transactions.push(TempTranFromXml {
+            transaction_type: String::from(if debit { "DEBIT" } else { "CREDIT" }),
+            date_posted,
+            transaction_amount,
+            refnum: field(columns.reference).to_string(),
+            name: description.clone(),
+            memo: description,
+            remittance: None,
+        });
+    }
+    Ok(statement)
+}
+
+// "Transaction Date, Description ,\"Amount\"" -> "transaction date,description,amount"
+pub fn header_signature(header: &str) -> String {
+    split_header(header)
+        .iter()
+        .map(|f| f.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn split_header(header: &str) -> Vec<String> {
+    header
+        .trim_start_matches('\u{feff}')
+        .split(',')
+        .map(|f| f.trim().trim_matches('"').trim().to_string())
+        .collect()
+}
+
+fn find_columns(header: &[String]) -> Option<Columns> {
+    let find = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|h| h.eq_ignore_ascii_case(name)))
+    };
+    let columns = Columns {
+        date: find(&DATE_COLUMNS)?,
+        description: find(&DESCRIPTION_COLUMNS),
+        amount: find(&AMOUNT_COLUMNS),
+        debit: find(&DEBIT_COLUMNS),
+        credit: find(&CREDIT_COLUMNS),
+        reference: find(&REFERENCE_COLUMNS),
+    };
+    if columns.amount.is_none() && (columns.debit.is_none() || columns.credit.is_none()) {
+        return None;
+    }
+    Some(columns)
+}
+
+// "-1,234.56", "(12.50)" and "$5.00" all show up
+fn read_amount(value: &str) -> (String, bool) {
+    let negative = value.starts_with('-') || (value.starts_with('(') && value.ends_with(')'));
+    (clean_amount(value), negative)
+}
+
+fn clean_amount(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| !matches!(c, '-' | '(' | ')' | '$' | ' '))
+        .collect();
+    // a comma next to a dot is a thousands separator, on its own
+    // it's left for normalize_amount to read as a decimal comma
+    if value.contains('.') {
+        value.replace(',', "")
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHASE_CSV: &str = "Transaction Date,Post Date,Description,Category,Type,Amount,Memo\n\
+        11/03/2025,11/04/2025,COFFEE SHOP,Food & Drink,Sale,-4.50,\n\
+        11/15/2025,11/15/2025,Payment Thank You,,Payment,\"1,200.00\",\n\
+        \n";
+
+    const BANK_CSV: &str = "\u{feff}\"Date\",\"Payee\",\"Withdrawal\",\"Deposit\"\n\
+        2025-11-01,Rent,1500.00,\n\
+        2025-11-15,Salary,,2500.00\n";
+
+    #[test]
+    fn test_parse_csv_with_amount_column() {
+        assert_eq!(CsvParser.sniff(CHASE_CSV.as_bytes()), Confidence::Medium);
+        let statement = parse_csv(CHASE_CSV).unwrap();
+        assert_eq!(
+            statement.csv_header.as_deref(),
+            Some("transaction date,post date,description,category,type,amount,memo")
+        );
+        assert_eq!(statement.transactions.len(), 2);
+
+        let coffee = &statement.transactions[0];
+        assert_eq!(coffee.date_posted, "2025-11-03T00:00:00+00:00");
+        assert_eq!(coffee.memo, "COFFEE SHOP");
+        assert_eq!(coffee.transaction_amount, "-4.50");
+        assert_eq!(coffee.transaction_type, "DEBIT");
+        assert_eq!(statement.transactions[1].transaction_amount, "1200.00");
+    }
+
+    #[test]
+    fn test_parse_csv_with_debit_and_credit_columns() {
+        let statement = parse_csv(BANK_CSV).unwrap();
+        assert_eq!(
+            statement.csv_header.as_deref(),
+            Some("date,payee,withdrawal,deposit")
+        );
+        assert_eq!(statement.transactions[0].transaction_amount, "-1500.00");
+        assert_eq!(statement.transactions[1].transaction_amount, "2500.00");
+        assert_eq!(statement.transactions[1].transaction_type, "CREDIT");
+    }
+
+    #[test]
+    fn test_parse_csv_rejects_other_files() {
+        assert_eq!(
+            CsvParser.sniff(b"name,email\nbob,bob@example.com"),
+            Confidence::None
+        );
+        assert!(parse_csv("Date,Amount\nyesterday,1.00").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// moneyd daemon: watch every [[directory]] and ingest statements as they land
+use crate::{
+    ingestion::{Config, IngestRun, ingest_files, ingestinator},
+    service::loginservice::try_login,
+    utils::{
+        filescan::{is_hidden, is_temporary},
+        globalutil::{AuthorizationData, post_statements_and_transactions},
+        logintransporter::LoginRequest,
+    },
+};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::mpsc,
+};
+use tracing::{error, info, warn};
+
+// browsers write to a temp name and rename once the download completes,
+// and a file has to sit still this long before it's picked up
+const DEBOUNCE: Duration = Duration::from_secs(5);
+const TICK: Duration = Duration::from_secs(1);
+
+struct PendingFile {
+    last_seen: Instant,
+    size: u64,
+}
+
+pub async fn run_daemon(
+    config: Config,
+    credentials: LoginRequest,
+    base_url: &str,
+    api_key: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })?;
+    for dir in config.directory.iter() {
+        let mode = if dir.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        match watcher.watch(Path::new(&dir.path), mode) {
+            Ok(()) => info!("Watching {} ({})", dir.path, dir.name),
+            Err(e) => error!("Not watching {} ({}): {}", dir.path, dir.name, e),
+        }
+    }
+    for inbox in config.inbox.iter() {
+        let mode = if inbox.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        match watcher.watch(Path::new(&inbox.path), mode) {
+            Ok(()) => info!("Watching inbox {}", inbox.path),
+            Err(e) => error!("Not watching inbox {}: {}", inbox.path, e),
+        }
+    }
+    // maildir delivers into new/ and moves to cur/ once read
+    for mailbox in config.mailbox.iter() {
+        match watcher.watch(Path::new(&mailbox.path), RecursiveMode::Recursive) {
+            Ok(()) => info!("Watching mailbox {} ({})", mailbox.path, mailbox.name),
+            Err(e) => error!(
+                "Not watching mailbox {} ({}): {}",
+                mailbox.path, mailbox.name, e
+            ),
+        }
+    }
+
+    // anything that arrived while we weren't running; already
+    // uploaded transactions are filtered out by their hashes
+    info!("Catching up on existing files");
+    match ingestinator(&config) {
+        Ok(run) => upload(run, &credentials, base_url, api_key).await,
+        Err(e) => error!("Catch-up ingest failed: {}", e),
+    }
+
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+    let mut tick = tokio::time::interval(TICK);
+    let mut sigterm = signal(SignalKind::terminate())?;
+    loop {
+        tokio::select! {
+            Some(res) = rx.recv() => match res {
+                Ok(event) => record_event(&mut pending, event),
+                Err(e) => error!("Watch error: {}", e),
+            },
+            _ = tick.tick() => {
+                let ready = take_settled(&mut pending);
+                if !ready.is_empty() {
+                    ingest(&config, &ready, &credentials, base_url, api_key).await;
+                }
+            }
+            _ = sigterm.recv() => {
+                info!("SIGTERM received, shutting down");
+                break;
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Interrupted, shutting down");
+                break;
+            }
+        }
+    }
+
+    if !pending.is_empty() {
+        warn!(
+            "{} file(s) were still settling and will be picked up on the next start",
+            pending.len()
+        );
+    }
+    Ok(())
+}
+
+fn record_event(pending: &mut HashMap<PathBuf, PendingFile>, event: Event) {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+    for path in event.paths {
+        // rename-from events and deleted temp files point at nothing
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        // include/exclude globs are applied once the file has settled
+        if !metadata.is_file() || is_temporary(&path) || is_hidden(&path) {
+            continue;
+        }
+        pending.insert(
+            path,
+            PendingFile {
+                last_seen: Instant::now(),
+                size: metadata.len(),
+            },
+        );
+    }
+}
+
+// files that haven't been touched for DEBOUNCE and haven't grown since
+fn take_settled(pending: &mut HashMap<PathBuf, PendingFile>) -> Vec<PathBuf> {
+    let mut ready: Vec<PathBuf> = Vec::new();
+    pending.retain(|path, file| {
+        let Ok(metadata) = fs::metadata(path) else {
+            // gone before it settled
+            return false;
+        };
+        if metadata.len() != file.size {
+            file.size = metadata.len();
+            file.last_seen = Instant::now();
+            return true;
+        }
+        if file.last_seen.elapsed() < DEBOUNCE {
+            return true;
+        }
+        ready.push(path.clone());
+        false
+    });
+    ready
+}
+
+async fn ingest(
+    config: &Config,
+    paths: &[PathBuf],
+    credentials: &LoginRequest,
+    base_url: &str,
+    api_key: &str,
+) {
+    let paths: Vec<PathBuf> = paths.iter().filter_map(|p| p.canonicalize().ok()).collect();
+    for path in paths.iter() {
+        info!("Ingesting {}", path.display());
+    }
+    match ingest_files(config, &paths) {
+        Ok(run) => upload(run, credentials, base_url, api_key).await,
+        Err(e) => error!("Ingest failed: {}", e),
+    }
+}
+
+// logs in fresh for every upload so a long-running daemon
+// never holds on to an expired token
+async fn upload(mut run: IngestRun, credentials: &LoginRequest, base_url: &str, api_key: &str) {
+    let new_transactions = run.new_transactions();
+    if new_transactions == 0 {
+        info!("No new transactions");
+        return;
+    }
+
+    let login_res = match try_login(credentials, base_url, api_key).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Login failed, upload skipped: {}", e);
+            return;
+        }
+    };
+    let auth_data = AuthorizationData {
+        auth_token: login_res.token.clone(),
+        api_key: api_key.to_string(),
+        base_url: base_url.to_string(),
+    };
+    match post_statements_and_transactions(&mut run.holders, &login_res, &auth_data).await {
+        Ok(_) => info!("Uploaded {} new transactions", new_transactions),
+        Err(e) => error!("Upload failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_settled_waits_for_debounce() {
+        let path = std::env::temp_dir().join("moneyd-daemon-settle-test.ofx");
+        fs::write(&path, "<OFX>").unwrap();
+
+        let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+        pending.insert(
+            path.clone(),
+            PendingFile {
+                last_seen: Instant::now(),
+                size: 5,
+            },
+        );
+        assert!(take_settled(&mut pending).is_empty());
+
+        pending.get_mut(&path).unwrap().last_seen = Instant::now() - DEBOUNCE;
+        assert_eq!(take_settled(&mut pending), vec![path.clone()]);
+        assert!(pending.is_empty());
+        let _ = fs::remove_file(path);
+    }
+}
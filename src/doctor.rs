@@ -0,0 +1,468 @@
+// `moneyd doctor`: the things that would otherwise blow up halfway
+// through a run, checked up front, each with a hint on how to fix it
+use crate::{
+    Env,
+    accounts::{SYNCED_ACCOUNTS_FILE, account_suffix, load_synced_accounts},
+    commands::base_url,
+    ingestion::{self, Config, Directory, INSTITUTIONS, institution_id_for},
+    mailbox::{PROCESSED_MESSAGES_FILE, get_processed_messages},
+    parsing::registry::ParserRegistry,
+    service::{healthservice::ping_backend, loginservice::try_login},
+    utils::{
+        consumed::{CONSUMED_FILE, get_consumed_hashes},
+        globalutil::{
+            HASH_FILE, STATEMENT_HASHES_FILE, get_transaction_hashes, load_statement_hashes,
+        },
+        logintransporter::LoginRequest,
+        output::{OutputFormat, print_records},
+        paths::{LEGACY_DIR, config_file, state_dir},
+    },
+};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::Path,
+};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        f.pad(label)
+    }
+}
+
+#[derive(Serialize)]
+pub struct Finding {
+    pub status: Status,
+    // what was checked, e.g. "directory 'amex'"
+    pub subject: String,
+    pub message: String,
+    // what to do about it
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn ok(subject: &str, message: impl Into<String>) -> Self {
+        Finding {
+            status: Status::Ok,
+            subject: subject.to_string(),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(subject: &str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Finding {
+            status: Status::Warn,
+            subject: subject.to_string(),
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(subject: &str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Finding {
+            status: Status::Fail,
+            subject: subject.to_string(),
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+pub async fn doctor(
+    env: &Env,
+    output: OutputFormat,
+    config_path: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut findings: Vec<Finding> = Vec::new();
+    match ingestion::load_config(config_path) {
+        Ok(config) => {
+            findings.push(Finding::ok(
+                "config",
+                config_file(config_path).display().to_string(),
+            ));
+            findings.extend(check_config(&config));
+        }
+        Err(e) => findings.push(Finding::fail(
+            "config",
+            e.to_string(),
+            "see config/moneyd-config-template.toml for what goes where",
+        )),
+    }
+    findings.extend(check_state());
+    findings.extend(check_backend(env).await);
+    report(output, &findings, true)
+}
+
+// prints the findings and fails if any of them did.
+// `config check` only shows what's wrong, doctor shows everything
+pub fn report(
+    output: OutputFormat,
+    findings: &[Finding],
+    show_ok: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let shown: Vec<&Finding> = findings
+        .iter()
+        .filter(|f| show_ok || f.status != Status::Ok)
+        .collect();
+    if output == OutputFormat::Table {
+        for finding in shown.iter() {
+            println!(
+                "{:<4} {}: {}",
+                finding.status, finding.subject, finding.message
+            );
+            if let Some(hint) = &finding.hint {
+                println!("     {}", hint);
+            }
+        }
+    } else {
+        print_records(output, &shown)?;
+    }
+    let failures = findings.iter().filter(|f| f.status == Status::Fail).count();
+    if failures > 0 {
+        return Err(format!("{} check(s) failed", failures).into());
+    }
+    Ok(())
+}
+
+// everything that can be checked without the network
+pub fn check_config(config: &Config) -> Vec<Finding> {
+    let parsers = ParserRegistry::with_defaults().names();
+    let mut findings = Vec::new();
+
+    for dir in config.directory.iter() {
+        let subject = format!("directory '{}'", dir.name);
+        findings.extend(check_institution(&subject, &dir.name));
+        findings.extend(check_parser(&subject, dir.parser.as_deref(), &parsers));
+        findings.push(check_folder(&subject, dir));
+    }
+    for inbox in config.inbox.iter() {
+        let subject = format!("inbox {}", inbox.path);
+        findings.extend(check_parser(&subject, inbox.parser.as_deref(), &parsers));
+        findings.push(check_folder(&subject, &inbox.as_directory()));
+    }
+    for mailbox in config.mailbox.iter() {
+        let subject = format!("mailbox '{}'", mailbox.name);
+        findings.extend(check_institution(&subject, &mailbox.name));
+        findings.extend(check_parser(&subject, mailbox.parser.as_deref(), &parsers));
+        findings.push(check_folder(&subject, &mailbox.as_directory()));
+    }
+    for account in config.account.iter() {
+        let subject = format!("account '{}'", account.display_name());
+        findings.extend(check_institution(&subject, &account.institution));
+        if account_suffix(&account.account_id).is_empty() {
+            findings.push(Finding::fail(
+                &subject,
+                "account_id has no digits to match on",
+                "use the account number or at least its last four digits",
+            ));
+        }
+    }
+    if let Some(export_path) = config.investment_export_path.as_deref()
+        && !Path::new(export_path).is_dir()
+    {
+        findings.push(Finding::warn(
+            "investment_export_path",
+            format!("{} does not exist yet", export_path),
+            "it's created on the first brokerage statement, check the path is right",
+        ));
+    }
+    findings
+}
+
+fn check_institution(subject: &str, institution: &str) -> Option<Finding> {
+    (institution_id_for(institution) == 0).then(|| {
+        Finding::fail(
+            subject,
+            format!("unknown institution '{}'", institution),
+            format!(
+                "use one of: {}",
+                INSTITUTIONS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        )
+    })
+}
+
+fn check_parser(subject: &str, parser: Option<&str>, known: &[&str]) -> Option<Finding> {
+    let parser = parser?;
+    (!known.contains(&parser)).then(|| {
+        Finding::fail(
+            subject,
+            format!("unknown parser '{}'", parser),
+            format!("use one of: {}, or leave it out", known.join(", ")),
+        )
+    })
+}
+
+fn check_folder(subject: &str, dir: &Directory) -> Finding {
+    match fs::read_dir(&dir.path) {
+        Ok(_) => match dir.file_filter() {
+            Ok(_) => Finding::ok(subject, dir.path.clone()),
+            Err(e) => Finding::fail(
+                subject,
+                format!("bad include/exclude: {}", e),
+                "globs look like \"**/*.ofx\"",
+            ),
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => Finding::fail(
+            subject,
+            format!("{} does not exist", dir.path),
+            "create the folder or fix `path`",
+        ),
+        Err(e) => Finding::fail(
+            subject,
+            format!("{} is not readable: {}", dir.path, e),
+            "check it's a folder and its permissions",
+        ),
+    }
+}
+
+type StateLoader = fn(&Path) -> Result<HashSet<String>, Box<dyn std::error::Error>>;
+
+// the state files have to parse, and be writable for the next upload.
+// only looks: nothing is created or carried over from ./config here
+fn check_state() -> Vec<Finding> {
+    check_state_in(&state_dir(), Path::new(LEGACY_DIR))
+}
+
+fn check_state_in(state_dir: &Path, legacy_dir: &Path) -> Vec<Finding> {
+    let files: [(&str, StateLoader); 5] = [
+        (HASH_FILE, |p| get_transaction_hashes(p)),
+        (STATEMENT_HASHES_FILE, |p| {
+            load_statement_hashes(p).map(|_| HashSet::new())
+        }),
+        (CONSUMED_FILE, |p| get_consumed_hashes(p)),
+        (PROCESSED_MESSAGES_FILE, |p| get_processed_messages(p)),
+        // only whether it parses matters here
+        (SYNCED_ACCOUNTS_FILE, |p| {
+            load_synced_accounts(p).map(|_| HashSet::new())
+        }),
+    ];
+    let mut findings = Vec::new();
+    for (name, load) in files {
+        let subject = format!("state {}", name);
+        let path = state_dir.join(name);
+        let legacy = legacy_dir.join(name);
+        // the legacy copy is what the next run would carry over and read
+        let (read_from, note) = if path.exists() {
+            (path.clone(), String::new())
+        } else if legacy.is_file() {
+            (
+                legacy.clone(),
+                format!(" (copied to {} on the next run)", path.display()),
+            )
+        } else {
+            (path.clone(), String::from(" (not created yet)"))
+        };
+
+        let finding = if read_from.exists()
+            && let Err(e) = load(&read_from)
+        {
+            Finding::fail(
+                &subject,
+                format!("{} can't be read: {}", read_from.display(), e),
+                "fix it by hand, or delete it to start over (already uploaded files will be re-read)",
+            )
+        } else if let Err(e) = check_writable(&path) {
+            Finding::fail(
+                &subject,
+                format!("{} is not writable: {}", path.display(), e),
+                format!("check the permissions on {}", state_dir.display()),
+            )
+        } else {
+            Finding::ok(&subject, format!("{}{}", read_from.display(), note))
+        };
+        findings.push(finding);
+    }
+    findings
+}
+
+// without creating anything: an existing file has to open for appending,
+// a missing one needs the closest folder that does exist to be writable
+fn check_writable(path: &Path) -> std::io::Result<()> {
+    if path.exists() {
+        return OpenOptions::new().append(true).open(path).map(|_| ());
+    }
+    let Some(folder) = path.ancestors().skip(1).find(|p| p.exists()) else {
+        return Ok(());
+    };
+    if !folder.is_dir() {
+        return Err(std::io::Error::other(format!(
+            "{} is not a folder",
+            folder.display()
+        )));
+    }
+    if fs::metadata(folder)?.permissions().readonly() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} is read-only", folder.display()),
+        ));
+    }
+    Ok(())
+}
+
+async fn check_backend(env: &Env) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let api_key = env.api_key.clone();
+    if api_key.is_none() {
+        findings.push(Finding::fail(
+            "API_KEY",
+            "not set",
+            "add API_KEY=... to .env, here or in the config folder, or export it",
+        ));
+    }
+    let base_url = base_url(env);
+    if let Err(e) = ping_backend(&base_url).await {
+        findings.push(Finding::fail(
+            "backend",
+            format!("{} unreachable: {}", base_url, e),
+            "start the backend, or point BASE_URL at where it runs",
+        ));
+        return findings;
+    }
+    findings.push(Finding::ok("backend", format!("{} reachable", base_url)));
+
+    let (Some(api_key), Some(email), Some(password)) = (api_key, &env.email, &env.password) else {
+        findings.push(Finding::warn(
+            "credentials",
+            "MONEYD_EMAIL/MONEYD_PASSWORD not set, not checked",
+            "set both to check them, the daemon needs them anyway",
+        ));
+        return findings;
+    };
+    let credentials = LoginRequest {
+        email: email.clone(),
+        password: password.clone(),
+    };
+    findings.push(match try_login(&credentials, &base_url, &api_key).await {
+        Ok(login_res) => Finding::ok(
+            "credentials",
+            format!("logged in as {}", login_res.user.email),
+        ),
+        Err(e) => Finding::fail(
+            "credentials",
+            format!("login failed: {}", e),
+            "check MONEYD_EMAIL, MONEYD_PASSWORD and API_KEY",
+        ),
+    });
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one per test and per run, so parallel tests and runs can't collide
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("moneyd-doctor-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_template_matches_schema() {
+        let template = include_str!("../config/moneyd-config-template.toml");
+        assert!(toml::from_str::<Config>(template).is_ok());
+
+        let typo = "[[directory]]\nname = \"amex\"\npath = \"/tmp\"\nafter_uplaod = \"archive\"\n";
+        let error = toml::from_str::<Config>(typo).unwrap_err().to_string();
+        assert!(error.contains("after_uplaod"));
+    }
+
+    #[test]
+    fn test_check_config_findings() {
+        let dir = test_dir("config");
+        fs::create_dir_all(&dir).unwrap();
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [[directory]]
+            name = "amex"
+            path = "{}"
+
+            [[directory]]
+            name = "wellsfargo"
+            path = "/does/not/exist"
+            parser = "pdf"
+
+            [[account]]
+            institution = "chase"
+            account_id = "XXXX"
+            "#,
+            dir.display()
+        ))
+        .unwrap();
+
+        let findings = check_config(&config);
+        let failed: Vec<(&str, &str)> = findings
+            .iter()
+            .filter(|f| f.status == Status::Fail)
+            .map(|f| (f.subject.as_str(), f.message.as_str()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                ("directory 'wellsfargo'", "unknown institution 'wellsfargo'"),
+                ("directory 'wellsfargo'", "unknown parser 'pdf'"),
+                ("directory 'wellsfargo'", "/does/not/exist does not exist"),
+                (
+                    "account 'chase ...'",
+                    "account_id has no digits to match on"
+                ),
+            ]
+        );
+        assert!(
+            findings
+                .iter()
+                .any(|f| f.subject == "directory 'amex'" && f.status == Status::Ok)
+        );
+        assert!(report(OutputFormat::Table, &findings, false).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_check_state_creates_nothing() {
+        let dir = test_dir("state");
+        let state = dir.join("state");
+        let legacy = dir.join("config");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join(PROCESSED_MESSAGES_FILE), "<id@example.com>\n").unwrap();
+        fs::write(legacy.join(CONSUMED_FILE), "not json").unwrap();
+
+        let findings = check_state_in(&state, &legacy);
+        let find = |name: &str| {
+            findings
+                .iter()
+                .find(|f| f.subject == format!("state {}", name))
+                .unwrap()
+        };
+        assert_eq!(find(HASH_FILE).status, Status::Ok);
+        assert!(find(HASH_FILE).message.ends_with("(not created yet)"));
+        assert_eq!(find(PROCESSED_MESSAGES_FILE).status, Status::Ok);
+        assert!(find(PROCESSED_MESSAGES_FILE).message.contains("copied to"));
+        assert_eq!(find(CONSUMED_FILE).status, Status::Fail);
+        assert!(!state.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,39 +1,173 @@
 // the ingestinator
 use crate::{
-    quickbooks::parser::parse_ofx_with_fallback,
+    accounts::{AccountConfig, AccountMatch, AccountRegistry, mask_account_id},
+    mailbox::{Mailbox, PROCESSED_MESSAGES_FILE, add_processed_messages, get_processed_messages},
+    parsing::registry::ParserRegistry,
+    quickbooks::investment::InvestmentStatement,
     utils::{
-        globalutil::{get_transaction_hashes, hash_transaction_data},
+        archiver::{AfterUpload, move_to_failed},
+        consumed::{CONSUMED_FILE, ConsumedFile, add_consumed, get_consumed_hashes},
+        csvutil::export_investment_statement,
+        filescan::FileFilter,
+        globalutil::{
+            HASH_FILE, add_multiple_hashes, finish_file, get_transaction_hashes,
+            hash_transaction_data, parse_and_format_date, short_date,
+        },
+        logging::redact,
+        paths::{config_file, state_file},
+        statementmodel::{AccountIdentity, ParsedStatement},
         transactiontransporter::TransactionTransport,
+        unpack::{InnerFile, is_archive, unpack},
     },
 };
-use serde::Deserialize;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     fs::{self, File, canonicalize},
     io::Read,
-    path::Path,
+    path::{Path, PathBuf},
 };
-use toml;
+use tracing::{debug, info, info_span, warn};
 
+// unknown keys are an error, so a typo like `after_uplaod` is
+// reported instead of quietly falling back to the default
 #[derive(Deserialize, Debug)]
-struct Config {
-    directory: Vec<Directory>,
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub directory: Vec<Directory>,
+    // email sources, see mailbox.rs
+    #[serde(default)]
+    pub mailbox: Vec<Mailbox>,
+    // folders holding statements from any bank, routed by their content
+    #[serde(default)]
+    pub inbox: Vec<Inbox>,
+    #[serde(default)]
+    pub account: Vec<AccountConfig>,
+    // brokerage statements have no upload endpoint yet, so they're
+    // written out as csv here instead
+    pub investment_export_path: Option<String>,
 }
 #[derive(Deserialize, Debug)]
-struct Directory {
-    name: String,
-    path: String,
+#[serde(deny_unknown_fields)]
+pub struct Directory {
+    pub name: String,
+    pub path: String,
+    // skip content sniffing and always use this parser, e.g. "mt940"
+    pub parser: Option<String>,
+    // leave (default), archive or rename
+    #[serde(default)]
+    pub after_upload: AfterUpload,
+    // subfolders are scanned too unless this is false
+    #[serde(default = "default_recursive")]
+    pub recursive: bool,
+    // globs relative to `path`. no include means every file
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+// like a directory, except the institution comes from each
+// file's account details instead of the folder
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Inbox {
+    pub path: String,
+    pub parser: Option<String>,
+    #[serde(default)]
+    pub after_upload: AfterUpload,
+    #[serde(default = "default_recursive")]
+    pub recursive: bool,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+fn default_recursive() -> bool {
+    true
+}
+
+impl Inbox {
+    pub fn as_directory(&self) -> Directory {
+        Directory {
+            name: String::from("inbox"),
+            path: self.path.clone(),
+            parser: self.parser.clone(),
+            after_upload: self.after_upload,
+            recursive: self.recursive,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+}
+
+impl Directory {
+    pub fn file_filter(&self) -> Result<FileFilter, Box<dyn std::error::Error>> {
+        FileFilter::new(
+            &canonicalize(&self.path)?,
+            self.recursive,
+            &self.include,
+            &self.exclude,
+        )
+    }
 }
 
 pub struct TransactionBatchHolder {
     pub transaction_batches: Vec<TransactionBatch>,
     pub institution_id: i32,
+    pub institution_name: String,
+    pub directory_path: PathBuf,
+    pub after_upload: AfterUpload,
+}
+
+impl TransactionBatchHolder {
+    fn new(directory: &Directory, transaction_batches: Vec<TransactionBatch>) -> Self {
+        Self::for_institution(&directory.name, directory, transaction_batches)
+    }
+
+    fn for_institution(
+        institution: &str,
+        directory: &Directory,
+        transaction_batches: Vec<TransactionBatch>,
+    ) -> Self {
+        TransactionBatchHolder {
+            transaction_batches,
+            institution_id: institution_id_for(institution),
+            institution_name: institution.to_string(),
+            directory_path: PathBuf::from(&directory.path),
+            after_upload: directory.after_upload,
+        }
+    }
 }
 
 pub struct TransactionBatch {
     pub transactions: Vec<TransactionTransport>,
     pub hashes: HashSet<String>,
     pub all_transactions_exist: bool,
+    pub period_start: String,
+    pub period_end: String,
+    // one file can hold several statements, so several batches
+    // can share a source file
+    pub source_file: PathBuf,
+    // set when the statement came out of an archive, and recorded
+    // as consumed once the whole entry is uploaded
+    pub consumed: Option<ConsumedFile>,
+    // likewise for statements that came in by email
+    pub message_id: Option<String>,
+    pub identity: AccountIdentity,
+    // the configured [[account]] the statement belongs to, if any
+    pub account: Option<AccountConfig>,
+    // which parser read it, e.g. "ofx-sgml"
+    pub parser: &'static str,
+    // transactions in the statement that were uploaded before
+    pub duplicates: usize,
+    // filled in by the upload
+    pub statement_id: Option<i32>,
+    pub upload_error: Option<String>,
 }
 
 impl TransactionBatch {
@@ -42,97 +176,1258 @@ impl TransactionBatch {
             transactions: Vec::new(),
             hashes: HashSet::new(),
             all_transactions_exist: false,
+            period_start: String::new(),
+            period_end: String::new(),
+            source_file: PathBuf::new(),
+            consumed: None,
+            message_id: None,
+            identity: AccountIdentity::default(),
+            account: None,
+            parser: "",
+            duplicates: 0,
+            statement_id: None,
+            upload_error: None,
+        }
+    }
+
+    // the archive entry a statement came from, or else its file
+    pub fn file_label(&self) -> String {
+        match &self.consumed {
+            Some(consumed) => consumed.path.clone(),
+            None => self.source_file.to_string_lossy().to_string(),
+        }
+    }
+}
+
+// a file (or archive entry, or attachment) that gave us nothing to
+// upload: unreadable, unparseable, or a statement no [[account]] claims
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedFile {
+    pub file: String,
+    pub reason: String,
+}
+
+pub struct IngestRun {
+    pub holders: Vec<TransactionBatchHolder>,
+    pub skipped: Vec<SkippedFile>,
+    // hashes of the transactions left out in review
+    pub left_out: HashSet<String>,
+}
+
+// one row per statement found, plus one per skipped file
+#[derive(Serialize, Debug)]
+pub struct IngestRecord {
+    pub file: String,
+    pub institution: String,
+    pub account: String,
+    pub parser: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub new_transactions: usize,
+    pub duplicates: usize,
+    pub statement_id: Option<i32>,
+    pub status: IngestStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestStatus {
+    Uploaded,
+    #[serde(rename = "up_to_date")]
+    UpToDate,
+    Pending,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IngestReport {
+    pub new_transactions: usize,
+    pub uploaded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub files: Vec<IngestRecord>,
+}
+
+impl IngestReport {
+    // what's worth showing someone: files with nothing new would
+    // drown out the ones that matter
+    pub fn changed_files(&self) -> Vec<&IngestRecord> {
+        self.files
+            .iter()
+            .filter(|f| f.status != IngestStatus::UpToDate)
+            .collect()
+    }
+}
+
+impl IngestRun {
+    pub fn new_transactions(&self) -> usize {
+        self.holders
+            .iter()
+            .flat_map(|h| h.transaction_batches.iter())
+            .map(|b| b.transactions.len())
+            .sum()
+    }
+
+    // left out transactions count as seen whether or not anything gets
+    // uploaded, and a file that had everything left out is done with
+    pub fn settle_left_out(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.settle_left_out_into(&state_file(HASH_FILE)?)?;
+        self.record_settled()
+    }
+
+    // archive entries and emails with nothing left to upload are as done
+    // as uploaded ones, so they're recorded now rather than waiting for an
+    // upload that might never come. the upload records the rest once they're up
+    fn record_settled(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.record_settled_into(
+            &state_file(CONSUMED_FILE)?,
+            &state_file(PROCESSED_MESSAGES_FILE)?,
+        )
+    }
+
+    fn record_settled_into(
+        &self,
+        consumed_file: &Path,
+        messages_file: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut entries: HashMap<String, (ConsumedFile, bool)> = HashMap::new();
+        let mut messages: HashMap<String, bool> = HashMap::new();
+        for batch in self
+            .holders
+            .iter()
+            .flat_map(|h| h.transaction_batches.iter())
+        {
+            let settled = batch.all_transactions_exist || batch.transactions.is_empty();
+            if let Some(consumed) = &batch.consumed {
+                let entry = entries
+                    .entry(consumed.path.clone())
+                    .or_insert_with(|| (consumed.clone(), true));
+                entry.1 &= settled;
+            }
+            if let Some(message_id) = &batch.message_id {
+                *messages.entry(message_id.clone()).or_insert(true) &= settled;
+            }
+        }
+        let consumed: Vec<ConsumedFile> = entries
+            .into_values()
+            .filter(|(_, settled)| *settled)
+            .map(|(c, _)| c)
+            .collect();
+        if !consumed.is_empty() {
+            add_consumed(consumed_file, &consumed)?;
+        }
+        let processed: HashSet<String> = messages
+            .into_iter()
+            .filter(|(_, settled)| *settled)
+            .map(|(id, _)| id)
+            .collect();
+        if !processed.is_empty() {
+            add_processed_messages(messages_file, &processed)?;
+        }
+        Ok(())
+    }
+
+    fn settle_left_out_into(&mut self, hash_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if self.left_out.is_empty() {
+            return Ok(());
+        }
+        add_multiple_hashes(hash_file, &self.left_out)?;
+        self.left_out.clear();
+        for holder in self.holders.iter_mut() {
+            let emptied: HashSet<PathBuf> = holder
+                .transaction_batches
+                .iter()
+                .filter(|b| !b.all_transactions_exist && b.transactions.is_empty())
+                .map(|b| b.source_file.clone())
+                .collect();
+            for batch in holder.transaction_batches.iter_mut() {
+                batch.all_transactions_exist |= batch.transactions.is_empty();
+            }
+            for file in emptied.iter() {
+                let batches: Vec<&TransactionBatch> = holder
+                    .transaction_batches
+                    .iter()
+                    .filter(|b| &b.source_file == file)
+                    .collect();
+                if batches.iter().all(|b| b.all_transactions_exist) {
+                    finish_file(
+                        file,
+                        &holder.directory_path,
+                        holder.after_upload,
+                        &holder.institution_name,
+                        &batches,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> IngestReport {
+        let mut files: Vec<IngestRecord> = Vec::new();
+        for holder in self.holders.iter() {
+            for batch in holder.transaction_batches.iter() {
+                let status = if batch.upload_error.is_some() {
+                    IngestStatus::Failed
+                } else if batch.statement_id.is_some() {
+                    IngestStatus::Uploaded
+                } else if batch.all_transactions_exist || batch.transactions.is_empty() {
+                    // or everything in it was left out in review
+                    IngestStatus::UpToDate
+                } else {
+                    IngestStatus::Pending
+                };
+                files.push(IngestRecord {
+                    file: batch.file_label(),
+                    institution: holder.institution_name.clone(),
+                    account: match (&batch.account, &batch.identity.account_id) {
+                        (Some(account), _) => account.display_name(),
+                        (None, Some(account_id)) => mask_account_id(account_id),
+                        (None, None) => String::new(),
+                    },
+                    parser: batch.parser.to_string(),
+                    period_start: short_date(&batch.period_start),
+                    period_end: short_date(&batch.period_end),
+                    new_transactions: batch.transactions.len(),
+                    duplicates: batch.duplicates,
+                    statement_id: batch.statement_id,
+                    status,
+                    error: batch.upload_error.clone(),
+                });
+            }
+        }
+        for skipped in self.skipped.iter() {
+            files.push(IngestRecord {
+                file: skipped.file.clone(),
+                institution: String::new(),
+                account: String::new(),
+                parser: String::new(),
+                period_start: String::new(),
+                period_end: String::new(),
+                new_transactions: 0,
+                duplicates: 0,
+                statement_id: None,
+                status: IngestStatus::Skipped,
+                error: Some(skipped.reason.clone()),
+            });
+        }
+
+        IngestReport {
+            new_transactions: self.new_transactions(),
+            uploaded: files
+                .iter()
+                .filter(|f| f.status == IngestStatus::Uploaded)
+                .count(),
+            failed: files
+                .iter()
+                .filter(|f| f.status == IngestStatus::Failed)
+                .count(),
+            skipped: self.skipped.len(),
+            files,
         }
     }
 }
 
-const HASH_PATH: &str = "./config/existing-hashes.txt";
+// everything process_file needs besides the file itself
+struct IngestContext<'a> {
+    registry: ParserRegistry,
+    hash_set: HashSet<String>,
+    consumed: HashSet<String>,
+    processed_messages: HashSet<String>,
+    investment_export_path: Option<&'a str>,
+    skipped: RefCell<Vec<SkippedFile>>,
+    // told about every file and email as it's read
+    progress: &'a dyn Fn(&Path),
+}
+
+impl<'a> IngestContext<'a> {
+    fn load(
+        config: &'a Config,
+        progress: &'a dyn Fn(&Path),
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(IngestContext {
+            registry: ParserRegistry::with_defaults(),
+            hash_set: get_transaction_hashes(state_file(HASH_FILE)?)?,
+            consumed: get_consumed_hashes(state_file(CONSUMED_FILE)?)?,
+            processed_messages: get_processed_messages(state_file(PROCESSED_MESSAGES_FILE)?)?,
+            investment_export_path: config.investment_export_path.as_deref(),
+            skipped: RefCell::new(Vec::new()),
+            progress,
+        })
+    }
+
+    // logged now, and listed in the run report later
+    fn skip(&self, file: &str, reason: impl ToString) {
+        let reason = reason.to_string();
+        warn!("{}: {}", file, redact::text(&reason));
+        // the per-parser detail stays in the log
+        let reason = reason.lines().next().unwrap_or_default().to_string();
+        self.skipped.borrow_mut().push(SkippedFile {
+            file: file.to_string(),
+            reason,
+        });
+    }
+
+    fn finish(self, holders: Vec<TransactionBatchHolder>) -> IngestRun {
+        IngestRun {
+            holders,
+            skipped: self.skipped.into_inner(),
+            left_out: HashSet::new(),
+        }
+    }
+}
 const AMEX_INSITUTION_ID: i32 = 1;
 const CITIZENS_INSTITUTION_ID: i32 = 2;
 const CAPITAL_ONE_INSITUTION_ID: i32 = 3;
 const APPLE_INSITUTION_ID: i32 = 4;
 const CHASE_INSTITUTION_ID: i32 = 5;
 
-pub fn ingestinator() -> Result<Vec<TransactionBatchHolder>, Box<dyn std::error::Error>> {
-    // Load configuration
-    let config = load_config()?;
+// every institution we know the backend id of, by its config name
+pub const INSTITUTIONS: [(&str, i32); 5] = [
+    ("amex", AMEX_INSITUTION_ID),
+    ("citizens", CITIZENS_INSTITUTION_ID),
+    ("capitalone", CAPITAL_ONE_INSITUTION_ID),
+    ("apple", APPLE_INSITUTION_ID),
+    ("chase", CHASE_INSTITUTION_ID),
+];
+
+pub fn ingestinator(config: &Config) -> Result<IngestRun, Box<dyn std::error::Error>> {
+    ingestinator_with_progress(config, &|_| {})
+}
 
-    // Load existing file hashes
-    let mut existing_hashes = get_transaction_hashes(HASH_PATH)?;
+// for the dashboard, which shows what's being read
+pub fn ingestinator_with_progress(
+    config: &Config,
+    progress: &dyn Fn(&Path),
+) -> Result<IngestRun, Box<dyn std::error::Error>> {
+    // Load existing transaction hashes, parsers and the like
+    let context = IngestContext::load(config, progress)?;
+    let run = ingest_all(config, context)?;
+    run.record_settled()?;
+    Ok(run)
+}
 
+fn ingest_all(
+    config: &Config,
+    context: IngestContext,
+) -> Result<IngestRun, Box<dyn std::error::Error>> {
     let mut master_transaction_batch_holder: Vec<TransactionBatchHolder> = Vec::new();
     for dir in config.directory.iter() {
-        let institution_id = match dir.name.as_str() {
-            "amex" => AMEX_INSITUTION_ID,
-            "citizens" => CITIZENS_INSTITUTION_ID,
-            "capitalone" => CAPITAL_ONE_INSITUTION_ID,
-            "apple" => APPLE_INSITUTION_ID,
-            "chase" => CHASE_INSTITUTION_ID,
-            _ => 0,
-        };
-        let processing_result_batch = process_directory(&dir.path, &mut existing_hashes).unwrap();
-        let trans_holder = TransactionBatchHolder {
-            transaction_batches: processing_result_batch,
-            institution_id,
+        // a missing or unreadable directory shouldn't stop the others
+        let processing_result_batch = match process_directory(dir, &context) {
+            Ok(batches) => batches,
+            Err(e) => {
+                context.skip(&dir.path, format!("could not scan directory: {}", e));
+                continue;
+            }
         };
+        let trans_holder = TransactionBatchHolder::new(dir, processing_result_batch);
         master_transaction_batch_holder.push(trans_holder);
     }
+    for mailbox in config.mailbox.iter() {
+        // same for a mailbox, the others still get read
+        let batches = match process_mailbox(mailbox, None, &context) {
+            Ok(batches) => batches,
+            Err(e) => {
+                context.skip(&mailbox.path, format!("could not read mailbox: {}", e));
+                continue;
+            }
+        };
+        master_transaction_batch_holder.push(TransactionBatchHolder::new(
+            &mailbox.as_directory(),
+            batches,
+        ));
+    }
+    for inbox in config.inbox.iter() {
+        let files = match inbox.as_directory().file_filter().and_then(|f| f.scan()) {
+            Ok(files) => files,
+            Err(e) => {
+                context.skip(&inbox.path, format!("could not scan inbox: {}", e));
+                continue;
+            }
+        };
+        master_transaction_batch_holder.extend(process_inbox(
+            inbox,
+            &files,
+            &config.account,
+            &context,
+        ));
+    }
+    attribute_accounts(&mut master_transaction_batch_holder, &config.account);
 
     // i think the hashes need to be separated by
     // transaction set.
-    Ok(master_transaction_batch_holder)
+    Ok(context.finish(master_transaction_batch_holder))
+}
+
+// same as ingestinator, but only for the given files. each file is
+// attributed to whichever configured directory it lives under
+pub fn ingest_files(
+    config: &Config,
+    paths: &[PathBuf],
+) -> Result<IngestRun, Box<dyn std::error::Error>> {
+    let context = IngestContext::load(config, &|_| {})?;
+    let run = ingest_files_with(config, paths, context)?;
+    run.record_settled()?;
+    Ok(run)
 }
 
-fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
-    let config_path = Path::new("./config/moneyd-config.toml");
-    let absolute_config = canonicalize(config_path)?;
+fn ingest_files_with(
+    config: &Config,
+    paths: &[PathBuf],
+    context: IngestContext,
+) -> Result<IngestRun, Box<dyn std::error::Error>> {
+    let mut master_transaction_batch_holder: Vec<TransactionBatchHolder> = Vec::new();
+    for dir in config.directory.iter() {
+        // a directory that's gone shouldn't stop the files in the others
+        let filter = match dir.file_filter() {
+            Ok(filter) => filter,
+            Err(e) => {
+                context.skip(&dir.path, format!("could not scan directory: {}", e));
+                continue;
+            }
+        };
+        let mut batches: Vec<TransactionBatch> = Vec::new();
+        for path in paths.iter().filter(|p| filter.accepts(p)) {
+            batches.extend(process_file(path, dir, &context));
+        }
+        if !batches.is_empty() {
+            master_transaction_batch_holder.push(TransactionBatchHolder::new(dir, batches));
+        }
+    }
+    for mailbox in config.mailbox.iter() {
+        let mail_paths: Vec<PathBuf> = paths
+            .iter()
+            .filter(|p| mailbox.contains(p))
+            .cloned()
+            .collect();
+        if mail_paths.is_empty() {
+            continue;
+        }
+        let batches = match process_mailbox(mailbox, Some(&mail_paths), &context) {
+            Ok(batches) => batches,
+            Err(e) => {
+                context.skip(&mailbox.path, format!("could not read mailbox: {}", e));
+                continue;
+            }
+        };
+        if !batches.is_empty() {
+            master_transaction_batch_holder.push(TransactionBatchHolder::new(
+                &mailbox.as_directory(),
+                batches,
+            ));
+        }
+    }
+    for inbox in config.inbox.iter() {
+        let filter = match inbox.as_directory().file_filter() {
+            Ok(filter) => filter,
+            Err(e) => {
+                context.skip(&inbox.path, format!("could not scan inbox: {}", e));
+                continue;
+            }
+        };
+        let files: Vec<PathBuf> = paths
+            .iter()
+            .filter(|p| filter.accepts(p))
+            .cloned()
+            .collect();
+        master_transaction_batch_holder.extend(process_inbox(
+            inbox,
+            &files,
+            &config.account,
+            &context,
+        ));
+    }
+    attribute_accounts(&mut master_transaction_batch_holder, &config.account);
+
+    Ok(context.finish(master_transaction_batch_holder))
+}
+
+pub fn institution_id_for(directory_name: &str) -> i32 {
+    INSTITUTIONS
+        .iter()
+        .find(|(name, _)| *name == directory_name)
+        .map(|(_, id)| *id)
+        .unwrap_or(0)
+}
+
+// the other way around, "" for an id we don't know
+pub fn institution_name(institution_id: i32) -> &'static str {
+    INSTITUTIONS
+        .iter()
+        .find(|(_, id)| *id == institution_id)
+        .map(|(name, _)| *name)
+        .unwrap_or_default()
+}
+
+// a single file (or stdin) from outside the configured sources. it's
+// attributed to `institution` when given, otherwise to whichever
+// [[account]] its contents match, like a file dropped in an inbox.
+// the file itself is never moved
+pub fn ingest_content(
+    config: &Config,
+    file_path: &Path,
+    bytes: &[u8],
+    institution: Option<&str>,
+    parser: Option<&str>,
+) -> Result<IngestRun, Box<dyn std::error::Error>> {
+    let _span = info_span!("file", path = %file_path.display()).entered();
+    let context = IngestContext::load(config, &|_| {})?;
+    let directory = Directory {
+        name: institution.unwrap_or("manual").to_string(),
+        path: file_path
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        parser: parser.map(|p| p.to_string()),
+        after_upload: AfterUpload::Leave,
+        recursive: false,
+        include: Vec::new(),
+        exclude: Vec::new(),
+    };
+
+    let batches = if is_archive(file_path) {
+        let entries =
+            unpack(file_path, bytes).map_err(|e| format!("could not open archive: {}", e))?;
+        process_archive(file_path, entries, &directory, &context).0
+    } else {
+        let (parser, statements, _) =
+            parse_statements(bytes, &file_path.to_string_lossy(), &directory, &context)?;
+        statements
+            .into_iter()
+            .map(|statement| build_batch(statement, parser, file_path, &context.hash_set))
+            .collect()
+    };
+
+    let mut holders = match institution {
+        Some(institution) => vec![TransactionBatchHolder::for_institution(
+            institution,
+            &directory,
+            batches,
+        )],
+        None => route_by_account(batches, &config.account, &directory, &context),
+    };
+    attribute_accounts(&mut holders, &config.account);
+    let run = context.finish(holders);
+    run.record_settled()?;
+    Ok(run)
+}
+
+// see utils::paths for where the config is looked for
+pub fn load_config(config_path: Option<&Path>) -> Result<Config, Box<dyn std::error::Error>> {
+    let config_path = config_file(config_path);
+    let absolute_config = canonicalize(&config_path).map_err(|e| {
+        format!(
+            "Could not read config {}: {} (run `moneyd setup` to create one, or pass --config)",
+            config_path.display(),
+            e
+        )
+    })?;
     let mut config_file = File::open(absolute_config)?;
     let mut config_contents = String::new();
     config_file.read_to_string(&mut config_contents)?;
 
-    Ok(toml::from_str(&config_contents)?)
+    let config = toml::from_str(&config_contents)
+        .map_err(|e| format!("Invalid config {}: {}", config_path.display(), e))?;
+    Ok(config)
 }
 
 fn process_directory(
-    directory_path: &str,
-    hash_set: &mut HashSet<String>,
+    directory: &Directory,
+    context: &IngestContext,
 ) -> Result<Vec<TransactionBatch>, Box<dyn std::error::Error>> {
-    let dir = fs::read_dir(directory_path)?;
+    let _span = info_span!("institution", name = %directory.name).entered();
+    let files = directory.file_filter()?.scan()?;
+
+    let mut batches = Vec::new();
+    for file_path in files {
+        batches.extend(process_file(&file_path, directory, context));
+    }
+
+    Ok(batches)
+}
+
+// one unreadable file shouldn't sink the rest of the directory,
+// so failures are reported here and the file just yields nothing
+fn process_file(
+    file_path: &Path,
+    directory: &Directory,
+    context: &IngestContext,
+) -> Vec<TransactionBatch> {
+    let _span = info_span!("file", path = %file_path.display()).entered();
+    (context.progress)(file_path);
+    let file_name = file_path.to_string_lossy();
+    let file_content = match fs::read(file_path) {
+        Ok(c) => c,
+        Err(e) => {
+            context.skip(&file_name, format!("could not read: {}", e));
+            return Vec::new();
+        }
+    };
+
+    let (batches, complete) = if is_archive(file_path) {
+        match unpack(file_path, &file_content) {
+            Ok(entries) => process_archive(file_path, entries, directory, context),
+            Err(e) => {
+                let e = format!("could not open archive: {}", e);
+                context.skip(&file_name, &e);
+                move_unreadable(file_path, directory, &e);
+                return Vec::new();
+            }
+        }
+    } else {
+        match parse_statements(&file_content, &file_name, directory, context) {
+            Ok((parser, statements, exported)) => {
+                let batches = statements
+                    .into_iter()
+                    .map(|statement| build_batch(statement, parser, file_path, &context.hash_set))
+                    .collect();
+                (batches, exported)
+            }
+            Err(e) => {
+                context.skip(&file_name, &e);
+                move_unreadable(file_path, directory, &e.to_string());
+                return Vec::new();
+            }
+        }
+    };
+
+    // nothing in it left to upload (all seen before, or only brokerage
+    // data that's been exported), so it's as done now as it would be
+    // after an upload
+    if complete && batches.iter().all(|b| b.all_transactions_exist) {
+        finish_file(
+            file_path,
+            Path::new(&directory.path),
+            directory.after_upload,
+            &directory.name,
+            &batches.iter().collect::<Vec<_>>(),
+        );
+    }
+    batches
+}
 
+// a file nobody can parse will fail the same way every run
+fn move_unreadable(file_path: &Path, directory: &Directory, error: &str) {
+    if directory.after_upload == AfterUpload::Leave {
+        return;
+    }
+    match move_to_failed(file_path, Path::new(&directory.path), error) {
+        Ok(target) => info!("Moved {} to {}", file_path.display(), target.display()),
+        Err(e) => warn!("Could not move {} to failed: {}", file_path.display(), e),
+    }
+}
+
+// every entry goes through the parsers on its own. entries that are
+// already consumed are skipped, and one bad entry doesn't fail the archive.
+// the bool is whether every entry could be read and its investments exported
+fn process_archive(
+    file_path: &Path,
+    entries: Vec<InnerFile>,
+    directory: &Directory,
+    context: &IngestContext,
+) -> (Vec<TransactionBatch>, bool) {
+    let file_name = file_path.to_string_lossy();
     let mut batches = Vec::new();
-    for entry in dir {
-        let file_path = entry?
-            .path()
-            .canonicalize()?
-            .as_os_str()
-            .to_str()
-            .expect("Invalid file path")
-            .to_string();
-        let file_name = &file_path.as_str();
-        let file_content = fs::read_to_string(&file_path)?;
-
-        let txns = parse_ofx_with_fallback(file_content.as_str(), file_name);
-        let mut new_hashes: HashSet<String> = HashSet::new();
-        let mut batch: TransactionBatch = TransactionBatch::new();
-        // if we got this far, the parsing worked.
-        // probably
-        let mut txn_transports: Vec<TransactionTransport> = Vec::new();
-        for txn in txns.iter() {
-            let xport = txn.to_transport();
-            let hashed_xport = hash_transaction_data(&xport);
-            if !hash_set.contains(&hashed_xport) {
-                txn_transports.push(xport);
-                new_hashes.insert(hashed_xport);
-            }
-        }
-
-        if new_hashes.len() == 0 {
-            batch.all_transactions_exist = true;
-        }
-        batch.transactions = txn_transports;
-        batch.hashes = new_hashes;
-        batches.push(batch);
+    let mut complete = true;
+    for entry in entries {
+        let _span = info_span!("entry", name = %entry.name).entered();
+        let consumed = ConsumedFile::new(&file_name, &entry.name, &entry.bytes);
+        if context.consumed.contains(&consumed.hash) {
+            debug!("Already consumed, skipping");
+            continue;
+        }
+        match parse_statements(&entry.bytes, &consumed.path, directory, context) {
+            Ok((parser, statements, exported)) => {
+                for statement in statements {
+                    let mut batch = build_batch(statement, parser, file_path, &context.hash_set);
+                    batch.consumed = Some(consumed.clone());
+                    batches.push(batch);
+                }
+                complete &= exported;
+            }
+            Err(e) => {
+                context.skip(&consumed.path, &e);
+                complete = false;
+            }
+        }
     }
+    (batches, complete)
+}
+
+// the bool is false when there were investments that didn't get exported,
+// so the file isn't done with yet
+fn parse_statements(
+    bytes: &[u8],
+    file_name: &str,
+    directory: &Directory,
+    context: &IngestContext,
+) -> Result<(&'static str, Vec<ParsedStatement>, bool), Box<dyn std::error::Error>> {
+    let outcome = context
+        .registry
+        .parse_file(bytes, file_name, directory.parser.as_deref())?;
+    debug!("{}: {}", file_name, outcome.report);
+
+    let not_exported = outcome
+        .parsed
+        .investments
+        .iter()
+        .filter(|investment| !export_investments(investment, context.investment_export_path))
+        .count();
+    Ok((
+        outcome.report.parser,
+        outcome.parsed.statements,
+        not_exported == 0,
+    ))
+}
 
+// each statement goes to the institution of the account it matches.
+// a statement that matches nothing is reported and left out, rather
+// than uploaded against an institution we'd have to guess
+fn process_inbox(
+    inbox: &Inbox,
+    files: &[PathBuf],
+    accounts: &[AccountConfig],
+    context: &IngestContext,
+) -> Vec<TransactionBatchHolder> {
+    let _span = info_span!("inbox", path = %inbox.path).entered();
+    let directory = inbox.as_directory();
+    let batches: Vec<TransactionBatch> = files
+        .iter()
+        .flat_map(|file_path| process_file(file_path, &directory, context))
+        .collect();
+    route_by_account(batches, accounts, &directory, context)
+}
+
+fn route_by_account(
+    batches: Vec<TransactionBatch>,
+    accounts: &[AccountConfig],
+    directory: &Directory,
+    context: &IngestContext,
+) -> Vec<TransactionBatchHolder> {
+    let registry = AccountRegistry::new(accounts);
+    let mut by_institution: Vec<(String, Vec<TransactionBatch>)> = Vec::new();
+    for batch in batches {
+        let file = batch.source_file.to_string_lossy();
+        let account = match registry.identify(&batch.identity) {
+            AccountMatch::Found(account) => account,
+            AccountMatch::Ambiguous(accounts) => {
+                context.skip(
+                    &file,
+                    format!(
+                        "{} matches several [[account]] entries ({}), add bank_id, org, \
+                         intu_bid or csv_header to tell them apart, skipping",
+                        describe_identity(&batch.identity),
+                        describe_accounts(&accounts)
+                    ),
+                );
+                continue;
+            }
+            AccountMatch::Unmatched => {
+                context.skip(
+                    &file,
+                    format!(
+                        "no [[account]] matches {}, skipping",
+                        describe_identity(&batch.identity)
+                    ),
+                );
+                continue;
+            }
+        };
+        match by_institution
+            .iter_mut()
+            .find(|(name, _)| *name == account.institution)
+        {
+            Some((_, batches)) => batches.push(batch),
+            None => by_institution.push((account.institution.clone(), vec![batch])),
+        }
+    }
+
+    by_institution
+        .into_iter()
+        .map(|(institution, batches)| {
+            TransactionBatchHolder::for_institution(&institution, directory, batches)
+        })
+        .collect()
+}
+
+// statements without a matching [[account]] still go up, just
+// attributed to the institution alone like before
+fn attribute_accounts(holders: &mut [TransactionBatchHolder], accounts: &[AccountConfig]) {
+    let registry = AccountRegistry::new(accounts);
+    for holder in holders.iter_mut() {
+        for batch in holder.transaction_batches.iter_mut() {
+            batch.account = match registry
+                .identify_for_institution(&holder.institution_name, &batch.identity)
+            {
+                AccountMatch::Found(account) => Some(account.clone()),
+                AccountMatch::Ambiguous(accounts) => {
+                    warn!(
+                        file = %batch.source_file.display(),
+                        "{} matches several accounts ({}), uploading without one",
+                        describe_identity(&batch.identity),
+                        describe_accounts(&accounts)
+                    );
+                    None
+                }
+                AccountMatch::Unmatched => None,
+            };
+        }
+    }
+}
+
+fn describe_accounts(accounts: &[&AccountConfig]) -> String {
+    accounts
+        .iter()
+        .map(|a| a.display_name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_identity(identity: &AccountIdentity) -> String {
+    let mut parts = vec![format!(
+        "ACCTID {}",
+        identity
+            .account_id
+            .as_deref()
+            .map(|id| redact::account(id).to_string())
+            .unwrap_or_else(|| String::from("(none)"))
+    )];
+    if let Some(bank_id) = &identity.bank_id {
+        parts.push(format!("BANKID {}", bank_id));
+    }
+    if let Some(org) = &identity.fi_org {
+        parts.push(format!("ORG {}", org));
+    }
+    if let Some(intu_bid) = &identity.intu_bid {
+        parts.push(format!("INTU.BID {}", intu_bid));
+    }
+    if let Some(header) = &identity.csv_header {
+        parts.push(format!("csv header \"{}\"", header));
+    }
+    parts.join(", ")
+}
+
+// `only` limits the run to the given message files, for the daemon
+fn process_mailbox(
+    mailbox: &Mailbox,
+    only: Option<&[PathBuf]>,
+    context: &IngestContext,
+) -> Result<Vec<TransactionBatch>, Box<dyn std::error::Error>> {
+    let _span = info_span!("mailbox", name = %mailbox.name).entered();
+    let directory = mailbox.as_directory();
+    let mut batches = Vec::new();
+    for message in mailbox.unprocessed_messages(&context.processed_messages)? {
+        if let Some(only) = only
+            && !only.contains(&message.file_path)
+        {
+            continue;
+        }
+        let _span = info_span!("message", id = %message.id).entered();
+        (context.progress)(&message.file_path);
+        for attachment in message.attachments.iter() {
+            let file_name = format!("{}!{}", message.file_path.display(), attachment.name);
+            match parse_statements(&attachment.bytes, &file_name, &directory, context) {
+                Ok((parser, statements, _)) => {
+                    for statement in statements {
+                        let mut batch =
+                            build_batch(statement, parser, &message.file_path, &context.hash_set);
+                        batch.message_id = Some(message.id.clone());
+                        batches.push(batch);
+                    }
+                }
+                Err(e) => context.skip(&file_name, &e),
+            }
+        }
+    }
     Ok(batches)
 }
+
+fn export_investments(investment: &InvestmentStatement, export_path: Option<&str>) -> bool {
+    let account = investment
+        .account_id
+        .as_deref()
+        .map(|id| redact::account(id).to_string())
+        .unwrap_or_else(|| String::from("unknown account"));
+    let Some(export_path) = export_path else {
+        warn!(
+            "Found {} investment transactions and {} positions for {}, \
+             but investment_export_path is not set. Skipping.",
+            investment.transactions.len(),
+            investment.positions.len(),
+            account
+        );
+        return false;
+    };
+    match export_investment_statement(investment, export_path) {
+        Ok(paths) => {
+            for path in paths {
+                info!("Exported investments for {} to {}", account, path.display());
+            }
+            true
+        }
+        Err(e) => {
+            warn!("Failed to export investments for {}: {}", account, e);
+            false
+        }
+    }
+}
+
+fn build_batch(
+    statement: ParsedStatement,
+    parser: &'static str,
+    file_path: &Path,
+    hash_set: &HashSet<String>,
+) -> TransactionBatch {
+    let mut new_hashes: HashSet<String> = HashSet::new();
+    let mut batch: TransactionBatch = TransactionBatch::new();
+    // if we got this far, the parsing worked.
+    // probably
+    let mut txn_transports: Vec<TransactionTransport> = Vec::new();
+    for txn in statement.transactions.iter() {
+        let xport = txn.to_transport();
+        let hashed_xport = hash_transaction_data(&xport);
+        if !hash_set.contains(&hashed_xport) {
+            txn_transports.push(xport);
+            new_hashes.insert(hashed_xport);
+        }
+    }
+
+    // files without a period of their own get one from their transactions,
+    // and an empty statement just gets today
+    let today = parse_and_format_date(&Utc::now().date_naive().to_string()).unwrap();
+    let (period_start, period_end) = statement.period(&txn_transports);
+    batch.period_start = period_start.unwrap_or_else(|| today.clone());
+    batch.period_end = period_end.unwrap_or(today);
+
+    if new_hashes.is_empty() {
+        batch.all_transactions_exist = true;
+    }
+    batch.duplicates = statement.transactions.len() - txn_transports.len();
+    batch.transactions = txn_transports;
+    batch.hashes = new_hashes;
+    batch.parser = parser;
+    batch.source_file = file_path.to_path_buf();
+    batch.identity = statement.identity();
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_context() -> IngestContext<'static> {
+        IngestContext {
+            registry: ParserRegistry::with_defaults(),
+            hash_set: HashSet::new(),
+            consumed: HashSet::new(),
+            processed_messages: HashSet::new(),
+            investment_export_path: None,
+            skipped: RefCell::new(Vec::new()),
+            progress: &|_| {},
+        }
+    }
+
+    fn directory(name: &str, path: &Path) -> Directory {
+        Directory {
+            name: name.to_string(),
+            path: path.to_string_lossy().to_string(),
+            parser: None,
+            after_upload: AfterUpload::Leave,
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    fn batch(file: &str, new_transactions: usize) -> TransactionBatch {
+        let mut batch = TransactionBatch::new();
+        batch.source_file = PathBuf::from(file);
+        batch.parser = "ofx-sgml";
+        batch.period_start = String::from("2025-11-01T00:00:00+00:00");
+        batch.period_end = String::from("2025-11-30T00:00:00+00:00");
+        batch.all_transactions_exist = new_transactions == 0;
+        for _ in 0..new_transactions {
+            batch.transactions.push(TransactionTransport {
+                statement_id: None,
+                description: String::from("Coffee"),
+                amount: 450,
+                transaction_date: String::from("2025-11-03T00:00:00+00:00"),
+                refnum: String::new(),
+                transaction_type_lookup_code: 10,
+            });
+        }
+        batch
+    }
+
+    #[test]
+    fn test_run_report() {
+        let directory = Directory {
+            name: String::from("chase"),
+            path: String::from("/statements/chase"),
+            parser: None,
+            after_upload: AfterUpload::Leave,
+            recursive: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        };
+        let mut uploaded = batch("/statements/chase/november.ofx", 2);
+        uploaded.statement_id = Some(41);
+        uploaded.identity.account_id = Some(String::from("XXXXXXXX1234"));
+        let mut failed = batch("/statements/chase/december.ofx", 1);
+        failed.upload_error = Some(String::from("500 Internal Server Error"));
+        let mut old = batch("/statements/chase/october.ofx", 0);
+        old.duplicates = 12;
+
+        let run = IngestRun {
+            holders: vec![TransactionBatchHolder::new(
+                &directory,
+                vec![uploaded, failed, old],
+            )],
+            skipped: vec![SkippedFile {
+                file: String::from("/statements/chase/notes.txt"),
+                reason: String::from("No parser could read it"),
+            }],
+            left_out: HashSet::new(),
+        };
+        let report = run.report();
+
+        assert_eq!(report.new_transactions, 3);
+        assert_eq!((report.uploaded, report.failed, report.skipped), (1, 1, 1));
+        let statuses: Vec<IngestStatus> = report.files.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                IngestStatus::Uploaded,
+                IngestStatus::Failed,
+                IngestStatus::UpToDate,
+                IngestStatus::Skipped
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&report.files[2].status).unwrap(),
+            "\"up_to_date\""
+        );
+        assert_eq!(report.files[0].account, "...1234");
+        assert_eq!(report.files[0].statement_id, Some(41));
+        assert_eq!(report.files[0].period_end, "2025-11-30");
+        assert_eq!(report.files[2].duplicates, 12);
+        let changed: Vec<&str> = report
+            .changed_files()
+            .iter()
+            .map(|f| f.file.as_str())
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                "/statements/chase/november.ofx",
+                "/statements/chase/december.ofx",
+                "/statements/chase/notes.txt"
+            ]
+        );
+        assert_eq!(
+            report.files[3].error.as_deref(),
+            Some("No parser could read it")
+        );
+    }
+
+    #[test]
+    fn test_process_file_archives_files_with_nothing_new() {
+        let dir = std::env::temp_dir().join(format!("moneyd-seen-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let statement = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST><DTSTART>20251101120000<DTEND>20251130120000<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20251129120000<TRNAMT>-0.92<FITID>1<NAME>Test</STMTTRN></BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let new_file = dir.join("new.qfx");
+        let seen_file = dir.join("seen.qfx");
+        fs::write(&new_file, statement).unwrap();
+        fs::write(&seen_file, statement).unwrap();
+        let directory = Directory {
+            name: String::from("chase"),
+            path: dir.to_string_lossy().to_string(),
+            parser: None,
+            after_upload: AfterUpload::Archive,
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        };
+        let mut context = empty_context();
+
+        // new transactions wait for the upload
+        let batches = process_file(&new_file, &directory, &context);
+        assert!(!batches[0].all_transactions_exist);
+        assert!(new_file.exists());
+
+        context.hash_set = batches[0].hashes.clone();
+        let batches = process_file(&seen_file, &directory, &context);
+        assert!(batches[0].all_transactions_exist);
+        assert!(!seen_file.exists());
+        assert!(dir.join("archive/2025/11/seen.qfx").exists());
+
+        // an archive that won't open is as unreadable as a bad statement
+        let broken = dir.join("statements.zip");
+        fs::write(&broken, "not a zip").unwrap();
+        assert!(process_file(&broken, &directory, &context).is_empty());
+        assert!(!broken.exists());
+        assert!(dir.join("failed/statements.zip").exists());
+        assert!(dir.join("failed/statements.zip.error.txt").exists());
+
+        // everything left out in review: recorded and archived without an upload
+        context.hash_set = HashSet::new();
+        let mut batches = process_file(&new_file, &directory, &context);
+        let left_out = std::mem::take(&mut batches[0].hashes);
+        batches[0].transactions.clear();
+        let mut run = IngestRun {
+            holders: vec![TransactionBatchHolder::new(&directory, batches)],
+            skipped: Vec::new(),
+            left_out: left_out.clone(),
+        };
+        let hash_file = dir.join("hashes.txt");
+        run.settle_left_out_into(&hash_file).unwrap();
+        assert_eq!(get_transaction_hashes(&hash_file).unwrap(), left_out);
+        assert!(run.left_out.is_empty());
+        assert!(run.holders[0].transaction_batches[0].all_transactions_exist);
+        assert!(!new_file.exists());
+        assert!(dir.join("archive/2025/11/new.qfx").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ingest_files_skips_missing_directories() {
+        let dir = std::env::temp_dir().join(format!("moneyd-missing-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let statement = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST><STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20251129120000<TRNAMT>-0.92<FITID>1<NAME>Test</STMTTRN></BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let file = dir.join("november.qfx");
+        fs::write(&file, statement).unwrap();
+        let missing = dir.join("gone");
+        let config = Config {
+            directory: vec![directory("amex", &missing), directory("chase", &dir)],
+            mailbox: Vec::new(),
+            inbox: Vec::new(),
+            account: Vec::new(),
+            investment_export_path: None,
+        };
+
+        let run =
+            ingest_files_with(&config, &[file.canonicalize().unwrap()], empty_context()).unwrap();
+        assert_eq!(run.holders.len(), 1);
+        assert_eq!(run.holders[0].institution_name, "chase");
+        assert_eq!(run.holders[0].transaction_batches.len(), 1);
+        assert_eq!(run.skipped.len(), 1);
+        assert_eq!(run.skipped[0].file, missing.to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ingest_skips_missing_mailboxes_and_inboxes() {
+        let dir = std::env::temp_dir().join(format!("moneyd-mailbox-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let missing = dir.join("mail");
+        let config = Config {
+            directory: vec![directory("chase", &dir)],
+            mailbox: vec![Mailbox {
+                name: String::from("amex"),
+                path: missing.to_string_lossy().to_string(),
+                from: Vec::new(),
+                subject: Vec::new(),
+                attachments: Vec::new(),
+                parser: None,
+            }],
+            inbox: vec![Inbox {
+                path: dir.join("inbox").to_string_lossy().to_string(),
+                parser: None,
+                after_upload: AfterUpload::Leave,
+                recursive: true,
+                include: Vec::new(),
+                exclude: Vec::new(),
+            }],
+            account: Vec::new(),
+            investment_export_path: None,
+        };
+
+        let run = ingest_all(&config, empty_context()).unwrap();
+        assert_eq!(run.holders.len(), 1);
+        assert_eq!(run.holders[0].institution_name, "chase");
+        let skipped: Vec<&str> = run.skipped.iter().map(|s| s.file.as_str()).collect();
+        assert_eq!(
+            skipped,
+            vec![
+                missing.to_str().unwrap(),
+                dir.join("inbox").to_str().unwrap()
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_investment_files_wait_for_their_export() {
+        let dir = std::env::temp_dir().join(format!("moneyd-invest-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let statement = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS><DTASOF>20251130120000<INVACCTFROM><ACCTID>X0000001</INVACCTFROM><INVTRANLIST><INCOME><INVTRAN><FITID>T2<DTTRADE>20251115120000<MEMO>Dividend</INVTRAN><INCOMETYPE>DIV<TOTAL>2.40</INCOME></INVTRANLIST></INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1></OFX>";
+        let file = dir.join("brokerage.ofx");
+        fs::write(&file, statement).unwrap();
+        let mut directory = directory("brokerage", &dir);
+        directory.after_upload = AfterUpload::Archive;
+        let mut context = empty_context();
+
+        // no investment_export_path, so it stays for the next run
+        assert!(process_file(&file, &directory, &context).is_empty());
+        assert!(file.exists());
+
+        let export = dir.join("export");
+        let export_path = export.to_string_lossy().to_string();
+        context.investment_export_path = Some(&export_path);
+        assert!(process_file(&file, &directory, &context).is_empty());
+        assert!(!file.exists());
+        assert!(export.join("X0000001_2025-11-30_transactions.csv").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_settled_entries_and_emails() {
+        let dir = std::env::temp_dir().join(format!("moneyd-settled-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let entry = |name: &str| ConsumedFile::new("/statements/2025.zip", name, name.as_bytes());
+        // everything in it seen before, partly new, and left out in review
+        let mut seen = batch("/statements/2025.zip", 0);
+        seen.consumed = Some(entry("october.ofx"));
+        let mut new = batch("/statements/2025.zip", 1);
+        new.consumed = Some(entry("november.ofx"));
+        let mut also_seen = batch("/statements/2025.zip", 0);
+        also_seen.consumed = Some(entry("november.ofx"));
+        let mut left_out = batch("/statements/2025.zip", 0);
+        left_out.all_transactions_exist = false;
+        left_out.consumed = Some(entry("december.ofx"));
+        let mut seen_email = batch("/mail/cur/1.eml", 0);
+        seen_email.message_id = Some(String::from("<stmt-2025-10@chase.com>"));
+        let mut new_email = batch("/mail/cur/2.eml", 1);
+        new_email.message_id = Some(String::from("<stmt-2025-11@chase.com>"));
+        let run = IngestRun {
+            holders: vec![TransactionBatchHolder::new(
+                &directory("chase", &dir),
+                vec![seen, new, also_seen, left_out, seen_email, new_email],
+            )],
+            skipped: Vec::new(),
+            left_out: HashSet::new(),
+        };
+
+        let (consumed_file, messages_file) =
+            (dir.join(CONSUMED_FILE), dir.join(PROCESSED_MESSAGES_FILE));
+        run.record_settled_into(&consumed_file, &messages_file)
+            .unwrap();
+        assert_eq!(
+            get_consumed_hashes(&consumed_file).unwrap(),
+            HashSet::from([entry("october.ofx").hash, entry("december.ofx").hash])
+        );
+        assert_eq!(
+            get_processed_messages(&messages_file).unwrap(),
+            HashSet::from([String::from("<stmt-2025-10@chase.com>")])
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,460 @@
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::reader::Reader;
+
+use crate::parsing::statementparser::{Confidence, ParsedFile, StatementParser, as_text};
+use crate::quickbooks::parser::TempTranFromXml;
+use crate::utils::globalutil::parse_and_format_date;
+use crate::utils::statementmodel::{
+    ParsedStatement, RemittanceInfo, StatementBalance, normalize_amount,
+};
+
+// camt.053 wraps statements in <BkToCstmrStmt><Stmt>,
+// camt.052 wraps intraday reports in <BkToCstmrAcctRpt><Rpt>
+const CAMT_MARKERS: [&str; 4] = ["camt.053", "camt.052", "BkToCstmrStmt", "BkToCstmrAcctRpt"];
+
+#[derive(Debug, Default)]
+struct CamtBalance {
+    code: String,
+    amount: String,
+    credit_debit: String,
+    date: String,
+}
+
+#[derive(Debug, Default)]
+struct CamtEntry {
+    amount: String,
+    credit_debit: String,
+    status: String,
+    booking_date: String,
+    value_date: String,
+    account_servicer_ref: String,
+    end_to_end_id: String,
+    debtor_name: String,
+    debtor_account: String,
+    creditor_name: String,
+    creditor_account: String,
+    additional_info: String,
+    remittance: RemittanceInfo,
+}
+
+impl CamtEntry {
+    fn is_debit(&self) -> bool {
+        self.credit_debit == "DBIT"
+    }
+
+    fn into_temp_tran(mut self) -> Result<TempTranFromXml, Box<dyn std::error::Error>> {
+        let is_debit = self.is_debit();
+        // the counterparty is whoever is on the other side of the money
+        let (counterparty_name, counterparty_account) = if is_debit {
+            (self.creditor_name, self.creditor_account)
+        } else {
+            (self.debtor_name, self.debtor_account)
+        };
+        let counterparty_name = non_empty(counterparty_name);
+        self.remittance.counterparty_name = counterparty_name.clone();
+        self.remittance.counterparty_account = non_empty(counterparty_account);
+
+        let memo = if !self.remittance.unstructured.is_empty() {
+            self.remittance.unstructured.join(" ")
+        } else if !self.additional_info.is_empty() {
+            self.additional_info.clone()
+        } else {
+            counterparty_name.clone().unwrap_or_default()
+        };
+
+        let refnum = if !self.account_servicer_ref.is_empty() {
+            self.account_servicer_ref.clone()
+        } else if self.end_to_end_id != "NOTPROVIDED" {
+            self.end_to_end_id.clone()
+        } else {
+            String::new()
+        };
+
+        let date_posted = if !self.booking_date.is_empty() {
+            self.booking_date.clone()
+        } else {
+            self.value_date.clone()
+        };
+
+        Ok(TempTranFromXml {
+            transaction_type: String::from(if is_debit { "DEBIT" } else { "CREDIT" }),
+            date_posted,
+            transaction_amount: normalize_amount(&self.amount, is_debit)?,
+            refnum,
+            name: counterparty_name.unwrap_or_default(),
+            memo,
+            remittance: Some(self.remittance),
+        })
+    }
+}
+
+pub struct CamtParser;
+
+impl StatementParser for CamtParser {
+    fn name(&self) -> &'static str {
+        "camt"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xml", "053", "052"]
+    }
+
+    fn sniff(&self, bytes: &[u8]) -> Confidence {
+        if looks_like_camt(&as_text(bytes)) {
+            Confidence::High
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>> {
+        Ok(ParsedFile {
+            statements: parse_camt(&as_text(bytes))?,
+            ..ParsedFile::default()
+        })
+    }
+}
+
+pub fn looks_like_camt(file_content: &str) -> bool {
+    CAMT_MARKERS.iter().any(|m| file_content.contains(m))
+}
+
+// one file can carry several <Stmt>/<Rpt> blocks (one per account),
+// so this hands back one ParsedStatement for each
+pub fn parse_camt(file_content: &str) -> Result<Vec<ParsedStatement>, Box<dyn std::error::Error>> {
+    // text is trimmed once the element closes instead, since entity
+    // references split it into several events ("Coffee &amp; Co")
+    let mut x_reader = Reader::from_str(file_content);
+
+    let mut statements: Vec<ParsedStatement> = Vec::new();
+    let mut statement: Option<ParsedStatement> = None;
+    let mut balance: Option<CamtBalance> = None;
+    let mut entry: Option<CamtEntry> = None;
+
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        match x_reader.read_event()? {
+            Event::Start(e) => {
+                let name = String::from_utf8(e.local_name().as_ref().to_vec())?;
+                match name.as_str() {
+                    "Stmt" | "Rpt" => statement = Some(ParsedStatement::default()),
+                    "Bal" if statement.is_some() => balance = Some(CamtBalance::default()),
+                    "Ntry" if statement.is_some() => entry = Some(CamtEntry::default()),
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Text(e) => text.push_str(&e.decode()?),
+            Event::GeneralRef(e) => {
+                if let Some(c) = e.resolve_char_ref()? {
+                    text.push(c);
+                } else if let Some(resolved) = resolve_predefined_entity(&e.decode()?) {
+                    text.push_str(resolved);
+                }
+            }
+            Event::End(_) => {
+                let value = std::mem::take(&mut text);
+                let value = value.trim();
+                if let Some(ref mut n) = entry {
+                    read_entry_field(n, &path, value);
+                } else if let Some(ref mut b) = balance {
+                    read_balance_field(b, &path, value);
+                } else if let Some(ref mut s) = statement {
+                    read_statement_field(s, &path, value);
+                }
+
+                match path.pop().as_deref() {
+                    Some("Stmt") | Some("Rpt") => {
+                        if let Some(s) = statement.take() {
+                            statements.push(s);
+                        }
+                    }
+                    Some("Bal") => {
+                        if let (Some(b), Some(s)) = (balance.take(), statement.as_mut()) {
+                            apply_balance(s, b)?;
+                        }
+                    }
+                    Some("Ntry") => {
+                        if let (Some(n), Some(s)) = (entry.take(), statement.as_mut()) {
+                            // pending entries in a camt.052 will show up again once booked
+                            if n.status != "PDNG" {
+                                s.transactions.push(n.into_temp_tran()?);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if statements.is_empty() {
+        return Err("No Stmt or Rpt elements found in camt file".into());
+    }
+
+    Ok(statements)
+}
+
+fn read_statement_field(statement: &mut ParsedStatement, path: &[String], value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    if ends_with(path, &["Acct", "Id", "IBAN"]) || ends_with(path, &["Acct", "Id", "Othr", "Id"]) {
+        statement.account_id = Some(value.to_string());
+    } else if ends_with(path, &["Acct", "Ccy"]) {
+        statement.currency = Some(value.to_string());
+    } else if ends_with(path, &["FinInstnId", "BIC"]) || ends_with(path, &["FinInstnId", "BICFI"]) {
+        statement.bank_id = Some(value.to_string());
+    } else if ends_with(path, &["FrToDt", "FrDtTm"]) {
+        statement.period_start = parse_and_format_date(value).ok();
+    } else if ends_with(path, &["FrToDt", "ToDtTm"]) {
+        statement.period_end = parse_and_format_date(value).ok();
+    }
+}
+
+fn read_balance_field(balance: &mut CamtBalance, path: &[String], value: &str) {
+    if ends_with(path, &["Tp", "CdOrPrtry", "Cd"]) {
+        balance.code = value.to_string();
+    } else if ends_with(path, &["Bal", "Amt"]) {
+        balance.amount = value.to_string();
+    } else if ends_with(path, &["Bal", "CdtDbtInd"]) {
+        balance.credit_debit = value.to_string();
+    } else if ends_with(path, &["Bal", "Dt", "Dt"]) || ends_with(path, &["Bal", "Dt", "DtTm"]) {
+        balance.date = value.to_string();
+    }
+}
+
+fn read_entry_field(entry: &mut CamtEntry, path: &[String], value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    let value = value.to_string();
+    if ends_with(path, &["Ntry", "Amt"]) {
+        entry.amount = value;
+    } else if ends_with(path, &["Ntry", "CdtDbtInd"]) {
+        entry.credit_debit = value;
+    } else if ends_with(path, &["Ntry", "Sts"]) || ends_with(path, &["Ntry", "Sts", "Cd"]) {
+        entry.status = value;
+    } else if ends_with(path, &["BookgDt", "Dt"]) || ends_with(path, &["BookgDt", "DtTm"]) {
+        entry.booking_date = value;
+    } else if ends_with(path, &["ValDt", "Dt"]) || ends_with(path, &["ValDt", "DtTm"]) {
+        entry.value_date = value;
+    } else if ends_with(path, &["Ntry", "AcctSvcrRef"]) {
+        entry.account_servicer_ref = value;
+    } else if ends_with(path, &["Refs", "AcctSvcrRef"]) {
+        // older banks only put the reference on the transaction details
+        if entry.account_servicer_ref.is_empty() {
+            entry.account_servicer_ref = value;
+        }
+    } else if ends_with(path, &["Refs", "EndToEndId"]) {
+        entry.end_to_end_id = value;
+    } else if ends_with(path, &["RltdPties", "Dbtr", "Nm"])
+        || ends_with(path, &["RltdPties", "Dbtr", "Pty", "Nm"])
+    {
+        entry.debtor_name = value;
+    } else if ends_with(path, &["RltdPties", "Cdtr", "Nm"])
+        || ends_with(path, &["RltdPties", "Cdtr", "Pty", "Nm"])
+    {
+        entry.creditor_name = value;
+    } else if ends_with(path, &["DbtrAcct", "Id", "IBAN"]) {
+        entry.debtor_account = value;
+    } else if ends_with(path, &["CdtrAcct", "Id", "IBAN"]) {
+        entry.creditor_account = value;
+    } else if ends_with(path, &["RmtInf", "Ustrd"]) {
+        entry.remittance.unstructured.push(value);
+    } else if ends_with(path, &["CdtrRefInf", "Ref"]) {
+        entry.remittance.reference = Some(value);
+    } else if ends_with(path, &["Ntry", "AddtlNtryInf"]) {
+        entry.additional_info = value;
+    }
+}
+
+fn apply_balance(
+    statement: &mut ParsedStatement,
+    balance: CamtBalance,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let parsed = StatementBalance {
+        amount: normalize_amount(&balance.amount, balance.credit_debit == "DBIT")?,
+        date: parse_and_format_date(&balance.date).ok(),
+    };
+    match balance.code.as_str() {
+        // opening booked, falling back to previously closed booked
+        "OPBD" => statement.opening_balance = Some(parsed),
+        "PRCD" if statement.opening_balance.is_none() => statement.opening_balance = Some(parsed),
+        // closing booked, falling back to interim booked for camt.052
+        "CLBD" => statement.closing_balance = Some(parsed),
+        "ITBD" if statement.closing_balance.is_none() => statement.closing_balance = Some(parsed),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn ends_with(path: &[String], suffix: &[&str]) -> bool {
+    path.len() >= suffix.len()
+        && path[path.len() - suffix.len()..]
+            .iter()
+            .zip(suffix)
+            .all(|(p, s)| p == s)
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() { None } else { Some(value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMT_053_DATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr>
+      <MsgId>STMT-0001</MsgId>
+      <CreDtTm>2025-11-02T06:00:00</CreDtTm>
+    </GrpHdr>
+    <Stmt>
+      <Id>STMT-0001-1</Id>
+      <FrToDt>
+        <FrDtTm>2025-11-01T00:00:00</FrDtTm>
+        <ToDtTm>2025-11-01T23:59:59</ToDtTm>
+      </FrToDt>
+      <Acct>
+        <Id><IBAN>DE00123456780000000000</IBAN></Id>
+        <Ccy>EUR</Ccy>
+        <Svcr><FinInstnId><BIC>FAKEDEFFXXX</BIC></FinInstnId></Svcr>
+      </Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1000</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2025-10-31</Dt></Dt>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1189.5</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2025-11-01</Dt></Dt>
+      </Bal>
+      <Ntry>
+        <Amt Ccy="EUR">10.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2025-11-01</Dt></BookgDt>
+        <ValDt><Dt>2025-11-01</Dt></ValDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <NtryDtls>
+          <TxDtls>
+            <RltdPties>
+              <Cdtr><Nm>Coffee &amp; Co</Nm></Cdtr>
+              <CdtrAcct><Id><IBAN>DE00999999990000000000</IBAN></Id></CdtrAcct>
+            </RltdPties>
+            <RmtInf><Ustrd>Card payment 1234</Ustrd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">200</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2025-11-01</Dt></BookgDt>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><AcctSvcrRef>REF-2</AcctSvcrRef></Refs>
+            <RltdPties><Dbtr><Nm>Employer GmbH</Nm></Dbtr></RltdPties>
+            <RmtInf>
+              <Strd><CdtrRefInf><Ref>RF18539007547034</Ref></CdtrRefInf></Strd>
+            </RmtInf>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    const CAMT_052_DATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.052.001.02">
+  <BkToCstmrAcctRpt>
+    <Rpt>
+      <Acct><Id><Othr><Id>0000123</Id></Othr></Id></Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>ITBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">5.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Dt><DtTm>2025-11-01T12:00:00</DtTm></Dt>
+      </Bal>
+      <Ntry>
+        <Amt Ccy="EUR">1.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <ValDt><Dt>2025-11-01</Dt></ValDt>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">2.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2025-11-01</Dt></BookgDt>
+        <AddtlNtryInf>Bank fee</AddtlNtryInf>
+      </Ntry>
+    </Rpt>
+  </BkToCstmrAcctRpt>
+</Document>"#;
+
+    #[test]
+    fn test_parse_camt_053() {
+        assert!(looks_like_camt(CAMT_053_DATA));
+        let statements = parse_camt(CAMT_053_DATA).unwrap();
+        assert_eq!(statements.len(), 1);
+        let stmt = &statements[0];
+        assert_eq!(stmt.account_id.as_deref(), Some("DE00123456780000000000"));
+        assert_eq!(stmt.bank_id.as_deref(), Some("FAKEDEFFXXX"));
+        assert_eq!(stmt.currency.as_deref(), Some("EUR"));
+        assert_eq!(stmt.opening_balance.as_ref().unwrap().amount, "1000.00");
+        assert_eq!(stmt.closing_balance.as_ref().unwrap().amount, "1189.50");
+        assert_eq!(stmt.transactions.len(), 2);
+
+        let first = &stmt.transactions[0];
+        assert_eq!(first.transaction_type, "DEBIT");
+        assert_eq!(first.transaction_amount, "-10.50");
+        assert_eq!(first.refnum, "REF-1");
+        assert_eq!(first.name, "Coffee & Co");
+        assert_eq!(first.memo, "Card payment 1234");
+        let remittance = first.remittance.as_ref().unwrap();
+        assert_eq!(
+            remittance.counterparty_account.as_deref(),
+            Some("DE00999999990000000000")
+        );
+
+        let second = &stmt.transactions[1];
+        assert_eq!(second.transaction_type, "CREDIT");
+        assert_eq!(second.transaction_amount, "200.00");
+        assert_eq!(second.refnum, "REF-2");
+        assert_eq!(second.memo, "Employer GmbH");
+        assert_eq!(
+            second.remittance.as_ref().unwrap().reference.as_deref(),
+            Some("RF18539007547034")
+        );
+        assert_eq!(second.to_transport().amount, 20000);
+    }
+
+    #[test]
+    fn test_parse_camt_052_skips_pending() {
+        let statements = parse_camt(CAMT_052_DATA).unwrap();
+        let stmt = &statements[0];
+        assert_eq!(stmt.account_id.as_deref(), Some("0000123"));
+        assert_eq!(stmt.closing_balance.as_ref().unwrap().amount, "-5.00");
+        assert_eq!(stmt.transactions.len(), 1);
+        assert_eq!(stmt.transactions[0].memo, "Bank fee");
+    }
+
+    #[test]
+    fn test_parse_camt_rejects_ofx() {
+        let res = parse_camt("<OFX><BANKMSGSRSV1></BANKMSGSRSV1></OFX>");
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,315 @@
+// statements that arrive by email: a local Maildir (or a folder of .eml
+// files) is searched for messages from the bank, and their attachments
+// go through the same parsers as files in a [[directory]]
+use crate::ingestion::Directory;
+use crate::utils::archiver::AfterUpload;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+// kept in the state dir, see utils::paths
+pub const PROCESSED_MESSAGES_FILE: &str = "processed-messages.txt";
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Mailbox {
+    // institution the statements belong to, same names as [[directory]]
+    pub name: String,
+    // a Maildir (with cur/ and new/) or a plain folder of .eml files
+    pub path: String,
+    // substrings of the sender address, any of which will do. empty means anyone
+    #[serde(default)]
+    pub from: Vec<String>,
+    // substrings of the subject, any of which will do. empty means any subject
+    #[serde(default)]
+    pub subject: Vec<String>,
+    // globs on the attachment's file name
+    #[serde(default = "default_attachments")]
+    pub attachments: Vec<String>,
+    pub parser: Option<String>,
+}
+
+fn default_attachments() -> Vec<String> {
+    [
+        "*.ofx", "*.qfx", "*.qbo", "*.xml", "*.sta", "*.mt940", "*.940",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+pub struct Attachment {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+pub struct MailMessage {
+    pub id: String,
+    pub file_path: PathBuf,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Mailbox {
+    // batches from a mailbox are grouped like a directory's, but the
+    // message files themselves are never moved
+    pub fn as_directory(&self) -> Directory {
+        Directory {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            parser: self.parser.clone(),
+            after_upload: AfterUpload::Leave,
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        fs::canonicalize(&self.path)
+            .map(|root| path.starts_with(root) && is_message_file(path))
+            .unwrap_or(false)
+    }
+
+    // every message file in the mailbox, in a stable order
+    pub fn message_files(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let root = fs::canonicalize(&self.path)?;
+        let maildir = root.join("cur").is_dir() || root.join("new").is_dir();
+        let folders = if maildir {
+            vec![root.join("cur"), root.join("new")]
+        } else {
+            vec![root]
+        };
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        for folder in folders.iter().filter(|f| f.is_dir()) {
+            for entry in fs::read_dir(folder)? {
+                let path = entry?.path();
+                // maildir file names carry no extension, a loose folder needs .eml
+                if path.is_file() && (maildir || is_message_file(&path)) {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    // messages that pass the sender/subject filters and haven't been
+    // processed before, with only the attachments worth parsing
+    pub fn unprocessed_messages(
+        &self,
+        processed: &HashSet<String>,
+    ) -> Result<Vec<MailMessage>, Box<dyn std::error::Error>> {
+        let attachment_globs = build_glob_set(&self.attachments)?;
+        let mut messages = Vec::new();
+        for file_path in self.message_files()? {
+            let bytes = fs::read(&file_path)?;
+            if let Some(message) = self.read_message(&file_path, &bytes, &attachment_globs)
+                && !processed.contains(&message.id)
+            {
+                messages.push(message);
+            }
+        }
+        Ok(messages)
+    }
+
+    fn read_message(
+        &self,
+        file_path: &Path,
+        bytes: &[u8],
+        attachment_globs: &GlobSet,
+    ) -> Option<MailMessage> {
+        let message = MessageParser::default().parse(bytes)?;
+        let sender = message
+            .from()
+            .and_then(|a| a.first())
+            .and_then(|a| a.address())
+            .unwrap_or_default();
+        if !matches_any(sender, &self.from)
+            || !matches_any(message.subject().unwrap_or_default(), &self.subject)
+        {
+            return None;
+        }
+
+        let attachments: Vec<Attachment> = message
+            .attachments()
+            .filter_map(|part| {
+                let name = part.attachment_name()?;
+                attachment_globs
+                    .is_match(name.to_lowercase())
+                    .then(|| Attachment {
+                        name: name.to_string(),
+                        bytes: part.contents().to_vec(),
+                    })
+            })
+            .collect();
+        if attachments.is_empty() {
+            return None;
+        }
+
+        // every sane mailer sets one, but fall back to the file name
+        let id = message
+            .message_id()
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| file_path.to_string_lossy().to_string());
+        Some(MailMessage {
+            id,
+            file_path: file_path.to_path_buf(),
+            attachments,
+        })
+    }
+}
+
+fn is_message_file(path: &Path) -> bool {
+    let in_maildir = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n == "cur" || n == "new")
+        .unwrap_or(false);
+    let is_eml = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("eml"))
+        .unwrap_or(false);
+    in_maildir || is_eml
+}
+
+fn matches_any(value: &str, filters: &[String]) -> bool {
+    let value = value.to_lowercase();
+    filters.is_empty() || filters.iter().any(|f| value.contains(&f.to_lowercase()))
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, Box<dyn std::error::Error>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(&pattern.to_lowercase())?);
+    }
+    Ok(builder.build()?)
+}
+
+pub fn get_processed_messages(
+    path: impl AsRef<Path>,
+) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().map(|s| s.to_string()).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn add_processed_messages(
+    path: impl AsRef<Path>,
+    message_ids: &HashSet<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let mut processed = get_processed_messages(path)?;
+    processed.extend(message_ids.iter().cloned());
+    let mut lines: Vec<&str> = processed.iter().map(|s| s.as_str()).collect();
+    lines.sort();
+    fs::write(path, lines.join("\n"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT_EMAIL: &str = "From: Chase Alerts <no-reply@alerts.chase.com>\r
+To: me@example.com\r
+Subject: Your November statement is ready\r
+Message-ID: <stmt-2025-11@chase.com>\r
+MIME-Version: 1.0\r
+Content-Type: multipart/mixed; boundary=\"XYZ\"\r
+\r
+--XYZ\r
+Content-Type: text/plain\r
+\r
+Your statement is attached.\r
+--XYZ\r
+Content-Type: application/pdf\r
+Content-Disposition: attachment; filename=\"statement.pdf\"\r
+Content-Transfer-Encoding: base64\r
+\r
+JVBERi0xLjQK\r
+--XYZ\r
+Content-Type: application/x-ofx\r
+Content-Disposition: attachment; filename=\"Statement.OFX\"\r
+\r
+<OFX><STMTTRN></STMTTRN></OFX>\r
+--XYZ--\r
+";
+
+    const NEWSLETTER_EMAIL: &str = "From: news@chase.com\r
+Subject: Earn more points\r
+Message-ID: <news-1@chase.com>\r
+Content-Type: text/plain\r
+\r
+Nothing to see here.\r
+";
+
+    fn mailbox(path: &Path) -> Mailbox {
+        Mailbox {
+            name: String::from("chase"),
+            path: path.to_string_lossy().to_string(),
+            from: vec![String::from("alerts.chase.com")],
+            subject: vec![String::from("statement")],
+            attachments: default_attachments(),
+            parser: None,
+        }
+    }
+
+    #[test]
+    fn test_reads_matching_maildir_messages() {
+        let dir = std::env::temp_dir().join("moneyd-maildir-test");
+        let _ = fs::remove_dir_all(&dir);
+        for sub in ["cur", "new", "tmp"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        fs::write(dir.join("cur/1700000000.M1.host:2,S"), STATEMENT_EMAIL).unwrap();
+        fs::write(dir.join("new/1700000001.M2.host"), NEWSLETTER_EMAIL).unwrap();
+        // tmp/ holds messages still being delivered
+        fs::write(dir.join("tmp/1700000002.M3.host"), STATEMENT_EMAIL).unwrap();
+
+        let mailbox = mailbox(&dir);
+        assert_eq!(mailbox.message_files().unwrap().len(), 2);
+
+        let messages = mailbox.unprocessed_messages(&HashSet::new()).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "stmt-2025-11@chase.com");
+        assert_eq!(messages[0].attachments.len(), 1);
+        assert_eq!(messages[0].attachments[0].name, "Statement.OFX");
+        assert!(String::from_utf8_lossy(&messages[0].attachments[0].bytes).starts_with("<OFX>"));
+
+        let processed = HashSet::from([String::from("stmt-2025-11@chase.com")]);
+        assert!(mailbox.unprocessed_messages(&processed).unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_eml_folder_and_processed_ledger() {
+        let dir = std::env::temp_dir().join("moneyd-eml-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("november.eml"), STATEMENT_EMAIL).unwrap();
+        fs::write(dir.join("notes.txt"), "not an email").unwrap();
+
+        let mailbox = mailbox(&dir);
+        assert_eq!(
+            mailbox.message_files().unwrap(),
+            vec![fs::canonicalize(&dir).unwrap().join("november.eml")]
+        );
+
+        let ledger = dir.join("processed.txt");
+        let ledger = ledger.to_str().unwrap();
+        assert!(get_processed_messages(ledger).unwrap().is_empty());
+        add_processed_messages(ledger, &HashSet::from([String::from("a@b")])).unwrap();
+        add_processed_messages(ledger, &HashSet::from([String::from("a@b")])).unwrap();
+        assert_eq!(get_processed_messages(ledger).unwrap().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
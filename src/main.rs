@@ -1,88 +1,371 @@
 use crate::{
-    analyzer::analyze::analyze_data,
-    service::loginservice::login,
-    ui::loginwindow::build_login_window,
+    commands::{AnalysisQuery, IngestSource, StatementEdit, TransactionEdit, TransactionQuery},
     utils::{
-        globalutil::{AuthorizationData, get_env_vars, post_statements_and_transactions},
+        globalutil::get_env_vars,
+        logging::{ConsoleLog, init_logging},
         logintransporter::LoginRequest,
+        output::{OutputFormat, set_verbosity},
+        paths::config_dir,
     },
 };
 
-use clap::Parser;
-use cursive::CursiveRunnable;
+use clap::{ArgAction, Parser, Subcommand};
 use dotenv::dotenv;
+use std::path::PathBuf;
 
+mod accounts;
 mod analyzer;
+mod commands;
+mod csvstatement;
+mod daemon;
+mod doctor;
 mod ingestion;
+mod iso20022;
+mod mailbox;
+mod parsing;
 mod quickbooks;
 mod service;
+mod swift;
 mod ui;
 mod utils;
 
 struct Env {
     api_key: Option<String>,
     base_url: Option<String>,
+    // skip the login window when both are set. the daemon
+    // has nobody to type them in, so it needs them
+    email: Option<String>,
+    password: Option<String>,
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(short = 'i', long = "ingest")]
-    ingest: bool,
-    #[arg(short = 'a', long = "analyze")]
-    analyze: bool,
-    #[arg(short = 't', long = "test")]
-    test: bool,
+    /// Path to moneyd-config.toml. Without it, $MONEYD_CONFIG, then
+    /// $XDG_CONFIG_HOME/moneyd/moneyd-config.toml (~/.config/moneyd/),
+    /// then ./config/moneyd-config.toml are tried
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// How query results are printed
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+    /// Log more: -v for debug (which parser read each file, account numbers
+    /// and amounts unmasked), -vv for trace. MONEYD_LOG overrides the filter
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Only print results and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also write the log to this file, one JSON object per line
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check your credentials against the backend
+    Login,
+    /// Parse statements and upload any new transactions
+    Ingest {
+        /// Only this file or folder among the configured sources, or - for stdin
+        path: Option<PathBuf>,
+        /// A statement file from anywhere, outside the configured sources
+        #[arg(long, conflicts_with = "path")]
+        file: Option<PathBuf>,
+        /// Only sources and statements for this institution. with --file
+        /// or stdin, the institution the statement belongs to
+        #[arg(long)]
+        institution: Option<String>,
+        /// Skip content sniffing and use this parser, for --file and stdin
+        #[arg(long)]
+        parser: Option<String>,
+        /// Look over the new transactions before they're uploaded: leave
+        /// some out, fix descriptions, or skip files until next time
+        #[arg(long)]
+        review: bool,
+    },
+    /// Totals per institution and across all of them
+    Analyze {
+        #[command(flatten)]
+        selection: SelectionArgs,
+    },
+    /// Debits, credits, net and top merchants per month, with the change
+    /// from the month before
+    Monthly {
+        #[command(flatten)]
+        selection: SelectionArgs,
+        /// How many merchants to list per month
+        #[arg(long, default_value_t = 3)]
+        top: usize,
+    },
+    /// Uploaded statements
+    Statements {
+        #[command(subcommand)]
+        command: StatementsCommand,
+    },
+    /// Uploaded transactions
+    Transactions {
+        #[command(subcommand)]
+        command: TransactionsCommand,
+    },
+    /// Inspect moneyd-config.toml
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Create the config and .env step by step
+    Setup,
+    /// Check the config, state files, backend and credentials, with hints on what to fix
+    Doctor,
+    /// Watch every configured directory and ingest new statements as they arrive
+    Daemon,
+    /// Log in once and ingest, review, browse and report from one full-screen session
+    Dashboard,
+}
+
+// which transactions analyze and monthly look at, all of them by default
+#[derive(clap::Args, Debug)]
+struct SelectionArgs {
+    /// Only this institution, can be given more than once
+    #[arg(long)]
+    institution: Vec<String>,
+    /// Only this account, by name or account number (the last four
+    /// digits are enough), can be given more than once
+    #[arg(long)]
+    account: Vec<String>,
+    /// Only transactions on or after this date, like 2025-11-01
+    #[arg(long)]
+    from: Option<String>,
+    /// Only transactions on or before this date
+    #[arg(long)]
+    to: Option<String>,
+}
+
+impl SelectionArgs {
+    fn query(&self) -> AnalysisQuery<'_> {
+        AnalysisQuery {
+            institutions: &self.institution,
+            accounts: &self.account,
+            from: self.from.as_deref(),
+            to: self.to.as_deref(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum StatementsCommand {
+    /// Every statement uploaded so far
+    List,
+    /// One statement and its transactions
+    Show { id: i32 },
+    /// Fix a statement's account or period
+    Edit {
+        id: i32,
+        /// The backend id of the account it belongs to
+        #[arg(long)]
+        account: Option<i32>,
+        /// The first day it covers, like 2025-11-01
+        #[arg(long)]
+        period_start: Option<String>,
+        /// The last day it covers
+        #[arg(long)]
+        period_end: Option<String>,
+    },
+    /// Delete a statement and its transactions
+    Delete {
+        id: i32,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TransactionsCommand {
+    /// Most recent transactions first
+    List {
+        #[arg(long)]
+        institution: Option<String>,
+        #[arg(long)]
+        statement: Option<i32>,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Transactions whose description contains QUERY
+    Search {
+        query: String,
+        #[arg(long)]
+        institution: Option<String>,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Fix a transaction's description, category or amount
+    Edit {
+        id: i32,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        category: Option<String>,
+        /// The corrected amount, negative like -12.50 for money going out
+        /// and positive for money coming in. The sign sets debit or credit
+        #[arg(long, allow_hyphen_values = true)]
+        amount: Option<String>,
+    },
+    /// Delete a transaction
+    Delete {
+        id: i32,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Check the config for missing folders, unknown institutions and parsers
+    Check,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    // a .env next to where moneyd runs wins over the one in the config dir
     dotenv().ok();
-    // if args.test {
-    //     println!("Test is true!");
-    //     //parse_test();
-    //     parse();
-    //     return Ok(());
-    // }
+    dotenv::from_path(config_dir().join(".env")).ok();
+    let verbosity = if args.quiet { -1 } else { args.verbose as i8 };
+    set_verbosity(verbosity);
+    init_logging(
+        verbosity,
+        args.log_file.as_deref(),
+        match args.command {
+            Command::Daemon => ConsoleLog::Timestamped,
+            Command::Dashboard => ConsoleLog::Off,
+            _ => ConsoleLog::Plain,
+        },
+    )?;
     let env_vars = get_env_vars();
+    let config_path = args.config.as_deref();
 
-    let mut siv = build_login_window();
-    siv.run();
-    let login_request = get_user_data_from_cursive(&mut siv);
-
-    let api_key = &env_vars.api_key.expect("API_KEY not set. Panicking.");
-    let mut auth_token: String = String::new();
-    let login_res = login(login_request, api_key).await;
-
-    auth_token = login_res.token.clone();
-    let auth_data: AuthorizationData = AuthorizationData {
-        auth_token: auth_token,
-        api_key: api_key.clone(),
-    };
-    println!("captured login info...");
-
-    if args.ingest {
-        // login handled, begin ingestion
-        let ingestion_res = ingestion::ingestinator()?;
-
-        post_statements_and_transactions(ingestion_res, &login_res, &auth_data)
+    match args.command {
+        Command::Login => commands::login(&env_vars, args.output).await,
+        Command::Ingest {
+            path,
+            file,
+            institution,
+            parser,
+            review,
+        } => {
+            let config = ingestion::load_config(config_path)?;
+            let source = match (path, file) {
+                (Some(path), _) if path.as_os_str() == "-" => IngestSource::Stdin,
+                (_, Some(file)) => IngestSource::File(file),
+                (Some(path), None) => IngestSource::Configured(Some(path)),
+                (None, None) => IngestSource::Configured(None),
+            };
+            commands::ingest(
+                &env_vars,
+                args.output,
+                config,
+                source,
+                institution.as_deref(),
+                parser.as_deref(),
+                review,
+            )
             .await
-            .unwrap();
-
-        println!("Execution successful. Data uploaded.");
-    }
-    if args.analyze {
-        analyze_data(&auth_data, &1, &login_res.user.id).await;
+        }
+        Command::Analyze { selection } => {
+            commands::analyze(&env_vars, args.output, &selection.query()).await
+        }
+        Command::Monthly { selection, top } => {
+            commands::monthly(&env_vars, args.output, &selection.query(), top).await
+        }
+        Command::Statements { command } => match command {
+            StatementsCommand::List => commands::list_statements(&env_vars, args.output).await,
+            StatementsCommand::Show { id } => {
+                commands::show_statement(&env_vars, args.output, id).await
+            }
+            StatementsCommand::Edit {
+                id,
+                account,
+                period_start,
+                period_end,
+            } => {
+                let edit = StatementEdit {
+                    account_id: account,
+                    period_start: period_start.as_deref(),
+                    period_end: period_end.as_deref(),
+                };
+                commands::edit_statement(&env_vars, args.output, id, &edit).await
+            }
+            StatementsCommand::Delete { id, yes } => {
+                commands::remove_statement(&env_vars, args.output, id, yes).await
+            }
+        },
+        Command::Transactions { command } => match command {
+            TransactionsCommand::List {
+                institution,
+                statement,
+                limit,
+            } => {
+                let query = TransactionQuery {
+                    institution: institution.as_deref(),
+                    statement_id: statement,
+                    search: None,
+                    limit,
+                };
+                commands::list_transactions(&env_vars, args.output, &query).await
+            }
+            TransactionsCommand::Search {
+                query,
+                institution,
+                limit,
+            } => {
+                let query = TransactionQuery {
+                    institution: institution.as_deref(),
+                    statement_id: None,
+                    search: Some(&query),
+                    limit,
+                };
+                commands::list_transactions(&env_vars, args.output, &query).await
+            }
+            TransactionsCommand::Edit {
+                id,
+                description,
+                category,
+                amount,
+            } => {
+                let edit = TransactionEdit {
+                    description: description.as_deref(),
+                    category: category.as_deref(),
+                    amount: amount.as_deref(),
+                };
+                commands::edit_transaction(&env_vars, args.output, id, &edit).await
+            }
+            TransactionsCommand::Delete { id, yes } => {
+                commands::remove_transaction(&env_vars, args.output, id, yes).await
+            }
+        },
+        Command::Config {
+            command: ConfigCommand::Check,
+        } => commands::check_config(args.output, &ingestion::load_config(config_path)?),
+        Command::Setup => commands::setup(&env_vars, config_path),
+        Command::Doctor => doctor::doctor(&env_vars, args.output, config_path).await,
+        Command::Dashboard => commands::dashboard(&env_vars, config_path),
+        Command::Daemon => {
+            let config = ingestion::load_config(config_path)?;
+            let api_key = commands::api_key(&env_vars)?;
+            let base_url = commands::base_url(&env_vars);
+            let credentials = LoginRequest {
+                email: env_vars
+                    .email
+                    .ok_or("MONEYD_EMAIL must be set to run as a daemon.")?,
+                password: env_vars
+                    .password
+                    .ok_or("MONEYD_PASSWORD must be set to run as a daemon.")?,
+            };
+            daemon::run_daemon(config, credentials, &base_url, &api_key).await
+        }
     }
-    Ok(())
-}
-
-fn get_user_data_from_cursive(siv: &mut CursiveRunnable) -> LoginRequest {
-    let user_data: LoginRequest = siv.take_user_data().unwrap();
-    let login_request: LoginRequest = LoginRequest {
-        email: user_data.email,
-        password: user_data.password,
-    };
-    login_request
 }
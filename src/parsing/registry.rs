@@ -0,0 +1,280 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::csvstatement::parser::CsvParser;
+use crate::iso20022::parser::CamtParser;
+use crate::parsing::statementparser::{Confidence, ParsedFile, StatementParser};
+use crate::quickbooks::investment::OfxInvestmentParser;
+use crate::quickbooks::parser::{OfxSgmlParser, OfxXmlParser};
+use crate::swift::parser::Mt940Parser;
+
+pub struct ParserRegistry {
+    parsers: Vec<Box<dyn StatementParser>>,
+}
+
+#[derive(Debug)]
+pub struct Rejection {
+    pub parser: &'static str,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct ParserReport {
+    pub parser: &'static str,
+    pub reason: String,
+    pub rejected: Vec<Rejection>,
+}
+
+pub struct ParseOutcome {
+    pub parsed: ParsedFile,
+    pub report: ParserReport,
+}
+
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub file_name: String,
+    pub rejected: Vec<Rejection>,
+}
+
+impl fmt::Display for ParseFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No parser could read {}", self.file_name)?;
+        for r in self.rejected.iter() {
+            write!(f, "\n  {}: {}", r.parser, r.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseFailure {}
+
+impl fmt::Display for ParserReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "parsed with {} ({})", self.parser, self.reason)?;
+        for r in self.rejected.iter() {
+            write!(f, "\n  skipped {}: {}", r.parser, r.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl ParserRegistry {
+    pub fn new() -> Self {
+        ParserRegistry {
+            parsers: Vec::new(),
+        }
+    }
+
+    // registration order breaks ties between parsers that are equally sure.
+    // investment comes first: brokerage files are also OFX, and the bank
+    // parsers would happily return just their cash transactions
+    pub fn with_defaults() -> Self {
+        let mut registry = ParserRegistry::new();
+        registry.register(Box::new(OfxInvestmentParser));
+        registry.register(Box::new(OfxXmlParser));
+        registry.register(Box::new(OfxSgmlParser));
+        registry.register(Box::new(CamtParser));
+        registry.register(Box::new(Mt940Parser));
+        registry.register(Box::new(CsvParser));
+        registry
+    }
+
+    pub fn register(&mut self, parser: Box<dyn StatementParser>) {
+        self.parsers.push(parser);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.parsers.iter().map(|p| p.name()).collect()
+    }
+
+    // try every parser that recognises the content, most confident first.
+    // a matching file extension only breaks ties, since banks are not
+    // careful about naming (camt as .txt, ofx as .xml...)
+    pub fn parse_file(
+        &self,
+        bytes: &[u8],
+        file_name: &str,
+        forced_parser: Option<&str>,
+    ) -> Result<ParseOutcome, Box<dyn std::error::Error>> {
+        if let Some(forced) = forced_parser {
+            return self.parse_with(bytes, file_name, forced);
+        }
+
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+
+        let mut rejected: Vec<Rejection> = Vec::new();
+        let mut candidates: Vec<(Confidence, bool, &dyn StatementParser)> = Vec::new();
+        for parser in self.parsers.iter() {
+            let confidence = parser.sniff(bytes);
+            if confidence == Confidence::None {
+                rejected.push(Rejection {
+                    parser: parser.name(),
+                    reason: String::from("content not recognised"),
+                });
+                continue;
+            }
+            let extension_match = parser.extensions().contains(&extension.as_str());
+            candidates.push((confidence, extension_match, parser.as_ref()));
+        }
+        // stable, so registration order survives within a tie
+        candidates.sort_by_key(|c| std::cmp::Reverse((c.0, c.1)));
+
+        for (confidence, extension_match, parser) in candidates {
+            match parser.parse(bytes) {
+                Ok(parsed) if !parsed.is_empty() => {
+                    let reason = if extension_match {
+                        format!("{:?} confidence, .{} extension", confidence, extension)
+                    } else {
+                        format!("{:?} confidence", confidence)
+                    };
+                    return Ok(ParseOutcome {
+                        parsed,
+                        report: ParserReport {
+                            parser: parser.name(),
+                            reason,
+                            rejected,
+                        },
+                    });
+                }
+                Ok(_) => rejected.push(Rejection {
+                    parser: parser.name(),
+                    reason: String::from("parsed, but found no statement"),
+                }),
+                Err(e) => rejected.push(Rejection {
+                    parser: parser.name(),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        Err(Box::new(ParseFailure {
+            file_name: file_name.to_string(),
+            rejected,
+        }))
+    }
+
+    fn parse_with(
+        &self,
+        bytes: &[u8],
+        file_name: &str,
+        parser_name: &str,
+    ) -> Result<ParseOutcome, Box<dyn std::error::Error>> {
+        let parser = self
+            .parsers
+            .iter()
+            .find(|p| p.name() == parser_name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown parser '{}' configured for {}, expected one of: {}",
+                    parser_name,
+                    file_name,
+                    self.names().join(", ")
+                )
+            })?;
+
+        let failure = |reason: String| ParseFailure {
+            file_name: file_name.to_string(),
+            rejected: vec![Rejection {
+                parser: parser.name(),
+                reason,
+            }],
+        };
+        let parsed = parser.parse(bytes).map_err(|e| failure(e.to_string()))?;
+        // same as when sniffing, reading nothing isn't a success
+        if parsed.is_empty() {
+            return Err(failure(String::from("parsed, but found no statement")).into());
+        }
+
+        Ok(ParseOutcome {
+            parsed,
+            report: ParserReport {
+                parser: parser.name(),
+                reason: String::from("forced by directory config"),
+                rejected: Vec::new(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMT_AS_TXT: &str = r#"<?xml version="1.0"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02"><BkToCstmrStmt><Stmt>
+<Acct><Id><IBAN>DE00</IBAN></Id></Acct>
+<Ntry><Amt Ccy="EUR">1.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><BookgDt><Dt>2025-11-01</Dt></BookgDt></Ntry>
+</Stmt></BkToCstmrStmt></Document>"#;
+
+    const SGML_OFX: &str = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST><STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20251129120000<TRNAMT>-0.92<FITID>1<NAME>Test</STMTTRN></BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+
+    #[test]
+    fn test_registry_picks_by_content() {
+        let registry = ParserRegistry::with_defaults();
+        let outcome = registry
+            .parse_file(CAMT_AS_TXT.as_bytes(), "statement.txt", None)
+            .unwrap();
+        assert_eq!(outcome.report.parser, "camt");
+        assert_eq!(outcome.parsed.statements[0].transactions.len(), 1);
+        assert!(
+            outcome
+                .report
+                .rejected
+                .iter()
+                .any(|r| r.parser == "mt940" && r.reason == "content not recognised")
+        );
+
+        let outcome = registry
+            .parse_file(SGML_OFX.as_bytes(), "statement.qfx", None)
+            .unwrap();
+        assert_eq!(outcome.report.parser, "ofx-sgml");
+        assert!(outcome.report.reason.contains(".qfx"));
+    }
+
+    #[test]
+    fn test_registry_accepts_statement_without_activity() {
+        let quiet = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKACCTFROM><ACCTID>1234</BANKACCTFROM><BANKTRANLIST><DTSTART>20251101<DTEND>20251130</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let registry = ParserRegistry::with_defaults();
+        let outcome = registry
+            .parse_file(quiet.as_bytes(), "statement.qfx", None)
+            .unwrap();
+        assert_eq!(outcome.report.parser, "ofx-sgml");
+        let statement = &outcome.parsed.statements[0];
+        assert!(statement.transactions.is_empty());
+        assert_eq!(statement.account_id.as_deref(), Some("1234"));
+    }
+
+    #[test]
+    fn test_registry_forced_parser() {
+        let registry = ParserRegistry::with_defaults();
+        let res = registry.parse_file(SGML_OFX.as_bytes(), "statement.qfx", Some("camt"));
+        assert!(res.is_err());
+
+        let res = registry.parse_file(SGML_OFX.as_bytes(), "statement.qfx", Some("nope"));
+        assert!(res.err().unwrap().to_string().contains("Unknown parser"));
+
+        let res = registry.parse_file(b"just some notes", "notes.qfx", Some("ofx-sgml"));
+        assert!(
+            res.err()
+                .unwrap()
+                .to_string()
+                .contains("found no statement")
+        );
+    }
+
+    #[test]
+    fn test_registry_reports_unreadable_files() {
+        let registry = ParserRegistry::with_defaults();
+        let err = registry
+            .parse_file(b"just some notes", "notes.txt", None)
+            .err()
+            .unwrap();
+        let message = err.to_string();
+        assert!(message.contains("notes.txt"));
+        assert!(message.contains("ofx-xml: content not recognised"));
+    }
+}
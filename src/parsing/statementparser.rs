@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+use crate::quickbooks::investment::InvestmentStatement;
+use crate::utils::statementmodel::ParsedStatement;
+
+// how sure a parser is that it can read a file, judged from its content alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    pub statements: Vec<ParsedStatement>,
+    pub investments: Vec<InvestmentStatement>,
+}
+
+impl ParsedFile {
+    // nothing was read at all. a statement that names its account or
+    // period but had no activity still counts, it's a valid statement
+    pub fn is_empty(&self) -> bool {
+        self.statements.iter().all(|s| s.is_blank())
+            && self
+                .investments
+                .iter()
+                .all(|i| i.transactions.is_empty() && i.positions.is_empty())
+    }
+}
+
+pub trait StatementParser {
+    // short stable name, used in config (`parser = "..."`) and in reports
+    fn name(&self) -> &'static str;
+    // lowercase, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+    fn sniff(&self, bytes: &[u8]) -> Confidence;
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>>;
+}
+
+// bank files are mostly ascii, but the odd cp1252 byte
+// shouldn't make the whole file unreadable
+pub fn as_text(bytes: &[u8]) -> Cow<'_, str> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes)
+}
@@ -0,0 +1,487 @@
+use crate::parsing::statementparser::{Confidence, ParsedFile, StatementParser, as_text};
+use crate::quickbooks::parser::{OfxSgmlParser, OfxXmlParser};
+use crate::utils::globalutil::parse_ofx_date;
+use crate::utils::statementmodel::ParsedStatement;
+
+// brokerage statements (INVSTMTMSGSRSV1). the bank parsers only look for
+// STMTTRN, so without this they'd either find nothing or just the cash legs
+
+const TRANSACTION_TAGS: [&str; 15] = [
+    "BUYSTOCK",
+    "SELLSTOCK",
+    "BUYMF",
+    "SELLMF",
+    "BUYDEBT",
+    "SELLDEBT",
+    "BUYOPT",
+    "SELLOPT",
+    "BUYOTHER",
+    "SELLOTHER",
+    "INCOME",
+    "REINVEST",
+    "TRANSFER",
+    "INVEXPENSE",
+    "INVBANKTRAN",
+];
+const POSITION_TAGS: [&str; 5] = ["POSSTOCK", "POSMF", "POSDEBT", "POSOPT", "POSOTHER"];
+const SECURITY_TAGS: [&str; 5] = ["STOCKINFO", "MFINFO", "DEBTINFO", "OPTINFO", "OTHERINFO"];
+
+#[derive(Debug, Default)]
+pub struct InvestmentStatement {
+    pub broker_id: Option<String>,
+    pub account_id: Option<String>,
+    pub currency: Option<String>,
+    pub as_of: Option<String>,
+    pub period_start: Option<String>,
+    pub period_end: Option<String>,
+    pub transactions: Vec<InvestmentTransaction>,
+    pub positions: Vec<Position>,
+    pub securities: Vec<Security>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct InvestmentTransaction {
+    // the OFX aggregate name: BUYSTOCK, INCOME, REINVEST...
+    pub kind: String,
+    pub fitid: String,
+    pub trade_date: String,
+    pub settle_date: String,
+    pub memo: String,
+    pub security_id: String,
+    pub security_id_type: String,
+    pub units: String,
+    pub unit_price: String,
+    pub commission: String,
+    pub fees: String,
+    pub total: String,
+    pub income_type: String,
+    // where the security is held, and where the cash for it came from
+    // or went to. CASH, MARGIN, SHORT or OTHER, and they can differ
+    pub sub_account_security: String,
+    pub sub_account_fund: String,
+    // BUY/SELL/BUYTOCOVER, IN/OUT for transfers
+    pub action: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Position {
+    pub kind: String,
+    pub security_id: String,
+    pub security_id_type: String,
+    pub held_in: String,
+    pub position_type: String,
+    pub units: String,
+    pub unit_price: String,
+    pub market_value: String,
+    pub price_date: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Security {
+    pub kind: String,
+    pub security_id: String,
+    // CUSIP, ISIN...
+    pub security_id_type: String,
+    pub name: String,
+    pub ticker: String,
+}
+
+impl InvestmentStatement {
+    pub fn security(&self, security_id: &str) -> Option<&Security> {
+        self.securities
+            .iter()
+            .find(|s| s.security_id == security_id)
+    }
+}
+
+pub struct OfxInvestmentParser;
+
+impl StatementParser for OfxInvestmentParser {
+    fn name(&self) -> &'static str {
+        "ofx-investment"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ofx", "qfx"]
+    }
+
+    fn sniff(&self, bytes: &[u8]) -> Confidence {
+        if as_text(bytes).contains("INVSTMTMSGSRSV1") {
+            Confidence::High
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>> {
+        let text = as_text(bytes);
+        let investments = parse_investment_statements(&text);
+        let statements = bank_statements(&text)?;
+        if investments.is_empty() && statements.is_empty() {
+            return Err("No INVSTMTRS found in OFX file".into());
+        }
+        Ok(ParsedFile {
+            statements,
+            investments,
+        })
+    }
+}
+
+// one download can hold a checking or card statement next to the brokerage
+// one. that part goes through the bank parsers, with the investment part cut
+// out first so its INVBANKTRAN cash legs aren't read as bank transactions
+fn bank_statements(text: &str) -> Result<Vec<ParsedStatement>, Box<dyn std::error::Error>> {
+    if !text.contains("<STMTRS>") && !text.contains("<CCSTMTRS>") {
+        return Ok(Vec::new());
+    }
+    let start = text.find("<INVSTMTMSGSRSV1>").unwrap_or(text.len());
+    let end = text
+        .find("</INVSTMTMSGSRSV1>")
+        .map(|i| i + "</INVSTMTMSGSRSV1>".len())
+        .unwrap_or(text.len())
+        .max(start);
+    let bank = format!("{}{}", &text[..start], &text[end..]);
+    let parsed = match OfxXmlParser.parse(bank.as_bytes()) {
+        Ok(parsed) if !parsed.is_empty() => parsed,
+        _ => OfxSgmlParser.parse(bank.as_bytes())?,
+    };
+    Ok(parsed.statements)
+}
+
+enum Section {
+    None,
+    Transaction(InvestmentTransaction),
+    Position(Position),
+    Security(Security),
+}
+
+// same one-tag-per-line trick as read_ofx_statement_header, so v1 and v2 both work
+pub fn parse_investment_statements(file_content: &str) -> Vec<InvestmentStatement> {
+    let mut statements: Vec<InvestmentStatement> = Vec::new();
+    let mut securities: Vec<Security> = Vec::new();
+    let mut statement: Option<InvestmentStatement> = None;
+    let mut section = Section::None;
+
+    let split_content = file_content.replace("<", "\n<");
+    for line in split_content.lines() {
+        let Some((tag, value)) = read_tag(line.trim()) else {
+            continue;
+        };
+
+        if tag == "INVSTMTRS" {
+            statement = Some(InvestmentStatement::default());
+            continue;
+        }
+        if tag == "/INVSTMTRS" {
+            if let Some(s) = statement.take() {
+                statements.push(s);
+            }
+            continue;
+        }
+        if TRANSACTION_TAGS.contains(&tag) {
+            section = Section::Transaction(InvestmentTransaction {
+                kind: tag.to_string(),
+                ..InvestmentTransaction::default()
+            });
+            continue;
+        }
+        if POSITION_TAGS.contains(&tag) {
+            section = Section::Position(Position {
+                kind: tag.to_string(),
+                ..Position::default()
+            });
+            continue;
+        }
+        if SECURITY_TAGS.contains(&tag) {
+            section = Section::Security(Security {
+                kind: tag.to_string(),
+                ..Security::default()
+            });
+            continue;
+        }
+        if let Some(closing) = tag.strip_prefix('/') {
+            if TRANSACTION_TAGS.contains(&closing)
+                || POSITION_TAGS.contains(&closing)
+                || SECURITY_TAGS.contains(&closing)
+            {
+                match std::mem::replace(&mut section, Section::None) {
+                    Section::Transaction(t) => {
+                        if let Some(ref mut s) = statement {
+                            s.transactions.push(t);
+                        }
+                    }
+                    Section::Position(p) => {
+                        if let Some(ref mut s) = statement {
+                            s.positions.push(p);
+                        }
+                    }
+                    Section::Security(s) => securities.push(s),
+                    Section::None => {}
+                }
+            }
+            continue;
+        }
+        if value.is_empty() {
+            continue;
+        }
+
+        let value = value.to_string();
+        match section {
+            Section::Transaction(ref mut t) => read_transaction_field(t, tag, value),
+            Section::Position(ref mut p) => read_position_field(p, tag, value),
+            Section::Security(ref mut s) => read_security_field(s, tag, value),
+            Section::None => {
+                if let Some(ref mut s) = statement {
+                    read_statement_field(s, tag, value);
+                }
+            }
+        }
+    }
+
+    // SECLIST lives in its own message set after the statements,
+    // so it can only be attached once the whole file has been read
+    for s in statements.iter_mut() {
+        s.securities = securities.clone();
+    }
+
+    statements
+}
+
+fn read_statement_field(statement: &mut InvestmentStatement, tag: &str, value: String) {
+    match tag {
+        "BROKERID" => statement.broker_id = Some(value),
+        "ACCTID" => statement.account_id = Some(value),
+        "CURDEF" => statement.currency = Some(value),
+        "DTASOF" if statement.as_of.is_none() => statement.as_of = parse_ofx_date(&value).ok(),
+        "DTSTART" => statement.period_start = parse_ofx_date(&value).ok(),
+        "DTEND" => statement.period_end = parse_ofx_date(&value).ok(),
+        _ => {}
+    }
+}
+
+fn read_transaction_field(txn: &mut InvestmentTransaction, tag: &str, value: String) {
+    match tag {
+        "FITID" => txn.fitid = value,
+        "DTTRADE" | "DTPOSTED" => txn.trade_date = parse_ofx_date(&value).unwrap_or(value),
+        "DTSETTLE" => txn.settle_date = parse_ofx_date(&value).unwrap_or(value),
+        "MEMO" => txn.memo = value,
+        // cash legs (INVBANKTRAN) carry a NAME instead of a MEMO
+        "NAME" if txn.memo.is_empty() => txn.memo = value,
+        "UNIQUEID" => txn.security_id = value,
+        "UNIQUEIDTYPE" => txn.security_id_type = value,
+        "UNITS" => txn.units = value,
+        "UNITPRICE" => txn.unit_price = value,
+        "COMMISSION" => txn.commission = value,
+        "FEES" => txn.fees = value,
+        "TOTAL" | "TRNAMT" => txn.total = value,
+        "INCOMETYPE" => txn.income_type = value,
+        "SUBACCTSEC" => txn.sub_account_security = value,
+        "SUBACCTFUND" => txn.sub_account_fund = value,
+        "BUYTYPE" | "SELLTYPE" | "TFERACTION" | "TRNTYPE" => txn.action = value,
+        _ => {}
+    }
+}
+
+fn read_position_field(position: &mut Position, tag: &str, value: String) {
+    match tag {
+        "UNIQUEID" => position.security_id = value,
+        "UNIQUEIDTYPE" => position.security_id_type = value,
+        "HELDINACCT" => position.held_in = value,
+        "POSTYPE" => position.position_type = value,
+        "UNITS" => position.units = value,
+        "UNITPRICE" => position.unit_price = value,
+        "MKTVAL" => position.market_value = value,
+        "DTPRICEASOF" => position.price_date = parse_ofx_date(&value).unwrap_or(value),
+        _ => {}
+    }
+}
+
+fn read_security_field(security: &mut Security, tag: &str, value: String) {
+    match tag {
+        "UNIQUEID" => security.security_id = value,
+        "UNIQUEIDTYPE" => security.security_id_type = value,
+        "SECNAME" => security.name = value,
+        "TICKER" => security.ticker = value,
+        _ => {}
+    }
+}
+
+fn read_tag(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('<')?;
+    let tag_end = rest.find('>')?;
+    Some((&rest[..tag_end], rest[tag_end + 1..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVESTMENT_DATA: &str = r#"OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<INVSTMTMSGSRSV1>
+<INVSTMTTRNRS>
+<TRNUID>1
+<INVSTMTRS>
+<DTASOF>20251130120000
+<CURDEF>USD
+<INVACCTFROM>
+<BROKERID>broker.example.com
+<ACCTID>X0000001
+</INVACCTFROM>
+<INVTRANLIST>
+<DTSTART>20251101120000
+<DTEND>20251130120000
+<BUYSTOCK>
+<INVBUY>
+<INVTRAN>
+<FITID>T1
+<DTTRADE>20251103120000
+<DTSETTLE>20251105120000
+<MEMO>Bought some
+</INVTRAN>
+<SECID>
+<UNIQUEID>037833100
+<UNIQUEIDTYPE>CUSIP
+</SECID>
+<UNITS>10
+<UNITPRICE>150.25
+<COMMISSION>0
+<TOTAL>-1502.50
+<SUBACCTSEC>CASH
+<SUBACCTFUND>MARGIN
+</INVBUY>
+<BUYTYPE>BUY
+</BUYSTOCK>
+<INCOME>
+<INVTRAN>
+<FITID>T2
+<DTTRADE>20251115120000
+<MEMO>Dividend
+</INVTRAN>
+<SECID>
+<UNIQUEID>037833100
+<UNIQUEIDTYPE>CUSIP
+</SECID>
+<INCOMETYPE>DIV
+<TOTAL>2.40
+<SUBACCTSEC>CASH
+<SUBACCTFUND>CASH
+</INCOME>
+<INVBANKTRAN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20251101120000
+<TRNAMT>2000.00
+<FITID>T3
+<NAME>Deposit
+</STMTTRN>
+<SUBACCTFUND>CASH
+</INVBANKTRAN>
+</INVTRANLIST>
+<INVPOSLIST>
+<POSSTOCK>
+<INVPOS>
+<SECID>
+<UNIQUEID>037833100
+<UNIQUEIDTYPE>CUSIP
+</SECID>
+<HELDINACCT>CASH
+<POSTYPE>LONG
+<UNITS>10
+<UNITPRICE>155.00
+<MKTVAL>1550.00
+<DTPRICEASOF>20251130120000
+</INVPOS>
+</POSSTOCK>
+</INVPOSLIST>
+</INVSTMTRS>
+</INVSTMTTRNRS>
+</INVSTMTMSGSRSV1>
+<SECLISTMSGSRSV1>
+<SECLIST>
+<STOCKINFO>
+<SECINFO>
+<SECID>
+<UNIQUEID>037833100
+<UNIQUEIDTYPE>CUSIP
+</SECID>
+<SECNAME>Apple Inc
+<TICKER>AAPL
+</SECINFO>
+</STOCKINFO>
+</SECLIST>
+</SECLISTMSGSRSV1>
+</OFX>"#;
+
+    #[test]
+    fn test_parse_investment_statement() {
+        assert_eq!(
+            OfxInvestmentParser.sniff(INVESTMENT_DATA.as_bytes()),
+            Confidence::High
+        );
+        let parsed = OfxInvestmentParser
+            .parse(INVESTMENT_DATA.as_bytes())
+            .unwrap();
+        assert!(parsed.statements.is_empty());
+        assert_eq!(parsed.investments.len(), 1);
+
+        let stmt = &parsed.investments[0];
+        assert_eq!(stmt.broker_id.as_deref(), Some("broker.example.com"));
+        assert_eq!(stmt.account_id.as_deref(), Some("X0000001"));
+        assert_eq!(
+            stmt.period_start.as_deref(),
+            Some("2025-11-01T00:00:00+00:00")
+        );
+        assert_eq!(stmt.transactions.len(), 3);
+
+        let buy = &stmt.transactions[0];
+        assert_eq!(buy.kind, "BUYSTOCK");
+        assert_eq!(buy.fitid, "T1");
+        assert_eq!(buy.security_id, "037833100");
+        assert_eq!(buy.units, "10");
+        assert_eq!(buy.total, "-1502.50");
+        assert_eq!(buy.action, "BUY");
+        assert_eq!(buy.sub_account_security, "CASH");
+        assert_eq!(buy.sub_account_fund, "MARGIN");
+
+        let income = &stmt.transactions[1];
+        assert_eq!(income.kind, "INCOME");
+        assert_eq!(income.income_type, "DIV");
+
+        let cash = &stmt.transactions[2];
+        assert_eq!(cash.kind, "INVBANKTRAN");
+        assert_eq!(cash.total, "2000.00");
+        assert_eq!(cash.memo, "Deposit");
+        assert_eq!(cash.sub_account_security, "");
+        assert_eq!(cash.sub_account_fund, "CASH");
+
+        assert_eq!(stmt.positions.len(), 1);
+        assert_eq!(stmt.positions[0].market_value, "1550.00");
+        assert_eq!(stmt.positions[0].position_type, "LONG");
+
+        let security = stmt.security("037833100").unwrap();
+        assert_eq!(security.ticker, "AAPL");
+        assert_eq!(security.security_id_type, "CUSIP");
+    }
+
+    #[test]
+    fn test_parse_mixed_bank_and_investment_file() {
+        let checking = "<BANKMSGSRSV1>\n<STMTTRNRS>\n<STMTRS>\n<CURDEF>USD\n<BANKACCTFROM>\n<ACCTID>00001234\n</BANKACCTFROM>\n<BANKTRANLIST>\n<STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20251103120000\n<TRNAMT>-4.50\n<FITID>B1\n<MEMO>Coffee\n</STMTTRN>\n</BANKTRANLIST>\n</STMTRS>\n</STMTTRNRS>\n</BANKMSGSRSV1>\n";
+        let mixed = INVESTMENT_DATA.replace(
+            "<INVSTMTMSGSRSV1>",
+            &format!("{}<INVSTMTMSGSRSV1>", checking),
+        );
+        let parsed = OfxInvestmentParser.parse(mixed.as_bytes()).unwrap();
+        assert_eq!(parsed.investments.len(), 1);
+        assert_eq!(parsed.investments[0].transactions.len(), 3);
+
+        assert_eq!(parsed.statements.len(), 1);
+        let bank = &parsed.statements[0];
+        assert_eq!(bank.account_id.as_deref(), Some("00001234"));
+        let memos: Vec<&str> = bank.transactions.iter().map(|t| t.memo.as_str()).collect();
+        assert_eq!(memos, vec!["Coffee"]);
+    }
+}
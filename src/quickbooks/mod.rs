@@ -1 +1,2 @@
+pub mod investment;
 pub mod parser;
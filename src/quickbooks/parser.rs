@@ -2,33 +2,30 @@ use std::io::{BufRead, BufReader};
 
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
+use tracing::warn;
 
+use crate::parsing::statementparser::{Confidence, ParsedFile, StatementParser, as_text};
 use crate::utils::globalutil::parse_ofx_date;
-use crate::utils::transactiontransporter::TransactionTransport;
+use crate::utils::logging::redact;
+use crate::utils::statementmodel::{ParsedStatement, RemittanceInfo, StatementBalance};
+use crate::utils::transactiontransporter::{
+    CREDIT_TYPE_CODE, DEBIT_TYPE_CODE, TransactionTransport,
+};
 
-const CREDIT_TYPE_CODE: i32 = 20;
-const DEBIT_TYPE_CODE: i32 = 10;
-
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TempTranFromXml {
-    transaction_type: String,
-    date_posted: String,
-    transaction_amount: String,
-    refnum: String,
-    name: String,
-    memo: String,
+    pub transaction_type: String,
+    pub date_posted: String,
+    pub transaction_amount: String,
+    pub refnum: String,
+    pub name: String,
+    pub memo: String,
+    pub remittance: Option<RemittanceInfo>,
 }
 
 impl TempTranFromXml {
     pub fn new() -> Self {
-        Self {
-            transaction_type: String::new(),
-            date_posted: String::new(),
-            transaction_amount: String::new(),
-            refnum: String::new(),
-            name: String::new(),
-            memo: String::new(),
-        }
+        Self::default()
     }
 
     pub fn to_transport(&self) -> TransactionTransport {
@@ -36,20 +33,29 @@ impl TempTranFromXml {
         let amount = amount.replace("-", "");
         let val = match amount.parse::<i32>() {
             Ok(v) => v,
-            Err(e) => {
-                println!("Invalid: {}", self.transaction_amount);
+            Err(_) => {
+                warn!(
+                    amount = %redact::amount(&self.transaction_amount),
+                    "Invalid transaction amount, using 0"
+                );
                 0
             }
         };
 
         let date = parse_ofx_date(self.date_posted.as_str()).unwrap();
+        // mt940 lines without a bank reference can still carry the
+        // end-to-end reference in :86:, better than nothing to dedup on
+        let refnum = match self.remittance.as_ref().and_then(|r| r.reference.as_ref()) {
+            Some(reference) if self.refnum.is_empty() => reference.clone(),
+            _ => self.refnum.clone(),
+        };
 
         TransactionTransport {
             statement_id: None,
             description: self.memo.clone(),
             amount: val,
             transaction_date: date,
-            refnum: self.refnum.clone(),
+            refnum,
             transaction_type_lookup_code: match self.transaction_type.as_str() {
                 "DEBIT" => DEBIT_TYPE_CODE,
                 "CREDIT" | "DIRECTDEP" => CREDIT_TYPE_CODE,
@@ -59,39 +65,137 @@ impl TempTranFromXml {
     }
 }
 
-pub fn parse_ofx_with_fallback(file_content: &str, file_name: &str) -> Vec<TempTranFromXml> {
-    match parse_as_xml(&file_content.to_string()) {
-        Ok(txns) if !txns.is_empty() => {
-            println!("Parsed as OFX v2");
-            return txns;
+pub struct OfxXmlParser;
+pub struct OfxSgmlParser;
+
+const OFX_EXTENSIONS: [&str; 3] = ["ofx", "qfx", "qbo"];
+
+impl StatementParser for OfxXmlParser {
+    fn name(&self) -> &'static str {
+        "ofx-xml"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &OFX_EXTENSIONS
+    }
+
+    // v2 announces itself with an <?OFX ...?> processing instruction
+    fn sniff(&self, bytes: &[u8]) -> Confidence {
+        let text = as_text(bytes);
+        if text.contains("<?OFX") {
+            Confidence::High
+        } else if text.trim_start().starts_with("<?xml") && text.contains("<OFX>") {
+            Confidence::Medium
+        } else if text.contains("<OFX>") && text.contains("</STMTTRN>") {
+            Confidence::Low
+        } else {
+            Confidence::None
         }
-        _ => {}
     }
 
-    match parse_as_sgml(&file_content.to_string()) {
-        Ok(txns) if !txns.is_empty() => {
-            println!("Parsed as OFX v1");
-            return txns;
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>> {
+        let text = as_text(bytes);
+        let mut statement = read_ofx_statement_header(&text);
+        statement.transactions = parse_as_xml(&text)?;
+        Ok(ParsedFile {
+            statements: vec![statement],
+            ..ParsedFile::default()
+        })
+    }
+}
+
+impl StatementParser for OfxSgmlParser {
+    fn name(&self) -> &'static str {
+        "ofx-sgml"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &OFX_EXTENSIONS
+    }
+
+    // v1 starts with a plain "OFXHEADER:100" block instead of xml
+    fn sniff(&self, bytes: &[u8]) -> Confidence {
+        let text = as_text(bytes);
+        if text.contains("OFXHEADER:") {
+            Confidence::High
+        } else if text.contains("<OFX>") && !text.contains("<?xml") {
+            Confidence::Medium
+        } else if text.contains("<OFX>") {
+            Confidence::Low
+        } else {
+            Confidence::None
         }
-        _ => {}
     }
 
-    match parse_as_sgml_on_one_line(&file_content.to_string()) {
-        Ok(txns) if !txns.is_empty() => {
-            println!("Parsed as OFX v1 that was all on one line");
-            return txns;
+    // some banks (citizens) write the whole body on one line
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>> {
+        let text = as_text(bytes);
+        let mut statement = read_ofx_statement_header(&text);
+        statement.transactions = parse_as_sgml(&text)?;
+        if statement.transactions.is_empty() {
+            statement.transactions = parse_as_sgml_on_one_line(&text)?;
         }
-        _ => {}
+        Ok(ParsedFile {
+            statements: vec![statement],
+            ..ParsedFile::default()
+        })
     }
+}
 
-    panic!("Could not parse file {} as either OFX v1 or v2", file_name);
+// account, period and balance info that lives outside the STMTTRN blocks.
+// splitting on '<' gives one tag per line for v1, one-line v1 and v2 alike,
+// so this doesn't care which flavour it was handed
+pub fn read_ofx_statement_header(file_content: &str) -> ParsedStatement {
+    let mut statement = ParsedStatement::default();
+    let mut in_transaction = false;
+    let mut in_ledger_balance = false;
+    let mut balance_amount: Option<String> = None;
+    let mut balance_date: Option<String> = None;
+
+    let split_content = file_content.replace("<", "\n<");
+    for line in split_content.lines() {
+        let line = line.trim();
+        let Some((tag, value)) = parse_sgml_line(line) else {
+            continue;
+        };
+        match tag.as_str() {
+            "STMTTRN" => in_transaction = true,
+            "/STMTTRN" => in_transaction = false,
+            "LEDGERBAL" => in_ledger_balance = true,
+            "/LEDGERBAL" => in_ledger_balance = false,
+            _ if in_transaction || value.is_empty() => {}
+            "ACCTID" if statement.account_id.is_none() => statement.account_id = Some(value),
+            "BANKID" if statement.bank_id.is_none() => statement.bank_id = Some(value),
+            "ORG" if statement.fi_org.is_none() => statement.fi_org = Some(value),
+            "INTU.BID" if statement.intu_bid.is_none() => statement.intu_bid = Some(value),
+            "CURDEF" if statement.currency.is_none() => statement.currency = Some(value),
+            "DTSTART" if statement.period_start.is_none() => {
+                statement.period_start = parse_ofx_date(&value).ok()
+            }
+            "DTEND" if statement.period_end.is_none() => {
+                statement.period_end = parse_ofx_date(&value).ok()
+            }
+            "BALAMT" if in_ledger_balance => balance_amount = Some(value),
+            "DTASOF" if in_ledger_balance => balance_date = parse_ofx_date(&value).ok(),
+            _ => {}
+        }
+    }
+
+    if let Some(amount) = balance_amount {
+        statement.closing_balance = Some(StatementBalance {
+            amount,
+            date: balance_date,
+        });
+    }
+
+    statement
 }
 
+#[allow(clippy::single_match, clippy::collapsible_match)]
 pub fn parse_as_xml(
-    file_content: &String,
+    file_content: &str,
 ) -> Result<Vec<TempTranFromXml>, Box<dyn std::error::Error>> {
-    println!("{}", "parsing as xml...");
-    let mut x_reader = Reader::from_str(&file_content.as_str());
+    let mut x_reader = Reader::from_str(file_content);
     x_reader.config_mut().trim_text(true);
 
     let mut buf: Vec<u8> = Vec::new();
@@ -107,14 +211,7 @@ pub fn parse_as_xml(
                 b"STMTTRN" => {
                     if temp_tran_holder.is_some() {
                         txns.push(temp_tran_holder.expect("Holder was None somehow."));
-                        temp_tran_holder = Some(TempTranFromXml {
-                            transaction_type: String::new(),
-                            date_posted: String::new(),
-                            transaction_amount: String::new(),
-                            refnum: String::new(),
-                            name: String::new(),
-                            memo: String::new(),
-                        })
+                        temp_tran_holder = Some(TempTranFromXml::new())
                     }
                 }
                 _ => (),
@@ -123,21 +220,14 @@ pub fn parse_as_xml(
             Ok(Event::Start(e)) => match e.name().as_ref() {
                 b"STMTTRN" => {
                     if temp_tran_holder.is_none() {
-                        temp_tran_holder = Some(TempTranFromXml {
-                            transaction_type: String::new(),
-                            date_posted: String::new(),
-                            transaction_amount: String::new(),
-                            refnum: String::new(),
-                            name: String::new(),
-                            memo: String::new(),
-                        })
+                        temp_tran_holder = Some(TempTranFromXml::new())
                     }
                 }
                 b"TRNTYPE" | b"DTPOSTED" | b"TRNAMT" | b"REFNUM" | b"NAME" | b"MEMO" | b"FITID" => {
                     curr_tag = match String::from_utf8(e.name().as_ref().to_owned()) {
                         Ok(s) => s,
-                        Err(e) => {
-                            println!("Failed to convert");
+                        Err(_) => {
+                            warn!("Tag name is not utf-8");
                             String::new()
                         }
                     }
@@ -180,13 +270,13 @@ pub fn parse_as_xml(
 }
 
 pub fn parse_as_sgml_on_one_line(
-    mut file_content: &String,
+    file_content: &str,
 ) -> Result<Vec<TempTranFromXml>, Box<dyn std::error::Error>> {
     let replaced_file_content = &file_content.replace("<", "\n<");
-    parse_as_sgml(&replaced_file_content)
+    parse_as_sgml(replaced_file_content)
 }
 pub fn parse_as_sgml(
-    file_content: &String,
+    file_content: &str,
 ) -> Result<Vec<TempTranFromXml>, Box<dyn std::error::Error>> {
     let reader = BufReader::new(file_content.as_bytes());
     let mut txns: Vec<TempTranFromXml> = Vec::new();
@@ -195,7 +285,6 @@ pub fn parse_as_sgml(
 
     for line in reader.lines() {
         let line = line.unwrap().trim().to_string();
-        println!("line: {}", line);
         if line.is_empty() {
             continue;
         }
@@ -213,17 +302,18 @@ pub fn parse_as_sgml(
             continue;
         }
 
-        if in_transaction && line.starts_with('<') {
-            if let Some((tag, value)) = parse_sgml_line(&line) {
-                match tag.as_str() {
-                    "TRNTYPE" => current_txn.transaction_type = value,
-                    "DTPOSTED" => current_txn.date_posted = value,
-                    "TRNAMT" => current_txn.transaction_amount = value,
-                    "FITID" | "REFNUM" => current_txn.refnum = value,
-                    "NAME" => current_txn.name = value,
-                    "MEMO" => current_txn.memo = value,
-                    _ => {}
-                }
+        if in_transaction
+            && line.starts_with('<')
+            && let Some((tag, value)) = parse_sgml_line(&line)
+        {
+            match tag.as_str() {
+                "TRNTYPE" => current_txn.transaction_type = value,
+                "DTPOSTED" => current_txn.date_posted = value,
+                "TRNAMT" => current_txn.transaction_amount = value,
+                "FITID" | "REFNUM" => current_txn.refnum = value,
+                "NAME" => current_txn.name = value,
+                "MEMO" => current_txn.memo = value,
+                _ => {}
             }
         }
     }
@@ -231,7 +321,7 @@ pub fn parse_as_sgml(
     Ok(txns)
 }
 
-fn parse_sgml_line(line: &String) -> Option<(String, String)> {
+fn parse_sgml_line(line: &str) -> Option<(String, String)> {
     if !line.starts_with('<') {
         return None;
     }
@@ -249,6 +339,12 @@ fn parse_sgml_line(line: &String) -> Option<(String, String)> {
 }
 
 #[cfg(test)]
+// kept as they were written, older than the lint set the build uses now
+#[allow(
+    clippy::get_first,
+    clippy::manual_unwrap_or_default,
+    clippy::unnecessary_to_owned
+)]
 mod tests {
     use super::*;
     const V1_SMGL_DATA: &str = r#"OFXHEADER:100
@@ -443,7 +539,13 @@ NEWFILEUID:NONE
 
     #[test]
     fn test_parse_ofx_with_smgl() {
-        let result = parse_ofx_with_fallback(&V1_SMGL_DATA, "dummy_file");
+        assert_eq!(
+            OfxSgmlParser.sniff(V1_SMGL_DATA.as_bytes()),
+            Confidence::High
+        );
+        assert!(OfxXmlParser.sniff(V1_SMGL_DATA.as_bytes()) < Confidence::High);
+        let mut parsed = OfxSgmlParser.parse(V1_SMGL_DATA.as_bytes()).unwrap();
+        let result = parsed.statements.remove(0).transactions;
 
         assert_eq!(result.len(), 2);
         let first_res = &result.get(0).expect("First value is none. This is wrong.");
@@ -458,7 +560,9 @@ NEWFILEUID:NONE
 
     #[test]
     fn test_parse_ofx_with_xml() {
-        let result = parse_ofx_with_fallback(&V2_XML_DATA, "dummy_file");
+        assert_eq!(OfxXmlParser.sniff(V2_XML_DATA.as_bytes()), Confidence::High);
+        let mut parsed = OfxXmlParser.parse(V2_XML_DATA.as_bytes()).unwrap();
+        let result = parsed.statements.remove(0).transactions;
 
         assert_eq!(result.len(), 2);
         let first_res = &result.get(0).expect("First value is none. This is wrong.");
@@ -471,4 +575,33 @@ NEWFILEUID:NONE
         assert_eq!(second_res.transaction_amount, r#"-5.01"#);
         assert_eq!(second_res.refnum, r#"2"#);
     }
+
+    #[test]
+    fn test_parse_ofx_with_one_line_smgl() {
+        let mut parsed = OfxSgmlParser.parse(ONE_LINE_TEST_DATA.as_bytes()).unwrap();
+        let statement = parsed.statements.remove(0);
+        assert_eq!(statement.account_id.as_deref(), Some("00-test"));
+        assert_eq!(statement.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_read_ofx_statement_header() {
+        let statement = read_ofx_statement_header(ONE_LINE_TEST_DATA);
+        assert_eq!(statement.account_id.as_deref(), Some("00-test"));
+        assert_eq!(statement.fi_org.as_deref(), Some("Dummy"));
+        assert_eq!(statement.currency.as_deref(), Some("USD"));
+        assert_eq!(
+            statement.period_start.as_deref(),
+            Some("2025-11-01T00:00:00+00:00")
+        );
+        assert_eq!(statement.closing_balance.unwrap().amount, "-1234.56");
+
+        let statement = read_ofx_statement_header(V1_SMGL_DATA);
+        assert_eq!(statement.bank_id.as_deref(), Some("0"));
+        assert!(statement.closing_balance.is_none());
+
+        let statement = read_ofx_statement_header(V2_XML_DATA);
+        assert_eq!(statement.fi_org.as_deref(), Some("FAKE"));
+        assert_eq!(statement.intu_bid.as_deref(), Some("1"));
+    }
 }
@@ -0,0 +1,38 @@
+use crate::{
+    service::api::{GET, POST, api_call_no_body, api_call_requires_body},
+    utils::{
+        accounttransporter::{AccountResponse, AccountTransport},
+        globalutil::AuthorizationData,
+    },
+};
+
+pub async fn get_accounts(
+    auth_data: &AuthorizationData,
+) -> Result<Vec<AccountResponse>, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/accounts", auth_data.base_url);
+    let api_result =
+        api_call_no_body::<GET>(endpoint, &auth_data.auth_token, &auth_data.api_key).await?;
+
+    let accounts: Vec<AccountResponse> = serde_json::from_str(&api_result)?;
+
+    Ok(accounts)
+}
+
+pub async fn create_account(
+    account_xport: &AccountTransport,
+    auth_data: &AuthorizationData,
+) -> Result<AccountResponse, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/accounts", auth_data.base_url);
+    let some_auth_token: Option<String> = Some(auth_data.auth_token.clone());
+    let api_result = api_call_requires_body::<AccountTransport, POST>(
+        endpoint,
+        account_xport,
+        some_auth_token,
+        &auth_data.api_key,
+    )
+    .await?;
+
+    let account: AccountResponse = serde_json::from_str(&api_result)?;
+
+    Ok(account)
+}
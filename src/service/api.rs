@@ -1,6 +1,10 @@
+// the request markers are named after the http verbs on purpose
+#![allow(clippy::upper_case_acronyms)]
+
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::{Client, RequestBuilder};
 use serde::Serialize;
+use tracing::debug;
 
 pub struct GET;
 pub struct POST;
@@ -43,17 +47,25 @@ impl ApiRequestBuildable for DELETE {
     }
 }
 
-pub async fn api_call_no_body<T: Serialize, K: ApiRequestBuildable + ForbiddenBody>(
+pub async fn api_call_no_body<K: ApiRequestBuildable + ForbiddenBody>(
     endpoint: String,
-    auth_token: &String,
-    api_key: &String,
+    auth_token: &str,
+    api_key: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
+    debug!(%endpoint, "API call");
     let client = Client::new();
     let mut builder = K::build(&client, endpoint.as_str());
     builder = builder
-        .headers(build_headers(api_key.as_str()))
+        .headers(build_headers(api_key))
         .bearer_auth(auth_token);
-    let resp_bytes = builder.send().await?.bytes().await?.to_vec();
+    // a DELETE has no body to tell a 404 apart from it working
+    let resp_bytes = builder
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec();
     let result = String::from_utf8(resp_bytes)?;
     Ok(result)
 }
@@ -62,12 +74,13 @@ pub async fn api_call_requires_body<T: Serialize, K: ApiRequestBuildable + Requi
     endpoint: String,
     payload: &T,
     auth_token: Option<String>,
-    api_key: &String,
+    api_key: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
+    debug!(%endpoint, "API call");
     let client = Client::new();
     let mut builder = K::build(&client, endpoint.as_str());
 
-    builder = builder.headers(build_headers(&api_key));
+    builder = builder.headers(build_headers(api_key));
     // login endpoint does not require auth token (duh)
     if let Some(auth_token_exists) = auth_token {
         builder = builder.bearer_auth(auth_token_exists);
@@ -0,0 +1,10 @@
+use reqwest::Client;
+use std::time::Duration;
+
+// any answer at all means the backend is up, whatever the status
+pub async fn ping_backend(base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/", base_url);
+    let client = Client::builder().timeout(Duration::from_secs(5)).build()?;
+    client.get(endpoint).send().await?;
+    Ok(())
+}
@@ -2,14 +2,74 @@ use crate::{
     service::api::{POST, api_call_requires_body},
     utils::logintransporter::{LoginRequest, LoginResponse},
 };
+use serde_json::Value;
+use std::fmt;
 
-pub async fn login(login: LoginRequest, api_key: &String) -> LoginResponse {
-    let endpoint = String::from("http://localhost:8085/auth/login");
-    let resp = api_call_requires_body::<LoginRequest, POST>(endpoint, &login, None, api_key)
+// the two ways a login goes wrong that someone at the keyboard can do
+// something about: retrying later, or fixing what they typed
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginError {
+    Unreachable(String),
+    Rejected(String),
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoginError::Unreachable(e) => write!(f, "the backend can't be reached: {}", e),
+            LoginError::Rejected(reason) => write!(f, "the backend turned it down: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LoginError {}
+
+pub async fn try_login(
+    login: &LoginRequest,
+    base_url: &str,
+    api_key: &str,
+) -> Result<LoginResponse, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/auth/login", base_url);
+    let resp = api_call_requires_body::<LoginRequest, POST>(endpoint, login, None, api_key)
         .await
-        .unwrap();
+        .map_err(|e| match e.downcast::<reqwest::Error>() {
+            Ok(e) => Box::new(LoginError::Unreachable(e.to_string())),
+            Err(e) => e,
+        })?;
+
+    // anything but a token means no, whatever the status code was
+    let result: LoginResponse =
+        serde_json::from_str(&resp).map_err(|_| LoginError::Rejected(rejection_reason(&resp)))?;
+
+    Ok(result)
+}
+
+// the backend's own message when there is one, else the start of the body
+fn rejection_reason(body: &str) -> String {
+    if let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(body)
+        && let Some(Value::String(message)) = ["message", "error", "detail"]
+            .iter()
+            .find_map(|key| fields.get(*key))
+    {
+        return message.clone();
+    }
+    match body.trim().lines().next() {
+        Some(line) => line.chars().take(200).collect(),
+        None => String::from("no reason given"),
+    }
+}
 
-    let result: LoginResponse = serde_json::from_str(&resp).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    result
+    #[test]
+    fn test_rejection_reason() {
+        assert_eq!(
+            rejection_reason(r#"{"error": "Invalid credentials", "status": 401}"#),
+            "Invalid credentials"
+        );
+        assert_eq!(rejection_reason("Unauthorized\n"), "Unauthorized");
+        assert_eq!(rejection_reason(""), "no reason given");
+    }
 }
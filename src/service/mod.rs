@@ -1,4 +1,6 @@
+pub mod accountservice;
 pub mod api;
+pub mod healthservice;
 pub mod loginservice;
 pub mod statementservice;
 pub mod transactionservice;
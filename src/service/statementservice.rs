@@ -1,18 +1,21 @@
 use crate::{
-    service::api::{POST, api_call_requires_body},
+    service::{
+        api::{DELETE, GET, POST, PUT, api_call_no_body, api_call_requires_body},
+        transactionservice::{delete_transaction, get_transactions_by_institution},
+    },
     utils::{
-        globalutil::AuthorizationData,
-        statementtransporter::{StatementResponse, StatementTransport},
+        globalutil::{AuthorizationData, forget_statement},
+        statementtransporter::{StatementResponse, StatementTransport, StatementUpdate},
     },
 };
+use tracing::warn;
 
 pub async fn create_statement(
     statement_xport: &StatementTransport,
     auth_data: &AuthorizationData,
 ) -> Result<StatementResponse, Box<dyn std::error::Error>> {
-    // should load base url from .env
     // check errors here, panicking on 404 TODO
-    let endpoint = String::from("http://localhost:8085/api/statements");
+    let endpoint = format!("{}/api/statements", auth_data.base_url);
     let some_auth_token: Option<String> = Some(auth_data.auth_token.clone());
     let api_result = api_call_requires_body::<StatementTransport, POST>(
         endpoint,
@@ -26,3 +29,107 @@ pub async fn create_statement(
 
     Ok(stmnt)
 }
+
+pub async fn get_statements(
+    user_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<Vec<StatementResponse>, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/statements/user/{user_id}", auth_data.base_url);
+    let api_result =
+        api_call_no_body::<GET>(endpoint, &auth_data.auth_token, &auth_data.api_key).await?;
+
+    let stmnts: Vec<StatementResponse> = serde_json::from_str(&api_result)?;
+
+    Ok(stmnts)
+}
+
+// the institutions something was uploaded for, the only ones with transactions
+pub async fn get_statement_institutions(
+    user_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+    Ok(statement_institutions(
+        &get_statements(user_id, auth_data).await?,
+    ))
+}
+
+pub fn statement_institutions(statements: &[StatementResponse]) -> Vec<i32> {
+    let mut institution_ids: Vec<i32> = statements.iter().map(|s| s.institution_id).collect();
+    institution_ids.sort();
+    institution_ids.dedup();
+    institution_ids
+}
+
+pub async fn get_statement(
+    statement_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<StatementResponse, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/statements/{statement_id}", auth_data.base_url);
+    let api_result =
+        api_call_no_body::<GET>(endpoint, &auth_data.auth_token, &auth_data.api_key).await?;
+
+    let stmnt: StatementResponse = serde_json::from_str(&api_result)?;
+
+    Ok(stmnt)
+}
+
+pub async fn update_statement(
+    statement_id: i32,
+    update: &StatementUpdate,
+    auth_data: &AuthorizationData,
+) -> Result<StatementResponse, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/statements/{statement_id}", auth_data.base_url);
+    let api_result = api_call_requires_body::<StatementUpdate, PUT>(
+        endpoint,
+        update,
+        Some(auth_data.auth_token.clone()),
+        &auth_data.api_key,
+    )
+    .await?;
+
+    let stmnt: StatementResponse = serde_json::from_str(&api_result)?;
+
+    Ok(stmnt)
+}
+
+pub async fn delete_statement(
+    statement_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/statements/{statement_id}", auth_data.base_url);
+    api_call_no_body::<DELETE>(endpoint, &auth_data.auth_token, &auth_data.api_key).await?;
+
+    Ok(())
+}
+
+// for a statement that shouldn't have been uploaded. its transactions go
+// first, so a backend that doesn't cascade isn't left with orphans, and
+// nothing is deleted when they can't be listed. their hashes are dropped
+// so the file can be ingested again. gives back how many transactions
+// went with it
+pub async fn delete_statement_and_transactions(
+    user_id: i32,
+    statement_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let statement = get_statement(statement_id, auth_data).await?;
+    let txns: Vec<_> =
+        get_transactions_by_institution(user_id, statement.institution_id, auth_data)
+            .await?
+            .into_iter()
+            .filter(|t| t.statement_id == statement_id)
+            .collect();
+    for txn in txns.iter() {
+        delete_transaction(txn.transaction_id, auth_data).await?;
+    }
+    delete_statement(statement_id, auth_data).await?;
+    // the backend is done with it either way
+    if let Err(e) = forget_statement(statement_id, &txns) {
+        warn!(
+            "Deleted statement {}, but could not forget its transaction hashes: {}",
+            statement_id, e
+        );
+    }
+
+    Ok(txns.len())
+}
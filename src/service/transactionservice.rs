@@ -1,29 +1,90 @@
 use crate::{
-    service::api::{POST, api_call_requires_body},
+    service::api::{DELETE, GET, POST, PUT, api_call_no_body, api_call_requires_body},
     utils::{
         globalutil::AuthorizationData,
-        transactiontransporter::{TransactionResponse, TransactionTransport},
+        transactiontransporter::{TransactionResponse, TransactionTransport, TransactionUpdate},
     },
 };
 
 pub async fn create_transactions(
-    mut txns: &Vec<TransactionTransport>,
-    statement_id: i32,
+    txns: &Vec<TransactionTransport>,
     auth_data: &AuthorizationData,
 ) -> Result<Vec<TransactionResponse>, Box<dyn std::error::Error>> {
-    // need to not hard code the endpoint TODO
-    let endpoint = String::from("http://localhost:8085/api/transactions/batch");
+    let endpoint = format!("{}/api/transactions/batch", auth_data.base_url);
     let some_auth_token: Option<String> = Some(auth_data.auth_token.clone());
     let api_result = api_call_requires_body::<Vec<TransactionTransport>, POST>(
         endpoint,
-        &txns,
+        txns,
         some_auth_token,
         &auth_data.api_key,
     )
-    .await
-    .unwrap();
+    .await?;
 
     let txn: Vec<TransactionResponse> = serde_json::from_str(&api_result)?;
 
     Ok(txn)
 }
+
+pub async fn get_transactions_by_institution(
+    user_id: i32,
+    institution_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<Vec<TransactionResponse>, Box<dyn std::error::Error>> {
+    let endpoint = format!(
+        "{}/api/transactions/by_institution/user/{user_id}/institution/{institution_id}",
+        auth_data.base_url
+    );
+    let api_result =
+        api_call_no_body::<GET>(endpoint, &auth_data.auth_token, &auth_data.api_key).await?;
+
+    let txns: Vec<TransactionResponse> = serde_json::from_str(&api_result)?;
+
+    Ok(txns)
+}
+
+// one call per institution. an institution nothing was uploaded for can
+// answer with an error, so ask only about the ones that have statements,
+// see get_statement_institutions
+pub async fn get_transactions_by_institutions(
+    user_id: i32,
+    institution_ids: &[i32],
+    auth_data: &AuthorizationData,
+) -> Result<Vec<TransactionResponse>, Box<dyn std::error::Error>> {
+    let mut txns: Vec<TransactionResponse> = Vec::new();
+    for institution_id in institution_ids {
+        let t = get_transactions_by_institution(user_id, *institution_id, auth_data)
+            .await
+            .map_err(|e| format!("Transactions for institution {}: {}", institution_id, e))?;
+        txns.extend(t);
+    }
+    Ok(txns)
+}
+
+pub async fn update_transaction(
+    transaction_id: i32,
+    update: &TransactionUpdate,
+    auth_data: &AuthorizationData,
+) -> Result<TransactionResponse, Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/transactions/{transaction_id}", auth_data.base_url);
+    let api_result = api_call_requires_body::<TransactionUpdate, PUT>(
+        endpoint,
+        update,
+        Some(auth_data.auth_token.clone()),
+        &auth_data.api_key,
+    )
+    .await?;
+
+    let txn: TransactionResponse = serde_json::from_str(&api_result)?;
+
+    Ok(txn)
+}
+
+pub async fn delete_transaction(
+    transaction_id: i32,
+    auth_data: &AuthorizationData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let endpoint = format!("{}/api/transactions/{transaction_id}", auth_data.base_url);
+    api_call_no_body::<DELETE>(endpoint, &auth_data.auth_token, &auth_data.api_key).await?;
+
+    Ok(())
+}
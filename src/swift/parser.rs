@@ -0,0 +1,535 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::parsing::statementparser::{Confidence, ParsedFile, StatementParser, as_text};
+use crate::quickbooks::parser::TempTranFromXml;
+use crate::utils::globalutil::parse_and_format_date;
+use crate::utils::statementmodel::{
+    ParsedStatement, RemittanceInfo, StatementBalance, normalize_amount,
+};
+
+#[derive(Debug, Default)]
+struct Mt940Line {
+    value_date: String,
+    booking_date: String,
+    debit: bool,
+    amount: String,
+    type_code: String,
+    customer_ref: String,
+    bank_ref: String,
+    information: String,
+}
+
+impl Mt940Line {
+    // lines without a reference of their own get the statement's, so the
+    // same fee in two statements that share a :20: doesn't hash alike
+    fn into_temp_tran(
+        self,
+        statement_reference: Option<&str>,
+    ) -> Result<TempTranFromXml, Box<dyn std::error::Error>> {
+        let remittance = parse_information(&self.information)?;
+        let memo = if !remittance.unstructured.is_empty() {
+            remittance.unstructured.join(" ")
+        } else {
+            self.information.clone()
+        };
+
+        let refnum = if !self.bank_ref.is_empty() {
+            self.bank_ref.clone()
+        } else if self.customer_ref != "NONREF" {
+            self.customer_ref.clone()
+        } else {
+            statement_reference.unwrap_or_default().to_string()
+        };
+
+        let date_posted = if !self.booking_date.is_empty() {
+            self.booking_date.clone()
+        } else {
+            self.value_date.clone()
+        };
+
+        Ok(TempTranFromXml {
+            transaction_type: String::from(if self.debit { "DEBIT" } else { "CREDIT" }),
+            date_posted,
+            transaction_amount: normalize_amount(&self.amount, self.debit)?,
+            refnum,
+            name: remittance.counterparty_name.clone().unwrap_or_default(),
+            memo,
+            remittance: Some(remittance),
+        })
+    }
+}
+
+pub struct Mt940Parser;
+
+impl StatementParser for Mt940Parser {
+    fn name(&self) -> &'static str {
+        "mt940"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["sta", "mt940", "940", "txt"]
+    }
+
+    fn sniff(&self, bytes: &[u8]) -> Confidence {
+        let text = as_text(bytes);
+        if looks_like_mt940(&text) {
+            Confidence::High
+        } else if text.contains(":20:") && text.contains(":61:") {
+            Confidence::Low
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedFile, Box<dyn std::error::Error>> {
+        Ok(ParsedFile {
+            statements: parse_mt940(&as_text(bytes))?,
+            ..ParsedFile::default()
+        })
+    }
+}
+
+pub fn looks_like_mt940(file_content: &str) -> bool {
+    file_content.contains(":20:")
+        && file_content.contains(":25:")
+        && (file_content.contains(":60F:") || file_content.contains(":60M:"))
+}
+
+// every :20: starts a new statement, so a file with several
+// days (or several accounts) comes back as several statements.
+// :20: is often the same fixed text every day, :28C: tells them apart
+pub fn parse_mt940(file_content: &str) -> Result<Vec<ParsedStatement>, Box<dyn std::error::Error>> {
+    let mut statements: Vec<ParsedStatement> = Vec::new();
+    let mut statement: Option<ParsedStatement> = None;
+    let mut line: Option<Mt940Line> = None;
+
+    for (tag, value) in split_fields(file_content) {
+        match tag.as_str() {
+            "20" => {
+                flush_line(&mut statement, &mut line)?;
+                if let Some(s) = statement.take() {
+                    statements.push(s);
+                }
+                statement = Some(ParsedStatement {
+                    reference: Some(value.trim().to_string()),
+                    ..ParsedStatement::default()
+                });
+            }
+            "28C" => {
+                if let Some(ref mut s) = statement {
+                    let number = value.trim();
+                    s.reference = Some(match s.reference.take() {
+                        Some(reference) => format!("{} {}", reference, number),
+                        None => number.to_string(),
+                    });
+                }
+            }
+            "25" => {
+                if let Some(ref mut s) = statement {
+                    read_account(s, &value);
+                }
+            }
+            "60F" | "60M" => {
+                if let Some(ref mut s) = statement {
+                    let (balance, currency) = parse_balance(&value)?;
+                    s.period_start = balance.date.clone();
+                    s.currency = Some(currency);
+                    s.opening_balance = Some(balance);
+                }
+            }
+            "61" => {
+                flush_line(&mut statement, &mut line)?;
+                line = Some(parse_statement_line(&value)?);
+            }
+            "86" => {
+                if let Some(ref mut l) = line {
+                    l.information = value;
+                }
+            }
+            "62F" | "62M" => {
+                flush_line(&mut statement, &mut line)?;
+                if let Some(ref mut s) = statement {
+                    let (balance, _) = parse_balance(&value)?;
+                    s.period_end = balance.date.clone();
+                    s.closing_balance = Some(balance);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    flush_line(&mut statement, &mut line)?;
+    if let Some(s) = statement.take() {
+        statements.push(s);
+    }
+
+    if statements.is_empty() {
+        return Err("No :20: statements found in MT940 file".into());
+    }
+
+    Ok(statements)
+}
+
+fn flush_line(
+    statement: &mut Option<ParsedStatement>,
+    line: &mut Option<Mt940Line>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let (Some(s), Some(l)) = (statement.as_mut(), line.take()) {
+        let transaction = l.into_temp_tran(s.reference.as_deref())?;
+        s.transactions.push(transaction);
+    }
+    Ok(())
+}
+
+// (tag, value) pairs, with continuation lines folded into the field above.
+// the swift block wrappers ({1:...}{4: and -}) and the "-" separator
+// between statements are dropped
+fn split_fields(file_content: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for raw in file_content.lines() {
+        let raw = raw.trim_end_matches('\r');
+        let raw = match raw.rfind("{4:") {
+            Some(i) => &raw[i + 3..],
+            None => raw,
+        };
+        if raw.trim() == "-" || raw.trim() == "-}" || raw.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = raw.strip_prefix(':')
+            && let Some(tag_end) = rest.find(':')
+            && tag_end <= 3
+        {
+            fields.push((rest[..tag_end].to_string(), rest[tag_end + 1..].to_string()));
+        } else if let Some((_, value)) = fields.last_mut() {
+            value.push('\n');
+            value.push_str(raw);
+        }
+    }
+    fields
+}
+
+// ":25:10020030/1234567" or just an IBAN
+fn read_account(statement: &mut ParsedStatement, value: &str) {
+    let value = value.trim();
+    match value.split_once('/') {
+        Some((bank, account)) => {
+            statement.bank_id = Some(bank.to_string());
+            statement.account_id = Some(account.to_string());
+        }
+        None => statement.account_id = Some(value.to_string()),
+    }
+}
+
+// C250101EUR1000,00
+fn parse_balance(value: &str) -> Result<(StatementBalance, String), Box<dyn std::error::Error>> {
+    let value = value.trim();
+    let invalid = || format!("Invalid MT940 balance '{}'", value);
+    let (Some(date), Some(currency), Some(amount)) =
+        (value.get(1..7), value.get(7..10), value.get(10..))
+    else {
+        return Err(invalid().into());
+    };
+    if amount.is_empty() {
+        return Err(invalid().into());
+    }
+    let debit = value.starts_with('D');
+    let date = swift_date(date)?;
+    let currency = currency.to_string();
+    let balance = StatementBalance {
+        amount: normalize_amount(amount, debit)?,
+        date: parse_and_format_date(&date.format("%Y-%m-%d").to_string()).ok(),
+    };
+    Ok((balance, currency))
+}
+
+// 2501020102D12,50NTRFNONREF//B1234
+// value date, optional entry date, mark, optional funds code, amount,
+// type code, customer reference, optional bank reference, then an
+// optional line of supplementary details
+fn parse_statement_line(value: &str) -> Result<Mt940Line, Box<dyn std::error::Error>> {
+    let first_line = value.lines().next().unwrap_or_default();
+    let invalid = || format!("Invalid MT940 :61: line '{}'", first_line);
+    let mut rest = first_line;
+    let mut line = Mt940Line::default();
+
+    let value_date = swift_date(rest.get(..6).ok_or_else(invalid)?)?;
+    rest = &rest[6..];
+    line.value_date = value_date.format("%Y-%m-%d").to_string();
+
+    if let Some(entry_date) = rest.get(..4)
+        && entry_date.chars().all(|c| c.is_ascii_digit())
+    {
+        let month: u32 = entry_date[..2].parse()?;
+        let day: u32 = entry_date[2..].parse()?;
+        // a december value date can be booked in january
+        let mut year = value_date.year();
+        if month == 1 && value_date.month() == 12 {
+            year += 1;
+        } else if month == 12 && value_date.month() == 1 {
+            year -= 1;
+        }
+        if let Some(booked) = NaiveDate::from_ymd_opt(year, month, day) {
+            line.booking_date = booked.format("%Y-%m-%d").to_string();
+        }
+        rest = &rest[4..];
+    }
+
+    // reversals flip the direction: RC is money going back out
+    let (debit, mark_len) = if rest.starts_with("RC") {
+        (true, 2)
+    } else if rest.starts_with("RD") {
+        (false, 2)
+    } else if rest.starts_with('D') {
+        (true, 1)
+    } else if rest.starts_with('C') {
+        (false, 1)
+    } else {
+        return Err(invalid().into());
+    };
+    line.debit = debit;
+    rest = &rest[mark_len..];
+
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .ok_or_else(invalid)?;
+    line.amount = rest[..amount_end].to_string();
+    rest = &rest[amount_end..];
+
+    line.type_code = rest.get(..4).ok_or_else(invalid)?.to_string();
+    rest = &rest[4..];
+
+    match rest.split_once("//") {
+        Some((customer_ref, bank_ref)) => {
+            line.customer_ref = customer_ref.trim().to_string();
+            line.bank_ref = bank_ref.trim().to_string();
+        }
+        None => line.customer_ref = rest.trim().to_string(),
+    }
+
+    Ok(line)
+}
+
+fn swift_date(yymmdd: &str) -> Result<NaiveDate, Box<dyn std::error::Error>> {
+    Ok(NaiveDate::parse_from_str(
+        &format!("20{}", yymmdd),
+        "%Y%m%d",
+    )?)
+}
+
+// :86: comes in two common structured flavours, the german
+// "166?00SEPA-UEBERWEISUNG?20...?32Name" and the dutch
+// "/TRTP/SEPA OVERBOEKING/NAME/.../REMI/...". anything else is kept raw
+fn parse_information(information: &str) -> Result<RemittanceInfo, Box<dyn std::error::Error>> {
+    let joined = information.replace('\n', "");
+    if joined.as_bytes().get(3) == Some(&b'?') {
+        return parse_question_mark_subfields(&joined);
+    }
+    if joined.starts_with('/') {
+        return Ok(parse_slash_subfields(&joined));
+    }
+
+    Ok(RemittanceInfo {
+        unstructured: information
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect(),
+        ..RemittanceInfo::default()
+    })
+}
+
+fn parse_question_mark_subfields(
+    information: &str,
+) -> Result<RemittanceInfo, Box<dyn std::error::Error>> {
+    let invalid = || format!("Invalid MT940 :86: subfields '{}'", information);
+    let (Some(code), Some(subfields)) = (information.get(..3), information.get(4..)) else {
+        return Err(invalid().into());
+    };
+    let mut remittance = RemittanceInfo {
+        transaction_code: Some(code.to_string()),
+        ..RemittanceInfo::default()
+    };
+    let mut purpose = String::new();
+    let mut name = String::new();
+
+    for subfield in subfields.split('?') {
+        if subfield.len() < 2 {
+            continue;
+        }
+        let (Some(key), Some(value)) = (subfield.get(..2), subfield.get(2..)) else {
+            return Err(invalid().into());
+        };
+        match key {
+            "00" => remittance.posting_text = Some(value.to_string()),
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61"
+            | "62" | "63" => purpose.push_str(value),
+            "30" => remittance.counterparty_bic = Some(value.to_string()),
+            "31" => remittance.counterparty_account = Some(value.to_string()),
+            "32" | "33" => name.push_str(value),
+            _ => {}
+        }
+    }
+
+    // sepa purpose lines are tagged, SVWZ is the actual remittance text
+    let svwz = purpose.find("SVWZ+");
+    if let Some(i) = purpose.find("EREF+") {
+        let end = svwz.filter(|s| *s > i).unwrap_or(purpose.len());
+        let eref = purpose[i + 5..end].trim();
+        if eref != "NOTPROVIDED" {
+            remittance.reference = Some(eref.to_string());
+        }
+    }
+    let text = match svwz {
+        Some(i) => purpose[i + 5..].trim().to_string(),
+        None => purpose.trim().to_string(),
+    };
+    if !text.is_empty() {
+        remittance.unstructured.push(text);
+    }
+    if !name.is_empty() {
+        remittance.counterparty_name = Some(name.trim().to_string());
+    }
+    Ok(remittance)
+}
+
+fn parse_slash_subfields(information: &str) -> RemittanceInfo {
+    let mut remittance = RemittanceInfo::default();
+    let parts: Vec<&str> = information.split('/').collect();
+    // "/KEY/value/KEY/value" splits into ["", KEY, value, KEY, value]
+    for pair in parts[1..].chunks(2) {
+        let [key, value] = pair else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+        match *key {
+            "TRTP" => remittance.posting_text = Some(value),
+            "NAME" => remittance.counterparty_name = Some(value),
+            "IBAN" => remittance.counterparty_account = Some(value),
+            "BIC" => remittance.counterparty_bic = Some(value),
+            "REMI" => remittance.unstructured.push(value),
+            "EREF" if value != "NOTPROVIDED" => remittance.reference = Some(value),
+            _ => {}
+        }
+    }
+    remittance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::globalutil::hash_transaction_data;
+
+    const MT940_DATA: &str =
+        "{1:F01FAKEDEFFAXXX0000000000}{2:O9400000000000FAKEDEFFAXXX00000000000000000000N}{4:
+:20:STARTUMSE
+:25:10020030/1234567
+:28C:00001/001
+:60F:C251031EUR1000,00
+:61:2511011101D12,5NTRFNONREF//B1234
+:86:166?00SEPA-UEBERWEISUNG?109310?20EREF+INV-42?21SVWZ+Rent Novem
+?22ber?30FAKEDEFFXXX?31DE00999999990000000000?32Landlord Ltd
+:61:251102C200,00NTRFPAYROLL
+:86:/TRTP/SEPA OVERBOEKING/IBAN/NL00FAKE0123456789/NAME/Employer BV/REMI/Salary/EREF/NOTPROVIDED
+:61:251103D1,00NCHGNONREF
+:86:Account fee
+:62F:C251103EUR1186,50
+-}";
+
+    #[test]
+    fn test_parse_mt940() {
+        assert!(looks_like_mt940(MT940_DATA));
+        let statements = parse_mt940(MT940_DATA).unwrap();
+        assert_eq!(statements.len(), 1);
+        let stmt = &statements[0];
+        assert_eq!(stmt.reference.as_deref(), Some("STARTUMSE 00001/001"));
+        assert_eq!(stmt.bank_id.as_deref(), Some("10020030"));
+        assert_eq!(stmt.account_id.as_deref(), Some("1234567"));
+        assert_eq!(stmt.currency.as_deref(), Some("EUR"));
+        assert_eq!(
+            stmt.period_start.as_deref(),
+            Some("2025-10-31T00:00:00+00:00")
+        );
+        assert_eq!(
+            stmt.period_end.as_deref(),
+            Some("2025-11-03T00:00:00+00:00")
+        );
+        assert_eq!(stmt.opening_balance.as_ref().unwrap().amount, "1000.00");
+        assert_eq!(stmt.closing_balance.as_ref().unwrap().amount, "1186.50");
+        assert_eq!(stmt.transactions.len(), 3);
+
+        let rent = &stmt.transactions[0];
+        assert_eq!(rent.transaction_type, "DEBIT");
+        assert_eq!(rent.transaction_amount, "-12.50");
+        assert_eq!(rent.refnum, "B1234");
+        assert_eq!(rent.name, "Landlord Ltd");
+        assert_eq!(rent.memo, "Rent November");
+        let remittance = rent.remittance.as_ref().unwrap();
+        assert_eq!(remittance.transaction_code.as_deref(), Some("166"));
+        assert_eq!(
+            remittance.posting_text.as_deref(),
+            Some("SEPA-UEBERWEISUNG")
+        );
+        assert_eq!(remittance.reference.as_deref(), Some("INV-42"));
+        assert_eq!(remittance.counterparty_bic.as_deref(), Some("FAKEDEFFXXX"));
+
+        let salary = &stmt.transactions[1];
+        assert_eq!(salary.transaction_type, "CREDIT");
+        assert_eq!(salary.refnum, "PAYROLL");
+        assert_eq!(salary.name, "Employer BV");
+        assert_eq!(salary.memo, "Salary");
+        assert!(salary.remittance.as_ref().unwrap().reference.is_none());
+
+        let fee = &stmt.transactions[2];
+        assert_eq!(fee.refnum, "STARTUMSE 00001/001");
+        assert_eq!(fee.memo, "Account fee");
+        assert_eq!(fee.to_transport().amount, 100);
+    }
+
+    #[test]
+    fn test_statement_number_keeps_same_reference_statements_apart() {
+        let second = MT940_DATA.replace(":28C:00001/001", ":28C:00002/001");
+        let fee = |data: &str| {
+            let statements = parse_mt940(data).unwrap();
+            hash_transaction_data(&statements[0].transactions[2].to_transport())
+        };
+        assert_eq!(fee(MT940_DATA), fee(MT940_DATA));
+        assert_ne!(fee(MT940_DATA), fee(&second));
+    }
+
+    #[test]
+    fn test_parse_statement_line_booking_date_rolls_year() {
+        let line = parse_statement_line("2412310102RD5,00NTRFNONREF").unwrap();
+        assert_eq!(line.value_date, "2024-12-31");
+        assert_eq!(line.booking_date, "2025-01-02");
+        assert!(!line.debit);
+        assert_eq!(line.amount, "5,00");
+        assert_eq!(line.type_code, "NTRF");
+    }
+
+    #[test]
+    fn test_short_and_non_ascii_fields_are_errors() {
+        assert!(parse_balance("C2501").is_err());
+        assert!(parse_balance("C25010€EUR1,00").is_err());
+        assert!(parse_statement_line("2501").is_err());
+        assert!(parse_statement_line("2501020€D12,50NTRF").is_err());
+        assert!(parse_statement_line("250102D12,50").is_err());
+        assert!(parse_question_mark_subfields("16€?00X").is_err());
+        assert!(parse_question_mark_subfields("166?2€").is_err());
+    }
+
+    #[test]
+    fn test_parse_mt940_rejects_ofx() {
+        assert!(!looks_like_mt940(
+            "<OFX><BANKMSGSRSV1></BANKMSGSRSV1></OFX>"
+        ));
+        assert!(parse_mt940("<OFX></OFX>").is_err());
+    }
+}
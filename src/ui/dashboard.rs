@@ -0,0 +1,805 @@
+// `moneyd dashboard`: log in once, then ingest, review, browse and
+// report from one full-screen session instead of a command each
+use cursive::CbSink;
+use cursive::Cursive;
+use cursive::CursiveRunnable;
+
+use cursive::traits::*;
+use cursive::views::{Dialog, EditView, LinearLayout, SelectView, TextView};
+
+use crate::{
+    analyzer::{
+        analyze::analyze_rows,
+        dataset::{Selection, fetch_dataset},
+        monthly::{monthly_rows, monthly_summary},
+    },
+    commands::period_date,
+    doctor,
+    ingestion::{self, IngestRun, institution_name},
+    service::{
+        statementservice::{
+            delete_statement_and_transactions, get_statement_institutions, get_statements,
+            update_statement,
+        },
+        transactionservice::{
+            delete_transaction, get_transactions_by_institution, update_transaction,
+        },
+    },
+    ui::{
+        loginwindow::add_login,
+        reviewscreen::{HasReview, Review, add_review},
+        theme::themed_cursive,
+        transactionbrowser::{Browser, BrowserRow, HasBrowser, add_browser, refresh},
+    },
+    utils::{
+        globalutil::{AuthorizationData, post_statements_and_transactions, short_date},
+        logintransporter::{LoginRequest, LoginResponse},
+        output::{format_cents, parse_cents, render_table},
+        paths::{config_file, state_dir},
+        statementtransporter::{StatementResponse, StatementUpdate},
+        transactiontransporter::{TransactionResponse, TransactionUpdate, unsigned_amount},
+    },
+};
+use std::{
+    cell::Cell,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    thread,
+};
+use tokio::runtime::Handle;
+
+pub struct Dashboard {
+    config_path: Option<PathBuf>,
+    login: LoginResponse,
+    auth: AuthorizationData,
+    // read by Ingest but not uploaded yet, what Review works on
+    run: Option<IngestRun>,
+    review: Review,
+    // whatever Transactions or a statement last opened
+    browser: Browser,
+}
+
+impl HasReview for Dashboard {
+    fn review(&mut self) -> &mut Review {
+        &mut self.review
+    }
+}
+
+impl HasBrowser for Dashboard {
+    fn browser(&mut self) -> &mut Browser {
+        &mut self.browser
+    }
+
+    fn open(s: &mut Cursive, index: usize) {
+        show_edit(s, index);
+    }
+}
+
+pub fn build_dashboard(
+    base_url: &str,
+    api_key: &str,
+    credentials: Option<LoginRequest>,
+    config_path: Option<PathBuf>,
+) -> CursiveRunnable {
+    let mut siv = themed_cursive();
+    let key = api_key.to_string();
+    let url = base_url.to_string();
+    add_login(
+        &mut siv,
+        base_url,
+        api_key,
+        credentials,
+        Box::new(move |s, login| {
+            // the login dialogs are done with
+            while s.pop_layer().is_some() {}
+            s.set_user_data(Dashboard {
+                config_path,
+                auth: AuthorizationData {
+                    auth_token: login.token.clone(),
+                    api_key: key,
+                    base_url: url,
+                },
+                login,
+                run: None,
+                review: Review::default(),
+                browser: Browser::default(),
+            });
+            show_menu(s);
+        }),
+    );
+    siv
+}
+
+fn show_menu(s: &mut Cursive) {
+    let email = s
+        .user_data::<Dashboard>()
+        .map(|d| d.login.user.email.clone())
+        .unwrap_or_default();
+    let menu = SelectView::<fn(&mut Cursive)>::new()
+        .item("Ingest", start_ingest as fn(&mut Cursive))
+        .item("Review", show_review)
+        .item("Transactions", show_transactions)
+        .item("Statements", show_statements)
+        .item("Reports", show_reports)
+        .item("Settings", show_settings)
+        .on_submit(|s, action| action(s));
+    s.add_layer(
+        Dialog::around(menu.min_width(30))
+            .title(format!("moneyd: {}", email))
+            .button("Quit", |s| s.quit()),
+    );
+}
+
+// what work on the background thread gets: a way to say what
+// it's up to, and the runtime to call the api with
+struct Background {
+    sink: CbSink,
+    runtime: Handle,
+}
+
+impl Background {
+    fn status(&self, text: String) {
+        let _ = self.sink.send(Box::new(move |s| {
+            s.call_on_name("working", |view: &mut TextView| view.set_content(text));
+        }));
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+// `work` runs off the ui thread with a dialog up saying what's going on,
+// and `done` gets what it came back with. errors end up in a dialog
+fn in_background<T: Send + 'static>(
+    s: &mut Cursive,
+    message: &str,
+    work: impl FnOnce(&Background) -> Result<T, String> + Send + 'static,
+    done: impl FnOnce(&mut Cursive, T) + Send + 'static,
+) {
+    s.add_layer(
+        Dialog::around(TextView::new(message).with_name("working").min_width(40)).title("Working"),
+    );
+    let background = Background {
+        sink: s.cb_sink().clone(),
+        runtime: Handle::current(),
+    };
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| work(&background)))
+            .unwrap_or_else(|_| Err(String::from("Something went wrong, see the log.")));
+        let _ = background.sink.send(Box::new(move |s| {
+            s.pop_layer();
+            match result {
+                Ok(value) => done(s, value),
+                Err(e) => s.add_layer(Dialog::info(e)),
+            }
+        }));
+    });
+}
+
+// the user id and credentials for an api call from the background
+fn session(s: &mut Cursive) -> Option<(i32, AuthorizationData)> {
+    s.user_data::<Dashboard>()
+        .map(|d| (d.login.user.id, d.auth.clone()))
+}
+
+fn start_ingest(s: &mut Cursive) {
+    let Some(config_path) = s.user_data::<Dashboard>().map(|d| d.config_path.clone()) else {
+        return;
+    };
+    in_background(
+        s,
+        "Reading statements...",
+        move |background| {
+            let config =
+                ingestion::load_config(config_path.as_deref()).map_err(|e| e.to_string())?;
+            let files = Cell::new(0);
+            ingestion::ingestinator_with_progress(&config, &|path| {
+                files.set(files.get() + 1);
+                background.status(format!("Reading file {}\n{}", files.get(), path.display()));
+            })
+            .map_err(|e| e.to_string())
+        },
+        |s, run| {
+            s.with_user_data(|d: &mut Dashboard| d.run = Some(run));
+            show_ingest_result(s);
+        },
+    );
+}
+
+fn show_ingest_result(s: &mut Cursive) {
+    let Some(report) = s
+        .user_data::<Dashboard>()
+        .and_then(|d| d.run.as_ref().map(|run| run.report()))
+    else {
+        return;
+    };
+    let changed = report.changed_files();
+    let text = if changed.is_empty() {
+        String::from("Nothing new.")
+    } else {
+        render_table(&changed).unwrap_or_else(|e| e.to_string())
+    };
+    let mut dialog = Dialog::around(TextView::new(text).scrollable()).title(format!(
+        "{} new transactions, {} file(s) skipped",
+        report.new_transactions, report.skipped
+    ));
+    if report.new_transactions > 0 {
+        dialog = dialog
+            .button("Review", |s| {
+                s.pop_layer();
+                show_review(s);
+            })
+            .button("Upload", |s| {
+                s.pop_layer();
+                upload(s);
+            });
+    }
+    s.add_layer(dialog.button("Back", |s| {
+        s.pop_layer();
+    }));
+}
+
+fn show_review(s: &mut Cursive) {
+    let ready = s
+        .with_user_data(|d: &mut Dashboard| match &d.run {
+            Some(run) if run.new_transactions() > 0 => {
+                d.review = Review::from_run(run);
+                true
+            }
+            _ => false,
+        })
+        .unwrap_or(false);
+    if !ready {
+        s.add_layer(Dialog::info("Nothing to review, run Ingest first."));
+        return;
+    }
+    add_review::<Dashboard>(s, |s, confirmed| {
+        s.pop_layer();
+        // cancelling keeps the run around for another look
+        if confirmed {
+            let settled = s
+                .with_user_data(|d: &mut Dashboard| match d.run.as_mut() {
+                    Some(run) => {
+                        d.review.apply(run);
+                        run.settle_left_out().map_err(|e| e.to_string())
+                    }
+                    None => Ok(()),
+                })
+                .unwrap_or(Ok(()));
+            if let Err(e) = settled {
+                s.add_layer(Dialog::info(format!(
+                    "Could not record what was left out: {}",
+                    e
+                )));
+                return;
+            }
+            upload(s);
+        }
+    });
+}
+
+fn upload(s: &mut Cursive) {
+    let taken = s
+        .with_user_data(|d: &mut Dashboard| {
+            d.run
+                .take()
+                .map(|run| (run, d.login.clone(), d.auth.clone()))
+        })
+        .flatten();
+    let Some((mut run, login, auth)) = taken else {
+        s.add_layer(Dialog::info("Nothing to upload, run Ingest first."));
+        return;
+    };
+    in_background(
+        s,
+        "Uploading...",
+        move |background| {
+            let result = background
+                .block_on(post_statements_and_transactions(
+                    &mut run.holders,
+                    &login,
+                    &auth,
+                ))
+                .map_err(|e| e.to_string());
+            Ok((run, result))
+        },
+        |s, (run, result)| {
+            let report = run.report();
+            let mut text = format!(
+                "{} new transactions, {} statement(s) uploaded.",
+                report.new_transactions, report.uploaded
+            );
+            if let Err(e) = result {
+                text.push_str(&format!(
+                    "\n{}, see the log for why. Upload again to retry them.",
+                    e
+                ));
+                // what made it up is skipped on the retry
+                s.with_user_data(|d: &mut Dashboard| d.run = Some(run));
+            }
+            s.add_layer(Dialog::info(text).title("Upload"));
+        },
+    );
+}
+
+fn show_transactions(s: &mut Cursive) {
+    let Some((user_id, auth)) = session(s) else {
+        return;
+    };
+    in_background(
+        s,
+        "Fetching transactions...",
+        move |background| {
+            let institutions = background
+                .block_on(get_statement_institutions(user_id, &auth))
+                .map_err(|e| e.to_string())?;
+            let mut rows: Vec<BrowserRow> = Vec::new();
+            for institution_id in institutions {
+                let txns = background
+                    .block_on(get_transactions_by_institution(
+                        user_id,
+                        institution_id,
+                        &auth,
+                    ))
+                    .map_err(|e| {
+                        format!("Transactions for institution {}: {}", institution_id, e)
+                    })?;
+                rows.extend(txns.into_iter().map(|transaction| BrowserRow {
+                    institution: institution_name(institution_id),
+                    transaction,
+                }));
+            }
+            Ok(rows)
+        },
+        |s, rows| show_browser(s, "Transactions", rows),
+    );
+}
+
+fn show_browser(s: &mut Cursive, title: &str, rows: Vec<BrowserRow>) {
+    s.with_user_data(|dashboard: &mut Dashboard| dashboard.browser = Browser::new(title, rows));
+    add_browser::<Dashboard>(s);
+}
+
+// Enter on a transaction: fix what the statement got wrong, or get rid of it
+fn show_edit(s: &mut Cursive, index: usize) {
+    let Some(txn) = s
+        .user_data::<Dashboard>()
+        .and_then(|d| d.browser.rows.get(index))
+        .map(|row| row.transaction.clone())
+    else {
+        return;
+    };
+    let field = |label: &str, name: &str, content: String| {
+        LinearLayout::horizontal()
+            .child(TextView::new(label).fixed_width(13))
+            .child(
+                EditView::new()
+                    .content(content)
+                    .with_name(name)
+                    .fixed_width(40),
+            )
+    };
+    let original = txn.clone();
+    let transaction_id = txn.transaction_id;
+    let amount = format_cents(txn.signed_amount());
+    s.add_layer(
+        Dialog::new()
+            .title(format!(
+                "Transaction #{} on {}",
+                txn.transaction_id,
+                txn.transaction_date.get(..10).unwrap_or_default()
+            ))
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(field("Description", "edit_description", txn.description))
+                    .child(field(
+                        "Category",
+                        "edit_category",
+                        txn.category.unwrap_or_default(),
+                    ))
+                    .child(field("Amount", "edit_amount", amount)),
+            )
+            .button("Save", move |s| save_edit(s, &original))
+            .button("Delete", move |s| confirm_delete(s, transaction_id))
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn save_edit(s: &mut Cursive, original: &TransactionResponse) {
+    let text = |s: &mut Cursive, name: &str| {
+        s.call_on_name(name, |view: &mut EditView| view.get_content())
+            .map(|content| content.trim().to_string())
+            .unwrap_or_default()
+    };
+    let (description, category) = (text(s, "edit_description"), text(s, "edit_category"));
+    let amount = match parse_cents(&text(s, "edit_amount")) {
+        Ok(amount) => amount,
+        Err(e) => return s.add_layer(Dialog::info(e)),
+    };
+    // typed signed like it's shown, a change of sign flips debit and credit
+    let (size, code) = match unsigned_amount(amount) {
+        Ok(_) if amount == original.signed_amount() => (None, None),
+        Ok((size, code)) => (Some(size), Some(code)),
+        Err(_) => return s.add_layer(Dialog::info("That amount is too large.")),
+    };
+    if description.is_empty() {
+        return s.add_layer(Dialog::info("The description can't be empty."));
+    }
+    // only what changed is sent
+    let update = TransactionUpdate {
+        description: Some(description).filter(|d| *d != original.description),
+        category: Some(category).filter(|c| original.category.as_deref().unwrap_or("") != c),
+        amount: size,
+        transaction_type_lookup_code: code,
+    };
+    if update.is_empty() {
+        s.pop_layer();
+        return;
+    }
+    let Some((_, auth)) = session(s) else {
+        return;
+    };
+    let transaction_id = original.transaction_id;
+    in_background(
+        s,
+        "Saving...",
+        move |background| {
+            background
+                .block_on(update_transaction(transaction_id, &update, &auth))
+                .map_err(|e| format!("Could not save transaction {}: {}", transaction_id, e))
+        },
+        |s, txn| {
+            s.pop_layer();
+            s.with_user_data(|d: &mut Dashboard| d.browser.replace(txn));
+            refresh::<Dashboard>(s);
+        },
+    );
+}
+
+fn confirm_delete(s: &mut Cursive, transaction_id: i32) {
+    s.add_layer(
+        Dialog::text(format!("Delete transaction {}?", transaction_id))
+            .title("Delete")
+            .button("Delete", move |s| {
+                s.pop_layer();
+                let Some((_, auth)) = session(s) else {
+                    return;
+                };
+                in_background(
+                    s,
+                    "Deleting...",
+                    move |background| {
+                        background
+                            .block_on(delete_transaction(transaction_id, &auth))
+                            .map_err(|e| {
+                                format!("Could not delete transaction {}: {}", transaction_id, e)
+                            })
+                    },
+                    move |s, ()| {
+                        s.pop_layer();
+                        s.with_user_data(|d: &mut Dashboard| d.browser.remove(transaction_id));
+                        refresh::<Dashboard>(s);
+                    },
+                );
+            })
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn show_statements(s: &mut Cursive) {
+    let Some((user_id, auth)) = session(s) else {
+        return;
+    };
+    in_background(
+        s,
+        "Fetching statements...",
+        move |background| {
+            background
+                .block_on(get_statements(user_id, &auth))
+                .map_err(|e| e.to_string())
+        },
+        |s, statements| {
+            let mut list = SelectView::<StatementResponse>::new().on_submit(|s, statement| {
+                show_statement(s, statement.statement_id, statement.institution_id)
+            });
+            let count = statements.len();
+            for statement in statements {
+                list.add_item(statement_row(&statement), statement);
+            }
+            s.add_fullscreen_layer(
+                Dialog::around(list.with_name("statements").scrollable())
+                    .title(format!("Statements ({})", count))
+                    .button("Edit", show_edit_statement)
+                    .button("Delete", confirm_delete_statement)
+                    .button("Back", |s| {
+                        s.pop_layer();
+                    }),
+            );
+        },
+    );
+}
+
+fn statement_row(statement: &StatementResponse) -> String {
+    format!(
+        "#{:<6} {:<10} {} to {}  added {}{}",
+        statement.statement_id,
+        institution_name(statement.institution_id),
+        statement.period_start.get(..10).unwrap_or_default(),
+        statement.period_end.get(..10).unwrap_or_default(),
+        statement.date_added.get(..10).unwrap_or_default(),
+        match statement.account_id {
+            Some(account_id) => format!("  account {}", account_id),
+            None => String::new(),
+        }
+    )
+}
+
+fn selected_statement(s: &mut Cursive) -> Option<StatementResponse> {
+    s.call_on_name("statements", |view: &mut SelectView<StatementResponse>| {
+        view.selection()
+    })
+    .flatten()
+    .map(|selected| (*selected).clone())
+}
+
+// for a statement that went up with the wrong account or period
+fn show_edit_statement(s: &mut Cursive) {
+    let Some(statement) = selected_statement(s) else {
+        return;
+    };
+    let field = |label: &str, name: &str, content: String| {
+        LinearLayout::horizontal()
+            .child(TextView::new(label).fixed_width(13))
+            .child(
+                EditView::new()
+                    .content(content)
+                    .with_name(name)
+                    .fixed_width(40),
+            )
+    };
+    let statement_id = statement.statement_id;
+    s.add_layer(
+        Dialog::new()
+            .title(format!("Statement #{}", statement_id))
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(field(
+                        "Account id",
+                        "edit_account",
+                        statement
+                            .account_id
+                            .map(|id| id.to_string())
+                            .unwrap_or_default(),
+                    ))
+                    .child(field(
+                        "Period start",
+                        "edit_period_start",
+                        short_date(&statement.period_start),
+                    ))
+                    .child(field(
+                        "Period end",
+                        "edit_period_end",
+                        short_date(&statement.period_end),
+                    )),
+            )
+            .button("Save", move |s| save_statement_edit(s, &statement))
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn save_statement_edit(s: &mut Cursive, original: &StatementResponse) {
+    let text = |s: &mut Cursive, name: &str| {
+        s.call_on_name(name, |view: &mut EditView| view.get_content())
+            .map(|content| content.trim().to_string())
+            .unwrap_or_default()
+    };
+    let account = text(s, "edit_account");
+    let account_id = match account.as_str() {
+        "" => None,
+        id => match id.parse::<i32>() {
+            Ok(id) => Some(id),
+            Err(_) => return s.add_layer(Dialog::info("The account id is a number.")),
+        },
+    };
+    let period = |s: &mut Cursive, name: &str| period_date(&text(s, name));
+    let (period_start, period_end) =
+        match (period(s, "edit_period_start"), period(s, "edit_period_end")) {
+            (Ok(start), Ok(end)) => (start, end),
+            (Err(e), _) | (_, Err(e)) => return s.add_layer(Dialog::info(e.to_string())),
+        };
+    // only what changed is sent
+    let update = StatementUpdate {
+        account_id: account_id.filter(|id| Some(*id) != original.account_id),
+        period_start: Some(period_start)
+            .filter(|d| short_date(d) != short_date(&original.period_start)),
+        period_end: Some(period_end).filter(|d| short_date(d) != short_date(&original.period_end)),
+    };
+    if update.is_empty() {
+        s.pop_layer();
+        return;
+    }
+    let Some((_, auth)) = session(s) else {
+        return;
+    };
+    let statement_id = original.statement_id;
+    in_background(
+        s,
+        "Saving...",
+        move |background| {
+            background
+                .block_on(update_statement(statement_id, &update, &auth))
+                .map_err(|e| format!("Could not save statement {}: {}", statement_id, e))
+        },
+        move |s, statement| {
+            s.pop_layer();
+            s.call_on_name("statements", |view: &mut SelectView<StatementResponse>| {
+                let line = view
+                    .iter()
+                    .position(|(_, s)| s.statement_id == statement_id);
+                if let Some(line) = line {
+                    let _ = view.remove_item(line);
+                    view.insert_item(line, statement_row(&statement), statement);
+                    view.set_selection(line);
+                }
+            });
+        },
+    );
+}
+
+// for a statement that was uploaded by mistake, its transactions go too
+fn confirm_delete_statement(s: &mut Cursive) {
+    let Some(statement_id) = selected_statement(s).map(|selected| selected.statement_id) else {
+        return;
+    };
+    s.add_layer(
+        Dialog::text(format!(
+            "Delete statement {} and its transactions?",
+            statement_id
+        ))
+        .title("Delete")
+        .button("Delete", move |s| {
+            s.pop_layer();
+            let Some((user_id, auth)) = session(s) else {
+                return;
+            };
+            in_background(
+                s,
+                "Deleting...",
+                move |background| {
+                    background
+                        .block_on(delete_statement_and_transactions(
+                            user_id,
+                            statement_id,
+                            &auth,
+                        ))
+                        .map_err(|e| format!("Could not delete statement {}: {}", statement_id, e))
+                },
+                move |s, transactions| {
+                    s.call_on_name("statements", |view: &mut SelectView<StatementResponse>| {
+                        let line = view
+                            .iter()
+                            .position(|(_, s)| s.statement_id == statement_id);
+                        if let Some(line) = line {
+                            let _ = view.remove_item(line);
+                        }
+                    });
+                    s.add_layer(Dialog::info(format!(
+                        "Deleted statement {} and its {} transaction(s).",
+                        statement_id, transactions
+                    )));
+                },
+            );
+        })
+        .button("Cancel", |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn show_statement(s: &mut Cursive, statement_id: i32, institution_id: i32) {
+    let Some((user_id, auth)) = session(s) else {
+        return;
+    };
+    in_background(
+        s,
+        "Fetching transactions...",
+        move |background| {
+            let txns = background.block_on(get_transactions_by_institution(
+                user_id,
+                institution_id,
+                &auth,
+            ));
+            Ok(txns
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|t| t.statement_id == statement_id)
+                .collect::<Vec<_>>())
+        },
+        move |s, txns| {
+            let rows = txns
+                .into_iter()
+                .map(|transaction| BrowserRow {
+                    institution: institution_name(institution_id),
+                    transaction,
+                })
+                .collect();
+            show_browser(s, &format!("Statement #{}", statement_id), rows)
+        },
+    );
+}
+
+fn show_reports(s: &mut Cursive) {
+    let Some((user_id, auth)) = session(s) else {
+        return;
+    };
+    in_background(
+        s,
+        "Crunching numbers...",
+        move |background| {
+            let rows = background
+                .block_on(fetch_dataset(user_id, &Selection::default(), &auth))
+                .map_err(|e| e.to_string())?;
+            Ok((
+                analyze_rows(&rows),
+                monthly_rows(&monthly_summary(&rows, 3)),
+            ))
+        },
+        |s, (analyses, months)| {
+            let text = [render_table(&analyses), render_table(&months)]
+                .into_iter()
+                .map(|table| table.unwrap_or_else(|e| e.to_string()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            s.add_fullscreen_layer(
+                Dialog::around(TextView::new(text).scrollable())
+                    .title("Reports: by institution and month, amounts in cents")
+                    .button("Back", |s| {
+                        s.pop_layer();
+                    }),
+            );
+        },
+    );
+}
+
+fn show_settings(s: &mut Cursive) {
+    let Some(dashboard) = s.user_data::<Dashboard>() else {
+        return;
+    };
+    let config_path = config_file(dashboard.config_path.as_deref());
+    let mut text = format!(
+        "Logged in as {} (user {})\nBackend     {}\nConfig      {}\nState       {}\n\n",
+        dashboard.login.user.email,
+        dashboard.login.user.id,
+        dashboard.auth.base_url,
+        config_path.display(),
+        state_dir().display()
+    );
+    match ingestion::load_config(Some(&config_path)) {
+        Ok(config) => {
+            for finding in doctor::check_config(&config) {
+                text.push_str(&format!(
+                    "{:<4} {}: {}\n",
+                    finding.status, finding.subject, finding.message
+                ));
+            }
+        }
+        Err(e) => text.push_str(&format!("FAIL config: {}\n", e)),
+    }
+    s.add_layer(
+        Dialog::around(TextView::new(text).scrollable())
+            .title("Settings")
+            .button("Back", |s| {
+                s.pop_layer();
+            }),
+    );
+}
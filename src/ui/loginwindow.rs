@@ -1,38 +1,78 @@
 use cursive::Cursive;
 use cursive::CursiveRunnable;
-use cursive::style::{BorderStyle, Palette};
 
 use cursive::traits::*;
 use cursive::views::{Dialog, EditView, TextView};
 
-use crate::utils::logintransporter::LoginRequest;
-pub fn build_login_window() -> CursiveRunnable {
-    let mut siv = cursive::default();
-    siv.set_theme(cursive::theme::Theme {
-        shadow: false,
-        borders: cursive::theme::BorderStyle::Simple,
-        palette: Palette::retro().with(|p| {
-            use cursive::style::BaseColor::*;
-            {
-                use cursive::style::Color::TerminalDefault;
-                use cursive::style::PaletteColor::*;
-                p[Background] = TerminalDefault;
-                p[View] = TerminalDefault;
-                p[Primary] = White.dark();
-                p[TitlePrimary] = Blue.light();
-            }
+use crate::service::loginservice::{LoginError, try_login};
+use crate::ui::theme::themed_cursive;
+use crate::utils::logintransporter::{LoginRequest, LoginResponse};
+use crate::utils::paths::state_file;
+use std::{fs, thread, time::Duration};
+use tracing::warn;
+
+// the email that last logged in, filled in next time
+const LAST_EMAIL_FILE: &str = "last-email.txt";
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+type AfterLogin = Box<dyn FnOnce(&mut Cursive, LoginResponse)>;
+
+pub struct LoginSession {
+    base_url: String,
+    api_key: String,
+    email: String,
+    password: String,
+    // set once the backend said yes, the window closes right after
+    pub response: Option<LoginResponse>,
+    after_login: Option<AfterLogin>,
+}
+
+// logs in from inside the window, so a typo or a backend that's down
+// is an error dialog with a way back instead of the end of the program
+pub fn build_login_window(base_url: &str, api_key: &str) -> CursiveRunnable {
+    let mut siv = themed_cursive();
+    add_login(
+        &mut siv,
+        base_url,
+        api_key,
+        None,
+        Box::new(|s, response| {
+            s.with_user_data(|data: &mut LoginSession| data.response = Some(response));
+            s.quit();
         }),
+    );
+    siv
+}
+
+// the login dialogs, on top of whatever else is up. with credentials
+// (from MONEYD_EMAIL/MONEYD_PASSWORD) it logs in right away and only
+// asks when they're turned down. `after_login` gets the response
+pub fn add_login(
+    s: &mut Cursive,
+    base_url: &str,
+    api_key: &str,
+    credentials: Option<LoginRequest>,
+    after_login: AfterLogin,
+) {
+    let email = match &credentials {
+        Some(credentials) => credentials.email.clone(),
+        None => remembered_email().unwrap_or_default(),
+    };
+    s.set_user_data(LoginSession {
+        base_url: base_url.to_string(),
+        api_key: api_key.to_string(),
+        email: email.clone(),
+        password: credentials.map(|c| c.password).unwrap_or_default(),
+        response: None,
+        after_login: Some(after_login),
     });
-    siv.set_user_data(LoginRequest {
-        email: String::new(),
-        password: String::new(),
-    });
-    siv.add_layer(
+    s.add_layer(
         Dialog::new()
             .title("Enter your email:")
             .padding_lrtb(1, 1, 1, 0)
             .content(
                 EditView::new()
+                    .content(email)
                     .on_submit(submit_email)
                     .with_name("email")
                     .fixed_width(40),
@@ -43,19 +83,25 @@ pub fn build_login_window() -> CursiveRunnable {
                     .unwrap();
 
                 submit_email(s, &email);
-            }),
+            })
+            .button("Quit", |s| s.quit()),
     );
-    siv
+    if s.user_data::<LoginSession>()
+        .is_some_and(|data| !data.password.is_empty())
+    {
+        start_login(s);
+    }
 }
 
 fn submit_email(s: &mut Cursive, email: &str) {
+    let email = email.trim();
     if email.is_empty() {
         s.add_layer(Dialog::info("Please enter a valid email address."));
     } else {
-        s.with_user_data(|data: &mut LoginRequest| data.email = email.to_string());
+        s.with_user_data(|data: &mut LoginSession| data.email = email.to_string());
         s.add_layer(
             Dialog::new()
-                .title("Enter your password:")
+                .title(format!("Password for {}:", email))
                 .padding_lrtb(1, 1, 1, 0)
                 .content(
                     EditView::new()
@@ -70,6 +116,9 @@ fn submit_email(s: &mut Cursive, email: &str) {
                         .unwrap();
 
                     submit_pass(s, &pass);
+                })
+                .button("Back", |s| {
+                    s.pop_layer();
                 }),
         )
     }
@@ -79,9 +128,122 @@ fn submit_pass(s: &mut Cursive, pass: &str) {
     if pass.is_empty() {
         s.add_layer(Dialog::info("Please enter a password."));
     } else {
-        s.with_user_data(|data: &mut LoginRequest| data.password = pass.to_string());
-        let content = format!("Submitted.");
-        s.pop_layer();
-        s.add_layer(Dialog::around(TextView::new(content)).button("Quit", |s| s.quit()));
+        s.with_user_data(|data: &mut LoginSession| data.password = pass.to_string());
+        start_login(s);
+    }
+}
+
+// the login runs on its own thread so the spinner keeps turning,
+// and reports back through the callback sink
+fn start_login(s: &mut Cursive) {
+    let Some((credentials, base_url, api_key)) = s.user_data::<LoginSession>().map(|data| {
+        (
+            LoginRequest {
+                email: data.email.clone(),
+                password: data.password.clone(),
+            },
+            data.base_url.clone(),
+            data.api_key.clone(),
+        )
+    }) else {
+        return;
+    };
+    s.add_layer(Dialog::around(
+        TextView::new(spinner_text(&credentials.email, 0)).with_name("spinner"),
+    ));
+
+    let sink = s.cb_sink().clone();
+    let runtime = tokio::runtime::Handle::current();
+    thread::spawn(move || {
+        let result = runtime.block_on(async {
+            let login = try_login(&credentials, &base_url, &api_key);
+            tokio::pin!(login);
+            let mut ticks = tokio::time::interval(Duration::from_millis(120));
+            let mut frame = 0;
+            loop {
+                tokio::select! {
+                    result = &mut login => break result,
+                    _ = ticks.tick() => {
+                        frame += 1;
+                        let text = spinner_text(&credentials.email, frame);
+                        let _ = sink.send(Box::new(move |s| {
+                            s.call_on_name("spinner", |view: &mut TextView| view.set_content(text));
+                        }));
+                    }
+                }
+            }
+        });
+        // the error has to cross back to the ui thread
+        let result = result.map_err(|e| match e.downcast::<LoginError>() {
+            Ok(e) => *e,
+            Err(e) => LoginError::Unreachable(e.to_string()),
+        });
+        let _ = sink.send(Box::new(move |s| finish_login(s, result)));
+    });
+}
+
+fn finish_login(s: &mut Cursive, result: Result<LoginResponse, LoginError>) {
+    s.pop_layer();
+    match result {
+        Ok(response) => {
+            let after_login = s
+                .with_user_data(|data: &mut LoginSession| {
+                    remember_email(&data.email);
+                    data.after_login.take()
+                })
+                .flatten();
+            if let Some(after_login) = after_login {
+                after_login(s, response);
+            }
+        }
+        Err(e @ LoginError::Rejected(_)) => s.add_layer(
+            Dialog::text(format!("Login failed, {}.", e))
+                .title("Wrong email or password?")
+                .button("Try again", |s| {
+                    s.pop_layer();
+                    if s.call_on_name("pass", |view: &mut EditView| view.set_content(""))
+                        .is_none()
+                    {
+                        // the credentials came from the environment
+                        let email = s
+                            .user_data::<LoginSession>()
+                            .map(|data| data.email.clone())
+                            .unwrap_or_default();
+                        submit_email(s, &email);
+                    }
+                })
+                .button("Change email", |s| {
+                    s.pop_layer();
+                    if s.call_on_name("pass", |_: &mut EditView| ()).is_some() {
+                        s.pop_layer();
+                    }
+                })
+                .button("Quit", |s| s.quit()),
+        ),
+        Err(e @ LoginError::Unreachable(_)) => s.add_layer(
+            Dialog::text(format!("Login failed, {}.", e))
+                .title("Backend unreachable")
+                .button("Retry", |s| {
+                    s.pop_layer();
+                    start_login(s);
+                })
+                .button("Quit", |s| s.quit()),
+        ),
+    }
+}
+
+fn spinner_text(email: &str, frame: usize) -> String {
+    format!("Logging in as {} {}", email, SPINNER[frame % SPINNER.len()])
+}
+
+fn remembered_email() -> Option<String> {
+    let email = fs::read_to_string(state_file(LAST_EMAIL_FILE).ok()?).ok()?;
+    Some(email.trim().to_string()).filter(|e| !e.is_empty())
+}
+
+fn remember_email(email: &str) {
+    let saved = state_file(LAST_EMAIL_FILE).and_then(|path| Ok(fs::write(path, email)?));
+    if let Err(e) = saved {
+        warn!("Could not remember the email: {}", e);
     }
 }
@@ -1 +1,6 @@
+pub mod dashboard;
 pub mod loginwindow;
+pub mod reviewscreen;
+pub mod setupwizard;
+pub mod theme;
+pub mod transactionbrowser;
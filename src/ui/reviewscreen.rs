@@ -0,0 +1,443 @@
+// `moneyd ingest --review`: every file with new transactions before
+// anything is uploaded. transactions can be left out or renamed, and
+// whole files skipped until next time
+use cursive::Cursive;
+use cursive::CursiveRunnable;
+
+use cursive::traits::*;
+use cursive::views::{Dialog, EditView, LinearLayout, Panel, SelectView, TextView};
+
+use crate::ingestion::{IngestRun, SkippedFile};
+use crate::ui::theme::themed_cursive;
+use crate::utils::globalutil::hash_transaction_data;
+use crate::utils::output::format_cents;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewTransaction {
+    // where it sits in the run: holder, batch, transaction
+    position: (usize, usize, usize),
+    pub date: String,
+    pub description: String,
+    pub amount: i32,
+    pub included: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewFile {
+    holder: usize,
+    pub institution: String,
+    pub file: String,
+    pub skipped: bool,
+    pub transactions: Vec<ReviewTransaction>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Review {
+    pub files: Vec<ReviewFile>,
+    // only set by "Upload", closing the screen any other way uploads nothing
+    pub confirmed: bool,
+}
+
+impl Review {
+    // one entry per file, with the new transactions of every statement in it
+    pub fn from_run(run: &IngestRun) -> Self {
+        let mut files: Vec<ReviewFile> = Vec::new();
+        for (h, holder) in run.holders.iter().enumerate() {
+            for (b, batch) in holder.transaction_batches.iter().enumerate() {
+                // already uploaded when this is a retry, nothing to decide
+                if batch.all_transactions_exist || batch.statement_id.is_some() {
+                    continue;
+                }
+                let label = batch.file_label();
+                let file = match files.iter().position(|f| f.holder == h && f.file == label) {
+                    Some(index) => &mut files[index],
+                    None => {
+                        files.push(ReviewFile {
+                            holder: h,
+                            institution: holder.institution_name.clone(),
+                            file: label,
+                            skipped: false,
+                            transactions: Vec::new(),
+                        });
+                        files.last_mut().unwrap()
+                    }
+                };
+                file.transactions
+                    .extend(batch.transactions.iter().enumerate().map(|(i, t)| {
+                        ReviewTransaction {
+                            position: (h, b, i),
+                            date: t.transaction_date.get(..10).unwrap_or_default().to_string(),
+                            description: t.description.clone(),
+                            amount: t.amount,
+                            included: true,
+                        }
+                    }));
+            }
+        }
+        Review {
+            files,
+            confirmed: false,
+        }
+    }
+
+    // skipped files are taken out of the run and left where they are, so
+    // they come up again next time. left out transactions are dropped
+    // from their statement but still count as seen, so they don't.
+    // their hashes go to run.left_out, see IngestRun::settle_left_out
+    pub fn apply(&self, run: &mut IngestRun) {
+        let mut dropped: HashSet<(usize, usize, usize)> = HashSet::new();
+        let mut left_out: HashSet<(usize, usize, usize)> = HashSet::new();
+        for file in self.files.iter() {
+            for t in file.transactions.iter() {
+                if file.skipped || !t.included {
+                    dropped.insert(t.position);
+                    if !file.skipped {
+                        left_out.insert(t.position);
+                    }
+                    continue;
+                }
+                let (h, b, i) = t.position;
+                // the hash stays the one of the original description
+                run.holders[h].transaction_batches[b].transactions[i].description =
+                    t.description.clone();
+            }
+        }
+        let skipped: HashSet<(usize, &str)> = self
+            .files
+            .iter()
+            .filter(|f| f.skipped)
+            .map(|f| (f.holder, f.file.as_str()))
+            .collect();
+
+        for (h, holder) in run.holders.iter_mut().enumerate() {
+            for (b, batch) in holder.transaction_batches.iter_mut().enumerate() {
+                let mut i = 0;
+                batch.transactions.retain(|t| {
+                    i += 1;
+                    if left_out.contains(&(h, b, i - 1)) {
+                        let hash = hash_transaction_data(t);
+                        batch.hashes.remove(&hash);
+                        run.left_out.insert(hash);
+                    }
+                    !dropped.contains(&(h, b, i - 1))
+                });
+            }
+            holder
+                .transaction_batches
+                .retain(|batch| !skipped.contains(&(h, batch.file_label().as_str())));
+        }
+        run.skipped.extend(
+            self.files
+                .iter()
+                .filter(|f| f.skipped)
+                .map(|f| SkippedFile {
+                    file: f.file.clone(),
+                    reason: String::from("skipped in review"),
+                }),
+        );
+    }
+
+    // "12 of 15 transactions from 2 files"
+    pub fn summary(&self) -> String {
+        let files: Vec<&ReviewFile> = self.files.iter().filter(|f| !f.skipped).collect();
+        let total: usize = self.files.iter().map(|f| f.transactions.len()).sum();
+        let included: usize = files
+            .iter()
+            .map(|f| f.transactions.iter().filter(|t| t.included).count())
+            .sum();
+        format!(
+            "{} of {} transactions from {} file(s) will be uploaded",
+            included,
+            total,
+            files.len()
+        )
+    }
+}
+
+// whatever the screen's user data is, as long as there's a review in it.
+// on its own that's the review itself, in the dashboard it's the dashboard
+pub trait HasReview: 'static {
+    fn review(&mut self) -> &mut Review;
+}
+
+impl HasReview for Review {
+    fn review(&mut self) -> &mut Review {
+        self
+    }
+}
+
+pub fn build_review_screen(review: Review) -> CursiveRunnable {
+    let mut siv = themed_cursive();
+    siv.set_user_data(review);
+    add_review::<Review>(&mut siv, |s, confirmed| {
+        s.with_user_data(|review: &mut Review| review.confirmed = confirmed);
+        s.quit();
+    });
+    siv
+}
+
+// the review as a layer. `done` hears whether it was "Upload" or "Cancel"
+pub fn add_review<D: HasReview>(s: &mut Cursive, done: fn(&mut Cursive, bool)) {
+    s.add_fullscreen_layer(
+        Dialog::new()
+            .title("Review new transactions")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(
+                        LinearLayout::horizontal()
+                            .child(
+                                Panel::new(
+                                    SelectView::<usize>::new()
+                                        .on_select(|s, _| refresh_transactions::<D>(s))
+                                        .with_name("files")
+                                        .scrollable(),
+                                )
+                                .title("Files")
+                                .min_width(40),
+                            )
+                            .child(
+                                Panel::new(
+                                    SelectView::<usize>::new()
+                                        .on_submit(|s, index| toggle_transaction::<D>(s, *index))
+                                        .with_name("transactions")
+                                        .scrollable(),
+                                )
+                                .title("Transactions")
+                                .full_width(),
+                            )
+                            .full_height(),
+                    )
+                    .child(TextView::new(
+                        "Enter leaves a transaction out, or takes it back in.",
+                    ))
+                    .child(TextView::new("").with_name("summary")),
+            )
+            .button("Edit", edit_description::<D>)
+            .button("Skip file", toggle_file::<D>)
+            .button("Upload", move |s| done(s, true))
+            .button("Cancel", move |s| done(s, false)),
+    );
+    refresh_files::<D>(s);
+}
+
+fn with_review<D: HasReview, R>(s: &mut Cursive, f: impl FnOnce(&mut Review) -> R) -> Option<R> {
+    s.with_user_data(|data: &mut D| f(data.review()))
+}
+
+fn selected(s: &mut Cursive, name: &str) -> Option<usize> {
+    s.call_on_name(name, |view: &mut SelectView<usize>| view.selection())
+        .flatten()
+        .map(|index| *index)
+}
+
+fn toggle_transaction<D: HasReview>(s: &mut Cursive, index: usize) {
+    let Some(file) = selected(s, "files") else {
+        return;
+    };
+    with_review::<D, _>(s, |review| {
+        let t = &mut review.files[file].transactions[index];
+        t.included = !t.included;
+    });
+    // the file's count changes too
+    refresh_files::<D>(s);
+}
+
+fn toggle_file<D: HasReview>(s: &mut Cursive) {
+    let Some(file) = selected(s, "files") else {
+        return;
+    };
+    with_review::<D, _>(s, |review| {
+        review.files[file].skipped = !review.files[file].skipped;
+    });
+    refresh_files::<D>(s);
+}
+
+fn edit_description<D: HasReview>(s: &mut Cursive) {
+    let (Some(file), Some(index)) = (selected(s, "files"), selected(s, "transactions")) else {
+        return;
+    };
+    let description = with_review::<D, _>(s, |review| {
+        review.files[file].transactions[index].description.clone()
+    })
+    .unwrap_or_default();
+    s.add_layer(
+        Dialog::new()
+            .title("Description")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                EditView::new()
+                    .content(description)
+                    .with_name("description")
+                    .fixed_width(50),
+            )
+            .button("Save", move |s| {
+                let description = s
+                    .call_on_name("description", |view: &mut EditView| view.get_content())
+                    .map(|content| content.trim().to_string())
+                    .unwrap_or_default();
+                if description.is_empty() {
+                    s.add_layer(Dialog::info("The description can't be empty."));
+                    return;
+                }
+                with_review::<D, _>(s, |review| {
+                    review.files[file].transactions[index].description = description;
+                });
+                s.pop_layer();
+                refresh_transactions::<D>(s);
+            })
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+// setting the selection back doesn't run on_select, the callback it
+// hands back is dropped on purpose
+fn refresh_files<D: HasReview>(s: &mut Cursive) {
+    let Some(review) = with_review::<D, _>(s, |review| review.clone()) else {
+        return;
+    };
+    s.call_on_name("files", |view: &mut SelectView<usize>| {
+        let selection = view.selected_id().unwrap_or(0);
+        view.clear();
+        for (index, file) in review.files.iter().enumerate() {
+            view.add_item(file_row(file), index);
+        }
+        let _ = view.set_selection(selection);
+    });
+    refresh_transactions::<D>(s);
+}
+
+fn refresh_transactions<D: HasReview>(s: &mut Cursive) {
+    let Some(review) = with_review::<D, _>(s, |review| review.clone()) else {
+        return;
+    };
+    let file = selected(s, "files").and_then(|index| review.files.get(index));
+    s.call_on_name("transactions", |view: &mut SelectView<usize>| {
+        let selection = view.selected_id().unwrap_or(0);
+        view.clear();
+        for (index, t) in file.iter().flat_map(|f| f.transactions.iter()).enumerate() {
+            view.add_item(transaction_row(t), index);
+        }
+        let _ = view.set_selection(selection);
+    });
+    s.call_on_name("summary", |view: &mut TextView| {
+        view.set_content(review.summary())
+    });
+}
+
+// "skip amex  statement.ofx (12)"
+fn file_row(file: &ReviewFile) -> String {
+    let name = file.file.rsplit('/').next().unwrap_or(&file.file);
+    format!(
+        "{} {:<10} {} ({})",
+        if file.skipped { "skip" } else { "    " },
+        file.institution,
+        name,
+        file.transactions.iter().filter(|t| t.included).count()
+    )
+}
+
+// "[x] 2025-11-03  Coffee                              -4.50"
+fn transaction_row(t: &ReviewTransaction) -> String {
+    let description: String = t.description.chars().take(34).collect();
+    format!(
+        "[{}] {}  {:<34} {:>10}",
+        if t.included { "x" } else { " " },
+        t.date,
+        description,
+        format_cents(t.amount as i64)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::{TransactionBatch, TransactionBatchHolder};
+    use crate::utils::{archiver::AfterUpload, transactiontransporter::TransactionTransport};
+    use std::path::PathBuf;
+
+    fn batch(file: &str, descriptions: &[&str]) -> TransactionBatch {
+        let mut batch = TransactionBatch::new();
+        batch.source_file = PathBuf::from(file);
+        batch.all_transactions_exist = descriptions.is_empty();
+        for description in descriptions {
+            batch.transactions.push(TransactionTransport {
+                statement_id: None,
+                description: description.to_string(),
+                amount: -450,
+                transaction_date: String::from("2025-11-03T00:00:00+00:00"),
+                refnum: String::new(),
+                transaction_type_lookup_code: 10,
+            });
+        }
+        batch.hashes = batch
+            .transactions
+            .iter()
+            .map(hash_transaction_data)
+            .collect();
+        batch
+    }
+
+    fn run() -> IngestRun {
+        IngestRun {
+            holders: vec![TransactionBatchHolder {
+                transaction_batches: vec![
+                    batch("/stmts/nov.ofx", &["Coffee", "Rent"]),
+                    batch("/stmts/dec.ofx", &["Groceries"]),
+                    batch("/stmts/oct.ofx", &[]),
+                ],
+                institution_id: 1,
+                institution_name: String::from("amex"),
+                directory_path: PathBuf::from("/stmts"),
+                after_upload: AfterUpload::Leave,
+            }],
+            skipped: Vec::new(),
+            left_out: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_review_decisions_are_applied() {
+        let mut run = run();
+        let mut review = Review::from_run(&run);
+        assert_eq!(review.files.len(), 2);
+        assert_eq!(
+            review.summary(),
+            "3 of 3 transactions from 2 file(s) will be uploaded"
+        );
+
+        review.files[0].transactions[0].included = false;
+        review.files[0].transactions[1].description = String::from("November rent");
+        review.files[1].skipped = true;
+        assert_eq!(
+            review.summary(),
+            "1 of 3 transactions from 1 file(s) will be uploaded"
+        );
+        assert_eq!(
+            transaction_row(&review.files[0].transactions[0]),
+            "[ ] 2025-11-03  Coffee                                  -4.50"
+        );
+
+        let coffee = hash_transaction_data(&run.holders[0].transaction_batches[0].transactions[0]);
+        review.apply(&mut run);
+        let batches = &run.holders[0].transaction_batches;
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].transactions.len(), 1);
+        // only what was left out, not the skipped file
+        assert_eq!(run.left_out, HashSet::from([coffee.clone()]));
+        assert_eq!(batches[0].hashes.len(), 1);
+        assert!(!batches[0].hashes.contains(&coffee));
+        assert_eq!(batches[0].transactions[0].description, "November rent");
+        assert_eq!(batches[1].source_file, PathBuf::from("/stmts/oct.ofx"));
+        assert_eq!(
+            run.skipped,
+            vec![SkippedFile {
+                file: String::from("/stmts/dec.ofx"),
+                reason: String::from("skipped in review"),
+            }]
+        );
+    }
+}
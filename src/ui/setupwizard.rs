@@ -0,0 +1,292 @@
+// `moneyd setup`: asks for the backend, the api key and a download
+// folder per bank, so nobody has to hand-edit the template
+use cursive::Cursive;
+use cursive::CursiveRunnable;
+
+use cursive::traits::*;
+use cursive::views::{Dialog, EditView, LinearLayout, SelectView, TextView};
+
+use crate::ingestion::INSTITUTIONS;
+use crate::ui::theme::themed_cursive;
+use std::{env, path::Path};
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8085";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupFolder {
+    pub institution: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SetupAnswers {
+    pub base_url: String,
+    pub api_key: String,
+    pub folders: Vec<SetupFolder>,
+    // only set by "Save", closing the window any other way writes nothing
+    pub finished: bool,
+}
+
+pub fn build_setup_wizard(answers: SetupAnswers) -> CursiveRunnable {
+    let mut siv = themed_cursive();
+    let base_url = answers.base_url.clone();
+    let api_key = answers.api_key.clone();
+    siv.set_user_data(answers);
+    siv.add_layer(
+        Dialog::new()
+            .title("moneyd setup: backend")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(TextView::new("Backend URL"))
+                    .child(
+                        EditView::new()
+                            .content(base_url)
+                            .with_name("base_url")
+                            .fixed_width(50),
+                    )
+                    .child(TextView::new("\nAPI key"))
+                    .child(
+                        EditView::new()
+                            .secret()
+                            .content(api_key)
+                            .with_name("api_key")
+                            .fixed_width(50),
+                    ),
+            )
+            .button("Next", submit_backend)
+            .button("Quit", |s| s.quit()),
+    );
+    siv
+}
+
+fn submit_backend(s: &mut Cursive) {
+    let base_url = edit_content(s, "base_url");
+    let api_key = edit_content(s, "api_key");
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        s.add_layer(Dialog::info(
+            "The backend URL starts with http:// or https://",
+        ));
+        return;
+    }
+    if api_key.is_empty() {
+        s.add_layer(Dialog::info("Please enter the API key."));
+        return;
+    }
+    s.with_user_data(|data: &mut SetupAnswers| {
+        data.base_url = base_url;
+        data.api_key = api_key;
+    });
+    s.pop_layer();
+    show_folders(s);
+}
+
+fn show_folders(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::new()
+            .title("moneyd setup: statement folders")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(TextView::new(
+                        "The folder each bank's statements are downloaded to.",
+                    ))
+                    .child(
+                        SelectView::<usize>::new()
+                            .with_name("folders")
+                            .min_height(5)
+                            .fixed_width(60),
+                    ),
+            )
+            .button("Add", show_add_folder)
+            .button("Remove", remove_folder)
+            .button("Save", finish)
+            .button("Quit", |s| s.quit()),
+    );
+    refresh_folders(s);
+}
+
+fn show_add_folder(s: &mut Cursive) {
+    let mut institutions = SelectView::<String>::new().popup();
+    for (name, _) in INSTITUTIONS.iter() {
+        institutions.add_item(*name, name.to_string());
+    }
+    s.add_layer(
+        Dialog::new()
+            .title("Add a folder")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(TextView::new("Institution"))
+                    .child(institutions.with_name("institution"))
+                    .child(TextView::new("\nFolder"))
+                    .child(EditView::new().with_name("path").fixed_width(50)),
+            )
+            .button("Add", add_folder)
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn add_folder(s: &mut Cursive) {
+    let institution = s
+        .call_on_name("institution", |view: &mut SelectView<String>| {
+            view.selection()
+        })
+        .flatten()
+        .map(|i| i.to_string())
+        .unwrap_or_default();
+    let path = expand_home(edit_content(s, "path").trim());
+    if !Path::new(&path).is_dir() {
+        s.add_layer(Dialog::info(format!(
+            "{} is not a folder. Create it first, or pick another.",
+            path
+        )));
+        return;
+    }
+    s.with_user_data(|data: &mut SetupAnswers| {
+        data.folders.push(SetupFolder { institution, path });
+    });
+    s.pop_layer();
+    refresh_folders(s);
+}
+
+fn remove_folder(s: &mut Cursive) {
+    let selected = s
+        .call_on_name("folders", |view: &mut SelectView<usize>| view.selection())
+        .flatten();
+    if let Some(index) = selected {
+        s.with_user_data(|data: &mut SetupAnswers| {
+            data.folders.remove(*index);
+        });
+        refresh_folders(s);
+    }
+}
+
+fn refresh_folders(s: &mut Cursive) {
+    let folders = s
+        .user_data::<SetupAnswers>()
+        .map(|data| data.folders.clone())
+        .unwrap_or_default();
+    s.call_on_name("folders", |view: &mut SelectView<usize>| {
+        view.clear();
+        for (index, folder) in folders.iter().enumerate() {
+            view.add_item(format!("{:<10} {}", folder.institution, folder.path), index);
+        }
+    });
+}
+
+fn finish(s: &mut Cursive) {
+    let no_folders = s
+        .user_data::<SetupAnswers>()
+        .is_none_or(|data| data.folders.is_empty());
+    if no_folders {
+        s.add_layer(Dialog::info("Add at least one folder."));
+        return;
+    }
+    s.with_user_data(|data: &mut SetupAnswers| data.finished = true);
+    s.quit();
+}
+
+fn edit_content(s: &mut Cursive, name: &str) -> String {
+    s.call_on_name(name, |view: &mut EditView| view.get_content())
+        .map(|content| content.trim().to_string())
+        .unwrap_or_default()
+}
+
+// "~/Downloads/amex" -> "/home/me/Downloads/amex"
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+pub fn render_config(answers: &SetupAnswers) -> String {
+    let mut config = String::from(
+        "# written by `moneyd setup`. see moneyd-config-template.toml for\n\
+         # everything else that can go in here\n",
+    );
+    for folder in answers.folders.iter() {
+        config.push_str(&format!(
+            "\n[[directory]]\nname = {}\npath = {}\n",
+            toml_string(&folder.institution),
+            toml_string(&folder.path)
+        ));
+    }
+    config
+}
+
+// the .env with BASE_URL and API_KEY set, keeping whatever else
+// (MONEYD_EMAIL and the like) is already in it
+pub fn render_env(existing: &str, answers: &SetupAnswers) -> String {
+    let settings = [
+        ("BASE_URL", &answers.base_url),
+        ("API_KEY", &answers.api_key),
+    ];
+    let mut lines: Vec<String> = existing
+        .lines()
+        .filter(|line| {
+            !settings
+                .iter()
+                .any(|(key, _)| line.trim_start().starts_with(&format!("{}=", key)))
+        })
+        .map(|line| line.to_string())
+        .collect();
+    for (key, value) in settings {
+        lines.push(format!("{}={}", key, value));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{doctor, ingestion::Config, utils::output::OutputFormat};
+
+    fn answers() -> SetupAnswers {
+        SetupAnswers {
+            base_url: String::from(DEFAULT_BASE_URL),
+            api_key: String::from("key-123"),
+            folders: vec![SetupFolder {
+                institution: String::from("amex"),
+                path: env::temp_dir().to_string_lossy().to_string(),
+            }],
+            finished: true,
+        }
+    }
+
+    #[test]
+    fn test_rendered_config_is_valid() {
+        let mut answers = answers();
+        answers.folders.push(SetupFolder {
+            institution: String::from("chase"),
+            path: String::from("C:\\Users\\me \"work\""),
+        });
+        let config: Config = toml::from_str(&render_config(&answers)).unwrap();
+        assert_eq!(config.directory.len(), 2);
+        assert_eq!(config.directory[1].path, "C:\\Users\\me \"work\"");
+
+        answers.folders.pop();
+        let config: Config = toml::from_str(&render_config(&answers)).unwrap();
+        assert!(doctor::report(OutputFormat::Table, &doctor::check_config(&config), false).is_ok());
+    }
+
+    #[test]
+    fn test_render_env_keeps_other_settings() {
+        let existing = "API_KEY=old\nMONEYD_EMAIL=me@example.com\n";
+        assert_eq!(
+            render_env(existing, &answers()),
+            "MONEYD_EMAIL=me@example.com\nBASE_URL=http://localhost:8085\nAPI_KEY=key-123\n"
+        );
+        assert_eq!(
+            render_env("", &answers()),
+            "BASE_URL=http://localhost:8085\nAPI_KEY=key-123\n"
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use cursive::CursiveRunnable;
+use cursive::style::Palette;
+use cursive::theme::{BorderStyle, Theme};
+use cursive::traits::*;
+
+// every moneyd window looks like this: the terminal's own background,
+// simple borders and no shadows
+pub fn themed_cursive() -> CursiveRunnable {
+    let mut siv = cursive::default();
+    siv.set_theme(Theme {
+        shadow: false,
+        borders: BorderStyle::Simple,
+        palette: Palette::retro().with(|p| {
+            use cursive::style::BaseColor::*;
+            {
+                use cursive::style::Color::TerminalDefault;
+                use cursive::style::PaletteColor::*;
+                p[Background] = TerminalDefault;
+                p[View] = TerminalDefault;
+                p[Primary] = White.dark();
+                p[TitlePrimary] = Blue.light();
+            }
+        }),
+    });
+    siv
+}
@@ -0,0 +1,606 @@
+// the dashboard's transaction list: search as you type, filter by
+// date, institution and amount, sort by any column, details on the side
+use cursive::Cursive;
+
+use cursive::traits::*;
+use cursive::views::{Dialog, EditView, LinearLayout, Panel, SelectView, TextView};
+
+use crate::utils::{
+    output::{format_cents, parse_cents},
+    transactiontransporter::TransactionResponse,
+};
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone)]
+pub struct BrowserRow {
+    // the transactions api is asked per institution, so it doesn't say
+    pub institution: &'static str,
+    pub transaction: TransactionResponse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortColumn {
+    Date,
+    Description,
+    Amount,
+    Institution,
+    Category,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 5] = [
+        SortColumn::Date,
+        SortColumn::Description,
+        SortColumn::Amount,
+        SortColumn::Institution,
+        SortColumn::Category,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Date => "Date",
+            SortColumn::Description => "Description",
+            SortColumn::Amount => "Amount",
+            SortColumn::Institution => "Institution",
+            SortColumn::Category => "Category",
+        }
+    }
+
+    fn compare(&self, a: &BrowserRow, b: &BrowserRow) -> Ordering {
+        let (x, y) = (&a.transaction, &b.transaction);
+        match self {
+            SortColumn::Date => x.transaction_date.cmp(&y.transaction_date),
+            SortColumn::Description => x
+                .description
+                .to_lowercase()
+                .cmp(&y.description.to_lowercase()),
+            SortColumn::Amount => x.signed_amount().cmp(&y.signed_amount()),
+            SortColumn::Institution => a.institution.cmp(b.institution),
+            SortColumn::Category => x.category.cmp(&y.category),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BrowserFilter {
+    // in the description or category, any case
+    pub search: String,
+    pub institution: Option<&'static str>,
+    // "2025-11-01", both ends included
+    pub from: Option<String>,
+    pub to: Option<String>,
+    // in cents, on the size of the amount so it works the same for
+    // money going out and coming in
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+}
+
+impl BrowserFilter {
+    fn matches(&self, row: &BrowserRow) -> bool {
+        let t = &row.transaction;
+        let date = t.transaction_date.get(..10).unwrap_or_default();
+        let size = t.signed_amount().abs();
+        let search = self.search.trim().to_lowercase();
+        (search.is_empty()
+            || t.description.to_lowercase().contains(&search)
+            || t.category
+                .as_deref()
+                .is_some_and(|c| c.to_lowercase().contains(&search)))
+            && self.institution.is_none_or(|i| i == row.institution)
+            && self.from.as_deref().is_none_or(|from| date >= from)
+            && self.to.as_deref().is_none_or(|to| date <= to)
+            && self.min_amount.is_none_or(|min| size >= min)
+            && self.max_amount.is_none_or(|max| size <= max)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Browser {
+    pub title: String,
+    pub rows: Vec<BrowserRow>,
+    pub filter: BrowserFilter,
+    pub sort: SortColumn,
+    pub descending: bool,
+}
+
+impl Default for Browser {
+    fn default() -> Self {
+        Browser::new("Transactions", Vec::new())
+    }
+}
+
+impl Browser {
+    // newest first
+    pub fn new(title: &str, rows: Vec<BrowserRow>) -> Self {
+        Browser {
+            title: title.to_string(),
+            rows,
+            filter: BrowserFilter::default(),
+            sort: SortColumn::Date,
+            descending: true,
+        }
+    }
+
+    // the rows that pass the filter, in order, as indexes into `rows`
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible: Vec<usize> = (0..self.rows.len())
+            .filter(|i| self.filter.matches(&self.rows[*i]))
+            .collect();
+        visible.sort_by(|a, b| {
+            let order = self.sort.compare(&self.rows[*a], &self.rows[*b]);
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        visible
+    }
+
+    // the same column again flips the order
+    pub fn sort_by(&mut self, column: SortColumn) {
+        if self.sort == column {
+            self.descending = !self.descending;
+        } else {
+            self.sort = column;
+            self.descending = column == SortColumn::Date;
+        }
+    }
+
+    // after an edit went through
+    pub fn replace(&mut self, transaction: TransactionResponse) {
+        if let Some(row) = self
+            .rows
+            .iter_mut()
+            .find(|r| r.transaction.transaction_id == transaction.transaction_id)
+        {
+            row.transaction = transaction;
+        }
+    }
+
+    pub fn remove(&mut self, transaction_id: i32) {
+        self.rows
+            .retain(|r| r.transaction.transaction_id != transaction_id);
+    }
+
+    fn institutions(&self) -> Vec<&'static str> {
+        let mut institutions: Vec<&'static str> = self.rows.iter().map(|r| r.institution).collect();
+        institutions.sort();
+        institutions.dedup();
+        institutions
+    }
+}
+
+// whatever the screen's user data is, as long as there's a browser in it
+pub trait HasBrowser: 'static {
+    fn browser(&mut self) -> &mut Browser;
+
+    // what Enter on a row does, `index` is into `rows`. nothing by default
+    fn open(_s: &mut Cursive, _index: usize) {}
+}
+
+pub fn add_browser<D: HasBrowser>(s: &mut Cursive) {
+    let title = with_browser::<D, _>(s, |browser| browser.title.clone()).unwrap_or_default();
+    s.add_fullscreen_layer(
+        Dialog::new()
+            .title(title)
+            .content(
+                LinearLayout::vertical()
+                    .child(
+                        LinearLayout::horizontal()
+                            .child(TextView::new("Search: "))
+                            .child(
+                                EditView::new()
+                                    .on_edit(|s, text, _| {
+                                        let search = text.to_string();
+                                        with_browser::<D, _>(s, |b| b.filter.search = search);
+                                        refresh::<D>(s);
+                                    })
+                                    .with_name("search")
+                                    .full_width(),
+                            ),
+                    )
+                    .child(TextView::new("").with_name("header"))
+                    .child(
+                        LinearLayout::horizontal()
+                            .child(
+                                SelectView::<usize>::new()
+                                    .on_select(|s, index| show_detail::<D>(s, *index))
+                                    .on_submit(|s, index| D::open(s, *index))
+                                    .with_name("rows")
+                                    .scrollable()
+                                    .full_width(),
+                            )
+                            .child(
+                                Panel::new(TextView::new("").with_name("detail"))
+                                    .title("Details")
+                                    .fixed_width(36),
+                            )
+                            .full_height(),
+                    )
+                    .child(TextView::new("").with_name("status")),
+            )
+            .button("Filter", show_filter::<D>)
+            .button("Sort", show_sort::<D>)
+            .button("Back", |s| {
+                s.pop_layer();
+            }),
+    );
+    refresh::<D>(s);
+}
+
+fn with_browser<D: HasBrowser, R>(s: &mut Cursive, f: impl FnOnce(&mut Browser) -> R) -> Option<R> {
+    s.with_user_data(|data: &mut D| f(data.browser()))
+}
+
+// after the rows, the filter or the order changed. the cursor stays
+// on the same line, or the last one when the list got shorter
+pub fn refresh<D: HasBrowser>(s: &mut Cursive) {
+    let Some(browser) = with_browser::<D, _>(s, |browser| browser.clone()) else {
+        return;
+    };
+    let visible = browser.visible();
+    s.call_on_name("header", |view: &mut TextView| {
+        view.set_content(header_row(browser.sort, browser.descending))
+    });
+    let selected = s
+        .call_on_name("rows", |view: &mut SelectView<usize>| {
+            let line = view.selected_id().unwrap_or(0);
+            view.clear();
+            for index in visible.iter() {
+                view.add_item(transaction_row(&browser.rows[*index]), *index);
+            }
+            let line = line.min(visible.len().saturating_sub(1));
+            // no callback, the detail pane is done below
+            let _ = view.set_selection(line);
+            visible.get(line).copied()
+        })
+        .flatten();
+    s.call_on_name("status", |view: &mut TextView| {
+        view.set_content(format!(
+            "{} of {} transactions{}",
+            visible.len(),
+            browser.rows.len(),
+            describe_filter(&browser.filter)
+        ))
+    });
+    match selected {
+        Some(index) => show_detail::<D>(s, index),
+        None => {
+            s.call_on_name("detail", |view: &mut TextView| view.set_content(""));
+        }
+    }
+}
+
+fn show_detail<D: HasBrowser>(s: &mut Cursive, index: usize) {
+    let Some(row) = with_browser::<D, _>(s, |browser| browser.rows.get(index).cloned()).flatten()
+    else {
+        return;
+    };
+    let t = &row.transaction;
+    let text = format!(
+        "Transaction #{}\nStatement   #{}\nInstitution {}\nDate        {}\nAmount      {}\nCategory    {}\n\n{}",
+        t.transaction_id,
+        t.statement_id,
+        row.institution,
+        t.transaction_date.get(..10).unwrap_or_default(),
+        format_cents(t.signed_amount()),
+        t.category.as_deref().unwrap_or("-"),
+        t.description
+    );
+    s.call_on_name("detail", |view: &mut TextView| view.set_content(text));
+}
+
+fn show_sort<D: HasBrowser>(s: &mut Cursive) {
+    let mut columns = SelectView::<SortColumn>::new().on_submit(|s, column| {
+        with_browser::<D, _>(s, |browser| browser.sort_by(*column));
+        s.pop_layer();
+        refresh::<D>(s);
+    });
+    for column in SortColumn::ALL {
+        columns.add_item(column.label(), column);
+    }
+    s.add_layer(
+        Dialog::around(columns)
+            .title("Sort by")
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn show_filter<D: HasBrowser>(s: &mut Cursive) {
+    let Some((filter, institutions)) = with_browser::<D, _>(s, |browser| {
+        (browser.filter.clone(), browser.institutions())
+    }) else {
+        return;
+    };
+    let mut institution = SelectView::<Option<&'static str>>::new().popup();
+    institution.add_item("any", None);
+    for name in institutions {
+        institution.add_item(name, Some(name));
+    }
+    let selected = institution
+        .iter()
+        .position(|(_, value)| *value == filter.institution)
+        .unwrap_or(0);
+    institution.set_selection(selected);
+
+    let field = |label: &str, name: &str, content: String| {
+        LinearLayout::horizontal()
+            .child(TextView::new(label).fixed_width(14))
+            .child(
+                EditView::new()
+                    .content(content)
+                    .with_name(name)
+                    .fixed_width(14),
+            )
+    };
+    s.add_layer(
+        Dialog::new()
+            .title("Filter")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(
+                LinearLayout::vertical()
+                    .child(
+                        LinearLayout::horizontal()
+                            .child(TextView::new("Institution").fixed_width(14))
+                            .child(institution.with_name("institution")),
+                    )
+                    .child(field("From", "from", filter.from.unwrap_or_default()))
+                    .child(field("To", "to", filter.to.unwrap_or_default()))
+                    .child(field(
+                        "Amount from",
+                        "min_amount",
+                        filter.min_amount.map(format_cents).unwrap_or_default(),
+                    ))
+                    .child(field(
+                        "Amount to",
+                        "max_amount",
+                        filter.max_amount.map(format_cents).unwrap_or_default(),
+                    ))
+                    .child(TextView::new(
+                        "\nDates like 2025-11-30. Amounts are\nsizes, -12.00 and 12.00 alike.",
+                    )),
+            )
+            .button("Apply", apply_filter::<D>)
+            .button("Clear", |s| {
+                s.pop_layer();
+                with_browser::<D, _>(s, |browser| {
+                    browser.filter = BrowserFilter {
+                        search: browser.filter.search.clone(),
+                        ..BrowserFilter::default()
+                    }
+                });
+                refresh::<D>(s);
+            })
+            .button("Cancel", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn apply_filter<D: HasBrowser>(s: &mut Cursive) {
+    let text = |s: &mut Cursive, name: &str| {
+        s.call_on_name(name, |view: &mut EditView| view.get_content())
+            .map(|content| content.trim().to_string())
+            .unwrap_or_default()
+    };
+    let (from, to) = (text(s, "from"), text(s, "to"));
+    let (min_amount, max_amount) = (text(s, "min_amount"), text(s, "max_amount"));
+    let institution = s
+        .call_on_name(
+            "institution",
+            |view: &mut SelectView<Option<&'static str>>| view.selection(),
+        )
+        .flatten()
+        .and_then(|selected| *selected);
+
+    let parsed = (|| -> Result<BrowserFilter, String> {
+        Ok(BrowserFilter {
+            search: String::new(),
+            institution,
+            from: parse_date(&from)?,
+            to: parse_date(&to)?,
+            min_amount: parse_amount(&min_amount)?,
+            max_amount: parse_amount(&max_amount)?,
+        })
+    })();
+    match parsed {
+        Ok(filter) => {
+            s.pop_layer();
+            with_browser::<D, _>(s, |browser| {
+                browser.filter = BrowserFilter {
+                    search: browser.filter.search.clone(),
+                    ..filter
+                }
+            });
+            refresh::<D>(s);
+        }
+        Err(e) => s.add_layer(Dialog::info(e)),
+    }
+}
+
+fn parse_date(text: &str) -> Result<Option<String>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|date| Some(date.to_string()))
+        .map_err(|_| format!("'{}' is not a date like 2025-11-30", text))
+}
+
+// "12", "12.5" and "-12.50" -> 1250
+fn parse_amount(text: &str) -> Result<Option<i64>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    parse_cents(text).map(|cents| Some(cents.abs()))
+}
+
+fn describe_filter(filter: &BrowserFilter) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(institution) = filter.institution {
+        parts.push(institution.to_string());
+    }
+    match (&filter.from, &filter.to) {
+        (Some(from), Some(to)) => parts.push(format!("{} to {}", from, to)),
+        (Some(from), None) => parts.push(format!("from {}", from)),
+        (None, Some(to)) => parts.push(format!("until {}", to)),
+        (None, None) => {}
+    }
+    match (filter.min_amount, filter.max_amount) {
+        (Some(min), Some(max)) => {
+            parts.push(format!("{} to {}", format_cents(min), format_cents(max)))
+        }
+        (Some(min), None) => parts.push(format!("at least {}", format_cents(min))),
+        (None, Some(max)) => parts.push(format!("at most {}", format_cents(max))),
+        (None, None) => {}
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(", {}", parts.join(", "))
+    }
+}
+
+const COLUMNS: [(SortColumn, usize); 5] = [
+    (SortColumn::Date, 10),
+    (SortColumn::Description, 36),
+    (SortColumn::Amount, 11),
+    (SortColumn::Institution, 11),
+    (SortColumn::Category, 14),
+];
+
+// the sorted column gets an arrow
+fn header_row(sort: SortColumn, descending: bool) -> String {
+    let cells: Vec<String> = COLUMNS
+        .iter()
+        .map(|(column, width)| {
+            let label = match (*column == sort, descending) {
+                (true, true) => format!("{} ▼", column.label()),
+                (true, false) => format!("{} ▲", column.label()),
+                (false, _) => column.label().to_string(),
+            };
+            if *column == SortColumn::Amount {
+                format!("{:>width$}", label, width = width)
+            } else {
+                format!("{:<width$}", label, width = width)
+            }
+        })
+        .collect();
+    cells.join(" ").trim_end().to_string()
+}
+
+fn transaction_row(row: &BrowserRow) -> String {
+    let t = &row.transaction;
+    let description: String = t.description.chars().take(36).collect();
+    let category: String = t
+        .category
+        .as_deref()
+        .unwrap_or("")
+        .chars()
+        .take(14)
+        .collect();
+    format!(
+        "{:<10} {:<36} {:>11} {:<11} {}",
+        t.transaction_date.get(..10).unwrap_or_default(),
+        description,
+        format_cents(t.signed_amount()),
+        row.institution,
+        category
+    )
+    .trim_end()
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::transactiontransporter::{CREDIT_TYPE_CODE, DEBIT_TYPE_CODE};
+
+    // unsigned with a type code, the way the backend hands them back
+    fn row(
+        institution: &'static str,
+        description: &str,
+        amount: i32,
+        code: i32,
+        date: &str,
+    ) -> BrowserRow {
+        BrowserRow {
+            institution,
+            transaction: TransactionResponse {
+                transaction_id: 0,
+                statement_id: 1,
+                description: description.to_string(),
+                amount,
+                transaction_date: format!("{}T00:00:00+00:00", date),
+                transaction_type_lookup_code: code,
+                category: None,
+            },
+        }
+    }
+
+    fn browser() -> Browser {
+        Browser::new(
+            "Transactions",
+            vec![
+                row("amex", "Coffee", 450, DEBIT_TYPE_CODE, "2025-11-03"),
+                row("chase", "Salary", 250000, CREDIT_TYPE_CODE, "2025-11-15"),
+                row("amex", "Rent", 150000, DEBIT_TYPE_CODE, "2025-12-01"),
+            ],
+        )
+    }
+
+    fn descriptions(browser: &Browser) -> Vec<&str> {
+        browser
+            .visible()
+            .iter()
+            .map(|i| browser.rows[*i].transaction.description.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_and_sort() {
+        let mut browser = browser();
+        assert_eq!(descriptions(&browser), vec!["Rent", "Salary", "Coffee"]);
+
+        browser.sort_by(SortColumn::Amount);
+        assert_eq!(descriptions(&browser), vec!["Rent", "Coffee", "Salary"]);
+        browser.sort_by(SortColumn::Amount);
+        assert_eq!(descriptions(&browser), vec!["Salary", "Coffee", "Rent"]);
+
+        browser.filter.search = String::from("  RE");
+        assert_eq!(descriptions(&browser), vec!["Rent"]);
+
+        browser.filter = BrowserFilter {
+            institution: Some("amex"),
+            min_amount: Some(1000),
+            ..BrowserFilter::default()
+        };
+        assert_eq!(descriptions(&browser), vec!["Rent"]);
+
+        browser.filter = BrowserFilter {
+            from: Some(String::from("2025-11-03")),
+            to: Some(String::from("2025-11-30")),
+            ..BrowserFilter::default()
+        };
+        assert_eq!(descriptions(&browser), vec!["Salary", "Coffee"]);
+    }
+
+    #[test]
+    fn test_parse_filter_fields() {
+        assert_eq!(parse_amount("12"), Ok(Some(1200)));
+        assert_eq!(parse_amount("-12.5"), Ok(Some(1250)));
+        assert_eq!(parse_amount("0.05"), Ok(Some(5)));
+        assert_eq!(parse_amount(""), Ok(None));
+        assert!(parse_amount("12.345").is_err());
+        assert!(parse_amount("twelve").is_err());
+        assert_eq!(
+            parse_date("2025-11-30"),
+            Ok(Some(String::from("2025-11-30")))
+        );
+        assert!(parse_date("30/11/2025").is_err());
+    }
+}
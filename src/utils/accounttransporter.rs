@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct AccountTransport {
+    pub banking_user_id: i32,
+    pub institution_id: i32,
+    pub name: String,
+    // last four of the account number, never the whole thing
+    pub account_mask: String,
+    pub account_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountResponse {
+    pub account_id: i32,
+    pub banking_user_id: i32,
+    pub institution_id: i32,
+    pub name: String,
+    pub account_mask: String,
+    pub account_type: String,
+}
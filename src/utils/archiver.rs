@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// what happens to a statement file once everything in it is uploaded
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterUpload {
+    #[default]
+    Leave,
+    // <directory>/archive/YYYY/MM/<original name>
+    Archive,
+    // <institution>_<account>_<start>_<end>.<ext>, in place
+    Rename,
+}
+
+pub const ARCHIVE_DIR: &str = "archive";
+pub const FAILED_DIR: &str = "failed";
+
+pub struct FileSummary<'a> {
+    pub institution: &'a str,
+    pub account_id: Option<&'a str>,
+    // rfc3339
+    pub period_start: &'a str,
+    pub period_end: &'a str,
+}
+
+pub fn apply_after_upload(
+    file_path: &Path,
+    directory_path: &Path,
+    action: AfterUpload,
+    summary: &FileSummary,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let target = match action {
+        AfterUpload::Leave => return Ok(None),
+        AfterUpload::Archive => {
+            let period_end = DateTime::parse_from_rfc3339(summary.period_end)
+                .map(|d| d.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now());
+            let archive_dir = directory_path
+                .join(ARCHIVE_DIR)
+                .join(period_end.format("%Y").to_string())
+                .join(period_end.format("%m").to_string());
+            fs::create_dir_all(&archive_dir)?;
+            archive_dir.join(file_path.file_name().ok_or("File has no name")?)
+        }
+        AfterUpload::Rename => {
+            let parent = file_path.parent().ok_or("File has no parent directory")?;
+            parent.join(canonical_file_name(file_path, summary))
+        }
+    };
+
+    if target == file_path {
+        return Ok(None);
+    }
+    let target = free_path(target);
+    fs::rename(file_path, &target)?;
+    Ok(Some(target))
+}
+
+// moves the file into <directory>/failed/ and writes <name>.error.txt next to it
+pub fn move_to_failed(
+    file_path: &Path,
+    directory_path: &Path,
+    error: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let failed_dir = directory_path.join(FAILED_DIR);
+    fs::create_dir_all(&failed_dir)?;
+    let target = free_path(failed_dir.join(file_path.file_name().ok_or("File has no name")?));
+    fs::rename(file_path, &target)?;
+
+    let mut report_name = target.file_name().unwrap_or_default().to_os_string();
+    report_name.push(".error.txt");
+    let report = format!(
+        "file: {}\nfailed at: {}\n\n{}\n",
+        file_path.display(),
+        Utc::now().to_rfc3339(),
+        error
+    );
+    fs::write(failed_dir.join(report_name), report)?;
+    Ok(target)
+}
+
+pub fn canonical_file_name(file_path: &Path, summary: &FileSummary) -> String {
+    // the last four are enough to tell cards apart without
+    // putting a whole account number into a file name
+    let account = summary
+        .account_id
+        .map(|a| {
+            let chars: Vec<char> = a.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+            chars[chars.len().saturating_sub(4)..]
+                .iter()
+                .collect::<String>()
+        })
+        .filter(|a| !a.is_empty())
+        .unwrap_or_else(|| String::from("unknown"));
+    let extension = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_else(|| String::from("ofx"));
+
+    format!(
+        "{}_{}_{}_{}.{}",
+        sanitize(summary.institution),
+        account,
+        compact_date(summary.period_start),
+        compact_date(summary.period_end),
+        extension
+    )
+}
+
+// "2025-11-01T00:00:00+00:00" -> "20251101"
+fn compact_date(date: &str) -> String {
+    date.chars()
+        .take(10)
+        .filter(|c| c.is_ascii_digit())
+        .collect()
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+// never clobber an earlier archive of the same statement
+fn free_path(target: PathBuf) -> PathBuf {
+    if !target.exists() {
+        return target;
+    }
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = target
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| target.with_file_name(format!("{}-{}{}", stem, n, extension)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> FileSummary<'static> {
+        FileSummary {
+            institution: "chase",
+            account_id: Some("1234-5678-9012"),
+            period_start: "2025-11-01T00:00:00+00:00",
+            period_end: "2025-11-30T00:00:00+00:00",
+        }
+    }
+
+    #[test]
+    fn test_canonical_file_name() {
+        let name = canonical_file_name(Path::new("/tmp/Activity.QFX"), &summary());
+        assert_eq!(name, "chase_9012_20251101_20251130.qfx");
+    }
+
+    #[test]
+    fn test_archive_and_fail() {
+        let dir = std::env::temp_dir().join("moneyd-archiver-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("activity.ofx");
+        fs::write(&file, "<OFX>").unwrap();
+        let archived = apply_after_upload(&file, &dir, AfterUpload::Archive, &summary())
+            .unwrap()
+            .unwrap();
+        assert_eq!(archived, dir.join("archive/2025/11/activity.ofx"));
+        assert!(!file.exists());
+
+        // a second copy of the same download doesn't overwrite the first
+        fs::write(&file, "<OFX>").unwrap();
+        let archived = apply_after_upload(&file, &dir, AfterUpload::Archive, &summary())
+            .unwrap()
+            .unwrap();
+        assert_eq!(archived, dir.join("archive/2025/11/activity-1.ofx"));
+
+        fs::write(&file, "garbage").unwrap();
+        let failed = move_to_failed(&file, &dir, "No parser could read it").unwrap();
+        assert_eq!(failed, dir.join("failed/activity.ofx"));
+        let report = fs::read_to_string(dir.join("failed/activity.ofx.error.txt")).unwrap();
+        assert!(report.contains("No parser could read it"));
+
+        fs::write(&file, "<OFX>").unwrap();
+        assert!(
+            apply_after_upload(&file, &dir, AfterUpload::Leave, &summary())
+                .unwrap()
+                .is_none()
+        );
+        assert!(file.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
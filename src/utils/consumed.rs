@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashSet, fs, io::ErrorKind, path::Path};
+
+// files pulled out of archives, so a zip with a year of statements
+// isn't re-parsed entry by entry every time it's scanned. kept in the state dir
+pub const CONSUMED_FILE: &str = "consumed-files.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConsumedFile {
+    // sha256 of the file's content
+    pub hash: String,
+    // "<archive path>!<path inside the archive>"
+    pub path: String,
+}
+
+impl ConsumedFile {
+    pub fn new(archive_path: &str, inner_name: &str, bytes: &[u8]) -> Self {
+        ConsumedFile {
+            hash: hash_bytes(bytes),
+            path: format!("{}!{}", archive_path, inner_name),
+        }
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// hashes of everything consumed so far. no file yet just means nothing has been
+pub fn get_consumed_hashes(
+    path: impl AsRef<Path>,
+) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    Ok(load_consumed(path.as_ref())?
+        .into_iter()
+        .map(|c| c.hash)
+        .collect())
+}
+
+pub fn add_consumed(
+    path: impl AsRef<Path>,
+    new_files: &[ConsumedFile],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let mut consumed = load_consumed(path)?;
+    for file in new_files {
+        if !consumed.iter().any(|c| c.hash == file.hash) {
+            consumed.push(file.clone());
+        }
+    }
+    fs::write(path, serde_json::to_string_pretty(&consumed)?)?;
+    Ok(())
+}
+
+fn load_consumed(path: &Path) -> Result<Vec<ConsumedFile>, Box<dyn std::error::Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_read_consumed() {
+        let path = std::env::temp_dir().join("moneyd-consumed-test.json");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        assert!(get_consumed_hashes(path).unwrap().is_empty());
+
+        let january = ConsumedFile::new("/tmp/2024.zip", "january.ofx", b"<OFX>1");
+        let february = ConsumedFile::new("/tmp/2024.zip", "february.ofx", b"<OFX>2");
+        add_consumed(path, std::slice::from_ref(&january)).unwrap();
+        // the same content again doesn't add a second entry
+        add_consumed(path, &[january.clone(), february.clone()]).unwrap();
+
+        let hashes = get_consumed_hashes(path).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert!(hashes.contains(&january.hash));
+        assert_eq!(january.path, "/tmp/2024.zip!january.ofx");
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1 +1,187 @@
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use serde::Serialize;
+
+use crate::quickbooks::investment::{InvestmentStatement, Security};
+
+// security name/ticker are looked up from the SECLIST so the csv
+// is readable without a cusip table next to it
+#[derive(Debug, Serialize)]
+struct InvestmentTransactionRow<'a> {
+    account_id: &'a str,
+    kind: &'a str,
+    action: &'a str,
+    fitid: &'a str,
+    trade_date: &'a str,
+    settle_date: &'a str,
+    security_id: &'a str,
+    security_id_type: &'a str,
+    ticker: &'a str,
+    security_name: &'a str,
+    units: &'a str,
+    unit_price: &'a str,
+    commission: &'a str,
+    fees: &'a str,
+    total: &'a str,
+    income_type: &'a str,
+    sub_account_security: &'a str,
+    sub_account_fund: &'a str,
+    memo: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct PositionRow<'a> {
+    account_id: &'a str,
+    as_of: &'a str,
+    kind: &'a str,
+    security_id: &'a str,
+    security_id_type: &'a str,
+    ticker: &'a str,
+    security_name: &'a str,
+    held_in: &'a str,
+    position_type: &'a str,
+    units: &'a str,
+    unit_price: &'a str,
+    market_value: &'a str,
+    price_date: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct SecurityRow<'a> {
+    kind: &'a str,
+    security_id: &'a str,
+    security_id_type: &'a str,
+    ticker: &'a str,
+    name: &'a str,
+}
+
+// writes <account>_<as of>_transactions.csv, _positions.csv and _securities.csv into
+// export_dir. names are stable, so re-ingesting a file overwrites
+// its previous export instead of piling up copies
+pub fn export_investment_statement(
+    statement: &InvestmentStatement,
+    export_dir: &str,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(export_dir)?;
+    let account_id = statement.account_id.as_deref().unwrap_or("unknown");
+    let as_of = statement
+        .as_of
+        .as_deref()
+        .or(statement.period_end.as_deref())
+        .unwrap_or_default();
+    let file_stem = format!("{}_{}", account_id, as_of.get(..10).unwrap_or(as_of));
+
+    let transactions_path = Path::new(export_dir).join(format!("{}_transactions.csv", file_stem));
+    let mut writer = csv::Writer::from_path(&transactions_path)?;
+    for txn in statement.transactions.iter() {
+        let (ticker, security_name) = security_labels(statement.security(&txn.security_id));
+        writer.serialize(InvestmentTransactionRow {
+            account_id,
+            kind: &txn.kind,
+            action: &txn.action,
+            fitid: &txn.fitid,
+            trade_date: &txn.trade_date,
+            settle_date: &txn.settle_date,
+            security_id: &txn.security_id,
+            security_id_type: &txn.security_id_type,
+            ticker,
+            security_name,
+            units: &txn.units,
+            unit_price: &txn.unit_price,
+            commission: &txn.commission,
+            fees: &txn.fees,
+            total: &txn.total,
+            income_type: &txn.income_type,
+            sub_account_security: &txn.sub_account_security,
+            sub_account_fund: &txn.sub_account_fund,
+            memo: &txn.memo,
+        })?;
+    }
+    writer.flush()?;
+
+    let positions_path = Path::new(export_dir).join(format!("{}_positions.csv", file_stem));
+    let mut writer = csv::Writer::from_path(&positions_path)?;
+    for position in statement.positions.iter() {
+        let (ticker, security_name) = security_labels(statement.security(&position.security_id));
+        writer.serialize(PositionRow {
+            account_id,
+            as_of,
+            kind: &position.kind,
+            security_id: &position.security_id,
+            security_id_type: &position.security_id_type,
+            ticker,
+            security_name,
+            held_in: &position.held_in,
+            position_type: &position.position_type,
+            units: &position.units,
+            unit_price: &position.unit_price,
+            market_value: &position.market_value,
+            price_date: &position.price_date,
+        })?;
+    }
+    writer.flush()?;
+
+    let securities_path = Path::new(export_dir).join(format!("{}_securities.csv", file_stem));
+    let mut writer = csv::Writer::from_path(&securities_path)?;
+    for security in statement.securities.iter() {
+        writer.serialize(SecurityRow {
+            kind: &security.kind,
+            security_id: &security.security_id,
+            security_id_type: &security.security_id_type,
+            ticker: &security.ticker,
+            name: &security.name,
+        })?;
+    }
+    writer.flush()?;
+
+    Ok(vec![transactions_path, positions_path, securities_path])
+}
+
+fn security_labels(security: Option<&Security>) -> (&str, &str) {
+    match security {
+        Some(s) => (s.ticker.as_str(), s.name.as_str()),
+        None => ("", ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quickbooks::investment::{InvestmentTransaction, Position};
+
+    #[test]
+    fn test_export_investment_statement() {
+        let statement = InvestmentStatement {
+            account_id: Some(String::from("X0000001")),
+            as_of: Some(String::from("2025-11-30T00:00:00+00:00")),
+            transactions: vec![InvestmentTransaction {
+                kind: String::from("BUYSTOCK"),
+                security_id: String::from("037833100"),
+                total: String::from("-1502.50"),
+                ..InvestmentTransaction::default()
+            }],
+            positions: vec![Position {
+                kind: String::from("POSSTOCK"),
+                security_id: String::from("037833100"),
+                ..Position::default()
+            }],
+            securities: vec![Security {
+                kind: String::from("STOCKINFO"),
+                security_id: String::from("037833100"),
+                ticker: String::from("AAPL"),
+                ..Security::default()
+            }],
+            ..InvestmentStatement::default()
+        };
+        let export_dir = std::env::temp_dir().join("moneyd-investment-export-test");
+        let paths = export_investment_statement(&statement, export_dir.to_str().unwrap()).unwrap();
+
+        assert!(paths[0].ends_with("X0000001_2025-11-30_transactions.csv"));
+        let transactions = fs::read_to_string(&paths[0]).unwrap();
+        assert!(transactions.starts_with("account_id,kind,"));
+        assert!(transactions.contains("X0000001,BUYSTOCK,"));
+        assert!(transactions.contains("AAPL"));
+        let _ = fs::remove_dir_all(export_dir);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::utils::archiver::{ARCHIVE_DIR, FAILED_DIR};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+// browsers and office suites write to these while a file is still being
+// saved, and rename them once it's complete
+const TEMPORARY_EXTENSIONS: [&str; 7] = [
+    "crdownload",
+    "part",
+    "partial",
+    "download",
+    "tmp",
+    "swp",
+    "lock",
+];
+
+// decides which files under a configured directory get ingested.
+// globs are matched against the path relative to the directory,
+// e.g. "2025/*.ofx" or "**/*.qfx"
+pub struct FileFilter {
+    root: PathBuf,
+    recursive: bool,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl FileFilter {
+    pub fn new(
+        root: &Path,
+        recursive: bool,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(FileFilter {
+            root: root.to_path_buf(),
+            recursive,
+            // no include patterns means everything is included
+            include: if include.is_empty() {
+                None
+            } else {
+                Some(build_glob_set(include)?)
+            },
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    // every file under the root that should be parsed, in a stable order
+    pub fn scan(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let mut walker = WalkDir::new(&self.root).sort_by_file_name();
+        if !self.recursive {
+            walker = walker.max_depth(1);
+        }
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        let entries = walker
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !self.is_skipped_dir(e));
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type().is_file() && self.accepts(entry.path()) {
+                files.push(entry.into_path());
+            }
+        }
+        Ok(files)
+    }
+
+    // whether a single file under the root would have been picked up by scan
+    pub fn accepts(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if !self.recursive && relative.components().count() > 1 {
+            return false;
+        }
+        // a hidden or moneyd-owned folder anywhere along the way
+        // rules the file out, same as scan skipping the folder
+        let mut parents = relative.parent().into_iter().flat_map(|p| p.components());
+        if parents.any(|c| is_skipped_dir_name(&c.as_os_str().to_string_lossy())) {
+            return false;
+        }
+        if is_hidden(path) || is_temporary(path) {
+            return false;
+        }
+        if let Some(include) = &self.include
+            && !include.is_match(relative)
+        {
+            return false;
+        }
+        !self.exclude.is_match(relative)
+    }
+
+    fn is_skipped_dir(&self, entry: &DirEntry) -> bool {
+        entry.file_type().is_dir() && is_skipped_dir_name(&entry.file_name().to_string_lossy())
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, Box<dyn std::error::Error>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| format!("Bad glob {:?}: {}", pattern, e))?);
+    }
+    Ok(builder.build()?)
+}
+
+// archive/ and failed/ hold files we've already dealt with
+fn is_skipped_dir_name(name: &str) -> bool {
+    name.starts_with('.') || name == ARCHIVE_DIR || name == FAILED_DIR
+}
+
+// .DS_Store, ._resource forks and friends
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().starts_with('.'))
+        .unwrap_or(false)
+}
+
+pub fn is_temporary(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // "statement.ofx~" backups and "~$statement.xlsx" office lock files
+    if name.ends_with('~') || name.starts_with("~$") {
+        return true;
+    }
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| TEMPORARY_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn patterns(p: &[&str]) -> Vec<String> {
+        p.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_temporary_and_hidden_files() {
+        assert!(is_temporary(Path::new("/tmp/amex.qfx.crdownload")));
+        assert!(is_temporary(Path::new("/tmp/amex.qfx.PART")));
+        assert!(is_temporary(Path::new("/tmp/amex.qfx~")));
+        assert!(!is_temporary(Path::new("/tmp/amex.qfx")));
+        assert!(is_hidden(Path::new("/tmp/.DS_Store")));
+        assert!(!is_hidden(Path::new("/tmp/amex.qfx")));
+    }
+
+    #[test]
+    fn test_scan_recurses_and_filters() {
+        let dir = std::env::temp_dir().join("moneyd-filescan-test");
+        let _ = fs::remove_dir_all(&dir);
+        for sub in ["2025", "archive/2025/10", "failed", ".cache", "drafts"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in [
+            "activity.ofx",
+            ".DS_Store",
+            "download.qfx.crdownload",
+            "2025/november.qfx",
+            "2025/notes.txt",
+            "archive/2025/10/october.ofx",
+            "failed/broken.ofx",
+            ".cache/cached.ofx",
+            "drafts/draft.ofx",
+        ] {
+            fs::write(dir.join(file), "<OFX>").unwrap();
+        }
+
+        let everything = FileFilter::new(&dir, true, &[], &[]).unwrap();
+        let found = everything.scan().unwrap();
+        assert_eq!(
+            found,
+            vec![
+                dir.join("2025/notes.txt"),
+                dir.join("2025/november.qfx"),
+                dir.join("activity.ofx"),
+                dir.join("drafts/draft.ofx"),
+            ]
+        );
+
+        let filtered = FileFilter::new(
+            &dir,
+            true,
+            &patterns(&["*.ofx", "*.qfx"]),
+            &patterns(&["drafts/**"]),
+        )
+        .unwrap();
+        assert_eq!(
+            filtered.scan().unwrap(),
+            vec![dir.join("2025/november.qfx"), dir.join("activity.ofx")]
+        );
+        assert!(filtered.accepts(&dir.join("2025/december.ofx")));
+        assert!(!filtered.accepts(&dir.join("drafts/other.ofx")));
+        assert!(!filtered.accepts(&dir.join("archive/2025/10/october.ofx")));
+
+        let flat = FileFilter::new(&dir, false, &[], &[]).unwrap();
+        assert_eq!(flat.scan().unwrap(), vec![dir.join("activity.ofx")]);
+        assert!(!flat.accepts(&dir.join("2025/november.qfx")));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,18 +1,29 @@
 use crate::Env;
-use crate::ingestion::TransactionBatchHolder;
+use crate::accounts::{AccountConfig, AccountKey, sync_accounts};
+use crate::ingestion::{TransactionBatch, TransactionBatchHolder};
+use crate::mailbox::{PROCESSED_MESSAGES_FILE, add_processed_messages};
 use crate::service::statementservice::create_statement;
+use crate::utils::archiver::{AfterUpload, FileSummary, apply_after_upload};
+use crate::utils::consumed::{CONSUMED_FILE, ConsumedFile, add_consumed};
 use crate::utils::logintransporter::LoginResponse;
+use crate::utils::paths::state_file;
 use crate::utils::statementtransporter::StatementTransport;
 use crate::{
     service::transactionservice::create_transactions,
-    utils::transactiontransporter::TransactionTransport,
+    utils::transactiontransporter::{TransactionResponse, TransactionTransport},
 };
 use chrono::{DateTime, TimeZone, Utc};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::{env, fs};
+use tracing::{Instrument, error, info, info_span};
 
-const HASH_PATH: &str = "./config/existing-hashes.txt";
+// hashes of every transaction uploaded so far, kept in the state dir
+pub const HASH_FILE: &str = "existing-hashes.txt";
+// and which statement each of them went up with
+pub const STATEMENT_HASHES_FILE: &str = "statement-hashes.json";
 
 pub fn parse_ofx_date(date_str: &str) -> Result<String, Box<dyn std::error::Error>> {
     if let Ok(dt) = chrono::NaiveDate::parse_from_str(date_str, "%Y%m%d%H%M%S") {
@@ -33,8 +44,9 @@ pub fn parse_ofx_date(date_str: &str) -> Result<String, Box<dyn std::error::Erro
         return Ok(dt_utc.to_rfc3339());
     }
 
-    // If parsing fails, return original string
-    Ok(date_str.to_string())
+    // not ofx-shaped at all (camt and friends use iso dates),
+    // let the general parser have a go
+    parse_and_format_date(date_str)
 }
 pub fn parse_and_format_date(date_str: &str) -> Result<String, Box<dyn std::error::Error>> {
     // Try to parse as ISO 8601 format
@@ -42,6 +54,12 @@ pub fn parse_and_format_date(date_str: &str) -> Result<String, Box<dyn std::erro
         return Ok(dt.to_rfc3339());
     }
 
+    // yyyy-MM-ddTHH:mm:ss without an offset, as camt writes them
+    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S%.f") {
+        let dt_utc = Utc.from_utc_datetime(&dt.date().and_hms_opt(0, 0, 0).unwrap());
+        return Ok(dt_utc.to_rfc3339());
+    }
+
     // Try to parse as yyyy-MM-dd format
     if let Ok(dt) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
         // Convert to datetime at midnight UTC
@@ -63,60 +81,248 @@ pub fn parse_and_format_date(date_str: &str) -> Result<String, Box<dyn std::erro
     // If parsing fails, return original string
     Ok(date_str.to_string())
 }
+
+// "2025-11-29T00:00:00+00:00" -> "2025-11-29"
+pub fn short_date(date: &str) -> String {
+    date.get(..10).unwrap_or(date).to_string()
+}
+#[derive(Clone)]
 pub struct AuthorizationData {
     pub auth_token: String,
     pub api_key: String,
+    pub base_url: String,
 }
 
 pub fn get_env_vars() -> Env {
     let mut api_key: Option<String> = None;
     let mut base_url: Option<String> = None;
+    let mut email: Option<String> = None;
+    let mut password: Option<String> = None;
     for (key, value) in env::vars() {
         if key.eq("API_KEY") {
             api_key = Some(value);
         } else if key.eq("BASE_URL") {
             base_url = Some(value);
+        } else if key.eq("MONEYD_EMAIL") {
+            email = Some(value);
+        } else if key.eq("MONEYD_PASSWORD") {
+            password = Some(value);
         }
     }
-    let envs: Env = Env { api_key, base_url };
+    let envs: Env = Env {
+        api_key,
+        base_url,
+        email,
+        password,
+    };
     envs
 }
+// each batch comes back with its statement id or upload error filled in
 pub async fn post_statements_and_transactions(
-    mut transaction_batch_data: Vec<TransactionBatchHolder>,
+    transaction_batch_data: &mut [TransactionBatchHolder],
     login_data: &LoginResponse,
     auth_data: &AuthorizationData,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    for institution_batch_holder in transaction_batch_data.iter_mut() {
-        let statement_data: StatementTransport = StatementTransport {
-            banking_user_id: login_data.user.id,
-            institution_id: institution_batch_holder.institution_id,
-            // TODO: gotta figure out these date formats from the new file
-            // deal with it later. can't be too hard...
-            period_start: parse_and_format_date("2025-11-01").unwrap(),
-            period_end: parse_and_format_date("2025-11-17").unwrap(),
-        };
+    // every configured account we're about to upload a statement for
+    let mut accounts: Vec<(i32, AccountConfig)> = Vec::new();
+    for holder in transaction_batch_data.iter() {
+        for account in holder
+            .transaction_batches
+            .iter()
+            .filter_map(|b| b.account.as_ref())
+        {
+            let key = AccountKey::new(login_data.user.id, holder.institution_id, account);
+            if !accounts
+                .iter()
+                .any(|(id, a)| AccountKey::new(login_data.user.id, *id, a) == key)
+            {
+                accounts.push((holder.institution_id, account.clone()));
+            }
+        }
+    }
+    let account_ids = sync_accounts(&accounts, login_data, auth_data).await;
 
+    let mut failures: usize = 0;
+    for institution_batch_holder in transaction_batch_data.iter_mut() {
+        // a file only counts as done once every statement in it made it up
+        let mut file_errors: HashMap<PathBuf, Option<String>> = HashMap::new();
+        // and the same for each entry of an archive
+        let mut entries: HashMap<String, (ConsumedFile, bool)> = HashMap::new();
+        // and each email
+        let mut messages: HashMap<String, bool> = HashMap::new();
         for batch in institution_batch_holder.transaction_batches.iter_mut() {
-            if !batch.all_transactions_exist {
-                let stmt = create_statement(&statement_data, &auth_data).await.unwrap();
-                for t in batch.transactions.iter_mut() {
-                    t.statement_id = Some(stmt.statement_id);
-                }
-                let txn_batch_result =
-                    create_transactions(&batch.transactions, stmt.statement_id, &auth_data).await;
-                match txn_batch_result {
-                    Ok(_) => {
-                        add_multiple_hashes(HASH_PATH, &batch.hashes);
-                    }
-                    _ => {}
-                }
+            // everything left out in review was settled before the upload,
+            // and a retry skips what made it up the first time
+            let result = if batch.all_transactions_exist
+                || batch.transactions.is_empty()
+                || batch.statement_id.is_some()
+            {
+                Ok(())
+            } else {
+                batch.upload_error = None;
+                let account_id = batch.account.as_ref().and_then(|a| {
+                    let key = AccountKey::new(
+                        login_data.user.id,
+                        institution_batch_holder.institution_id,
+                        a,
+                    );
+                    account_ids.get(&key).copied()
+                });
+                let span = info_span!(
+                    "upload",
+                    institution = %institution_batch_holder.institution_name,
+                    file = %batch.source_file.display(),
+                    period_end = %batch.period_end,
+                );
+                post_batch(
+                    batch,
+                    institution_batch_holder.institution_id,
+                    account_id,
+                    login_data,
+                    auth_data,
+                )
+                .instrument(span)
+                .await
+            };
+            if let Some(consumed) = &batch.consumed {
+                let entry = entries
+                    .entry(consumed.path.clone())
+                    .or_insert_with(|| (consumed.clone(), true));
+                entry.1 &= result.is_ok();
+            }
+            if let Some(message_id) = &batch.message_id {
+                *messages.entry(message_id.clone()).or_insert(true) &= result.is_ok();
             }
+            let entry = file_errors.entry(batch.source_file.clone()).or_default();
+            if let Err(e) = result {
+                error!(file = %batch.source_file.display(), "Upload failed: {}", e);
+                failures += 1;
+                entry.get_or_insert(e.to_string());
+                batch.upload_error = Some(e.to_string());
+            }
+        }
+
+        let consumed: Vec<ConsumedFile> = entries
+            .into_values()
+            .filter(|(_, uploaded)| *uploaded)
+            .map(|(c, _)| c)
+            .collect();
+        if !consumed.is_empty() {
+            add_consumed(state_file(CONSUMED_FILE)?, &consumed)?;
+        }
+        let processed: HashSet<String> = messages
+            .into_iter()
+            .filter(|(_, uploaded)| *uploaded)
+            .map(|(id, _)| id)
+            .collect();
+        if !processed.is_empty() {
+            add_processed_messages(state_file(PROCESSED_MESSAGES_FILE)?, &processed)?;
         }
+        finish_files(institution_batch_holder, &file_errors);
     }
+
+    if failures > 0 {
+        return Err(format!("{} statement(s) failed to upload", failures).into());
+    }
+    Ok(())
+}
+
+async fn post_batch(
+    batch: &mut TransactionBatch,
+    institution_id: i32,
+    account_id: Option<i32>,
+    login_data: &LoginResponse,
+    auth_data: &AuthorizationData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let statement_data: StatementTransport = StatementTransport {
+        banking_user_id: login_data.user.id,
+        institution_id,
+        account_id,
+        period_start: batch.period_start.clone(),
+        period_end: batch.period_end.clone(),
+    };
+    let stmt = create_statement(&statement_data, auth_data).await?;
+    batch.statement_id = Some(stmt.statement_id);
+    for t in batch.transactions.iter_mut() {
+        t.statement_id = Some(stmt.statement_id);
+    }
+    create_transactions(&batch.transactions, auth_data).await?;
+    add_multiple_hashes(state_file(HASH_FILE)?, &batch.hashes)?;
+    add_statement_hashes(
+        state_file(STATEMENT_HASHES_FILE)?,
+        stmt.statement_id,
+        &batch.hashes,
+    )?;
+    info!(
+        statement = stmt.statement_id,
+        transactions = batch.transactions.len(),
+        "Uploaded statement"
+    );
     Ok(())
 }
 
-// "./config/existing-hashes.txt"
+// runs the directory's after_upload action on files that went up cleanly.
+// files that failed to upload stay where they are and are retried next
+// run, failed/ is only for files that can't be parsed. files with nothing
+// new were already dealt with when they were read, see process_file
+fn finish_files(holder: &TransactionBatchHolder, file_errors: &HashMap<PathBuf, Option<String>>) {
+    if holder.after_upload == AfterUpload::Leave {
+        return;
+    }
+    for (file, error) in file_errors.iter() {
+        if error.is_some() {
+            info!("Leaving {} in place to retry next run", file.display());
+            continue;
+        }
+        let batches: Vec<&TransactionBatch> = holder
+            .transaction_batches
+            .iter()
+            .filter(|b| &b.source_file == file)
+            .collect();
+        if batches.iter().all(|b| b.all_transactions_exist) {
+            continue;
+        }
+        finish_file(
+            file,
+            &holder.directory_path,
+            holder.after_upload,
+            &holder.institution_name,
+            &batches,
+        );
+    }
+}
+
+// the after_upload action for one file, named after the statements in it
+pub fn finish_file(
+    file: &Path,
+    directory_path: &Path,
+    after_upload: AfterUpload,
+    institution: &str,
+    batches: &[&TransactionBatch],
+) {
+    let summary = FileSummary {
+        institution,
+        account_id: batches
+            .iter()
+            .find_map(|b| b.identity.account_id.as_deref()),
+        period_start: batches
+            .iter()
+            .map(|b| b.period_start.as_str())
+            .min()
+            .unwrap_or_default(),
+        period_end: batches
+            .iter()
+            .map(|b| b.period_end.as_str())
+            .max()
+            .unwrap_or_default(),
+    };
+    match apply_after_upload(file, directory_path, after_upload, &summary) {
+        Ok(Some(target)) => info!("Moved {} to {}", file.display(), target.display()),
+        Ok(None) => {}
+        Err(e) => error!("Could not move {}: {}", file.display(), e),
+    }
+}
+
 pub fn hash_transaction_data(txn: &TransactionTransport) -> String {
     let mut hasher = Sha256::new();
 
@@ -126,27 +332,30 @@ pub fn hash_transaction_data(txn: &TransactionTransport) -> String {
     // Add more string fields as needed
 
     // Optionally, add integer fields as bytes
-    hasher.update(&txn.amount.to_le_bytes());
-    hasher.update(&txn.refnum.as_bytes());
+    hasher.update(txn.amount.to_le_bytes());
+    hasher.update(txn.refnum.as_bytes());
 
     // Finalize and format as hex
     let result = hasher.finalize();
     format!("{:x}", result)
 }
 
-pub fn get_transaction_hashes(path: &str) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
-    let hashes: HashSet<String> = fs::read_to_string(path)?
-        .lines()
-        .map(|s| s.to_string())
-        .collect();
-
-    Ok(hashes)
+// nothing uploaded yet (a fresh state dir) is an empty set
+pub fn get_transaction_hashes(
+    path: impl AsRef<Path>,
+) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().map(|s| s.to_string()).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e.into()),
+    }
 }
 pub fn add_multiple_hashes(
-    path: &str,
+    path: impl AsRef<Path>,
     new_hashes: &HashSet<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut existing_hashes = get_transaction_hashes(path).unwrap();
+    let path = path.as_ref();
+    let mut existing_hashes = get_transaction_hashes(path)?;
 
     for hash in new_hashes.iter() {
         if !existing_hashes.contains(hash) {
@@ -162,6 +371,87 @@ pub fn add_multiple_hashes(
     fs::write(path, hash_string)?;
     Ok(())
 }
+pub fn load_statement_hashes(
+    path: impl AsRef<Path>,
+) -> Result<BTreeMap<i32, HashSet<String>>, Box<dyn std::error::Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn add_statement_hashes(
+    path: impl AsRef<Path>,
+    statement_id: i32,
+    hashes: &HashSet<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let mut statements = load_statement_hashes(path)?;
+    statements
+        .entry(statement_id)
+        .or_default()
+        .extend(hashes.iter().cloned());
+    fs::write(path, serde_json::to_string_pretty(&statements)?)?;
+    Ok(())
+}
+
+// after a statement is deleted, so its transactions are new again next
+// time the file is read. statements uploaded before their hashes were
+// kept only have what can be worked out from the deleted rows
+pub fn forget_statement(
+    statement_id: i32,
+    deleted: &[TransactionResponse],
+) -> Result<(), Box<dyn std::error::Error>> {
+    forget_statement_in(
+        &state_file(HASH_FILE)?,
+        &state_file(STATEMENT_HASHES_FILE)?,
+        statement_id,
+        deleted,
+    )
+}
+
+fn forget_statement_in(
+    hash_path: &Path,
+    statement_hashes_path: &Path,
+    statement_id: i32,
+    deleted: &[TransactionResponse],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut statements = load_statement_hashes(statement_hashes_path)?;
+    let mut forgotten = statements.remove(&statement_id).unwrap_or_default();
+    // the backend doesn't keep the refnum, so this only finds the ones without
+    forgotten.extend(deleted.iter().map(|t| {
+        hash_transaction_data(&TransactionTransport {
+            statement_id: Some(t.statement_id),
+            description: t.description.clone(),
+            amount: t.amount,
+            transaction_date: t.transaction_date.clone(),
+            refnum: String::new(),
+            transaction_type_lookup_code: t.transaction_type_lookup_code,
+        })
+    }));
+
+    let mut hashes = get_transaction_hashes(hash_path)?;
+    let before = hashes.len();
+    hashes.retain(|h| !forgotten.contains(h));
+    if hashes.len() != before {
+        let hash_string = hashes
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(hash_path, hash_string)?;
+    }
+    if statement_hashes_path.exists() {
+        fs::write(
+            statement_hashes_path,
+            serde_json::to_string_pretty(&statements)?,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
 pub fn add_hash(path: &str, new_hash: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut hashes = get_transaction_hashes(path).unwrap();
     if !hashes.contains(new_hash) {
@@ -178,6 +468,12 @@ pub fn add_hash(path: &str, new_hash: &str) -> Result<(), Box<dyn std::error::Er
 }
 
 #[cfg(test)]
+// kept as they were written, older than the lint set the build uses now
+#[allow(
+    clippy::len_zero,
+    clippy::manual_unwrap_or_default,
+    clippy::unnecessary_get_then_check
+)]
 mod tests {
     use super::*;
     const HASH_PATH: &str = "./config/existing-hashes-test.txt";
@@ -208,6 +504,46 @@ mod tests {
         clean_test_file(HASH_PATH);
     }
 
+    #[test]
+    fn test_forget_statement() {
+        let dir = std::env::temp_dir().join(format!("moneyd-forget-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (hash_path, statement_path) = (dir.join(HASH_FILE), dir.join(STATEMENT_HASHES_FILE));
+        let deleted = TransactionResponse {
+            transaction_id: 7,
+            statement_id: 41,
+            description: String::from("Coffee"),
+            amount: 450,
+            transaction_date: String::from("2025-11-03T00:00:00+00:00"),
+            transaction_type_lookup_code: 10,
+            category: None,
+        };
+        let kept = HashSet::from([String::from("KEPT")]);
+        add_multiple_hashes(&hash_path, &kept).unwrap();
+        add_statement_hashes(&statement_path, 41, &HashSet::from([String::from("FITID")])).unwrap();
+        add_statement_hashes(&statement_path, 42, &kept).unwrap();
+        // one recorded at upload, one worked out from the row
+        add_multiple_hashes(&hash_path, &HashSet::from([String::from("FITID")])).unwrap();
+        add_multiple_hashes(&hash_path, &HashSet::from([row_hash(&deleted)])).unwrap();
+
+        forget_statement_in(&hash_path, &statement_path, 41, &[deleted]).unwrap();
+        assert_eq!(get_transaction_hashes(&hash_path).unwrap(), kept);
+        let statements = load_statement_hashes(&statement_path).unwrap();
+        assert_eq!(statements.keys().collect::<Vec<_>>(), vec![&42]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn row_hash(t: &TransactionResponse) -> String {
+        hash_transaction_data(&TransactionTransport {
+            statement_id: None,
+            description: t.description.clone(),
+            amount: t.amount,
+            transaction_date: t.transaction_date.clone(),
+            refnum: String::new(),
+            transaction_type_lookup_code: t.transaction_type_lookup_code,
+        })
+    }
+
     fn clean_test_file(path: &str) {
         let template_data = fs::read_to_string(HASH_TEMPLATE_PATH).unwrap();
         let _ = fs::write(path, template_data);
@@ -0,0 +1,287 @@
+use crate::accounts::mask_account_id;
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, IsTerminal},
+    path::Path,
+    sync::Mutex,
+};
+use tracing::{Level, level_filters::LevelFilter};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+// where the console half of the log goes
+pub enum ConsoleLog {
+    Plain,
+    // the daemon's output is read back later, a one-off run's isn't
+    Timestamped,
+    // a full-screen ui owns the terminal, only --log-file gets anything
+    Off,
+}
+
+// -q lets only errors through, nothing means info, -v debug and -vv trace.
+// MONEYD_LOG takes a filter like "moneyd=trace,reqwest=debug" and beats both.
+// logs go to stderr, so they never end up in --output json or csv
+pub fn init_logging(
+    verbosity: i8,
+    log_file: Option<&Path>,
+    console_log: ConsoleLog,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let level = match verbosity {
+        i8::MIN..=-1 => LevelFilter::ERROR,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    // other crates only get a say at warn, their info is noise to us
+    let filter = EnvFilter::try_from_env("MONEYD_LOG").unwrap_or_else(|_| {
+        EnvFilter::new(format!("{},moneyd={}", level.min(LevelFilter::WARN), level))
+    });
+
+    let console = tracing_subscriber::fmt::layer()
+        .with_writer(Masking(io::stderr))
+        .with_ansi(io::stderr().is_terminal())
+        .with_target(false);
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+    match console_log {
+        ConsoleLog::Plain => layers.push(console.without_time().boxed()),
+        ConsoleLog::Timestamped => layers.push(console.boxed()),
+        ConsoleLog::Off => {}
+    }
+
+    // one json object per line, with the file/institution/upload spans
+    // each event happened in
+    if let Some(log_file) = log_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .map_err(|e| format!("{}: {}", log_file.display(), e))?;
+        layers.push(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_writer(Masking(Mutex::new(file)))
+                .boxed(),
+        );
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()?;
+    Ok(())
+}
+
+// whether moneyd itself logs at debug. a filter like reqwest=debug
+// turns up the max level, but shouldn't reveal anything of ours
+fn reveal() -> bool {
+    tracing::enabled!(target: "moneyd", Level::DEBUG)
+}
+
+// everything that's logged goes through this, so account numbers and
+// amounts are masked even where nobody thought to use redact::
+struct Masking<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Masking<M> {
+    type Writer = MaskingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        MaskingWriter {
+            inner: self.0.make_writer(),
+            reveal: reveal(),
+        }
+    }
+}
+
+struct MaskingWriter<W> {
+    inner: W,
+    reveal: bool,
+}
+
+impl<W: io::Write> io::Write for MaskingWriter<W> {
+    // the fmt layers write each event in one go, so a number is
+    // never split across two calls
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.reveal {
+            return self.inner.write(buf);
+        }
+        let text = String::from_utf8_lossy(buf);
+        self.inner.write_all(mask_numbers(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// "acct 123456789 paid -1,204.50" -> "acct ...6789 paid -***". only
+// whole words count, so timestamps, hashes and file names are left be
+fn mask_numbers(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars().chain(std::iter::once('\n')) {
+        if c.is_alphanumeric() || matches!(c, '.' | ',' | '_') {
+            word.push(c);
+            continue;
+        }
+        // a full stop or a comma after a number isn't part of it
+        let trimmed = word.trim_end_matches(['.', ',']);
+        masked.push_str(&mask_word(trimmed));
+        masked.push_str(&word[trimmed.len()..]);
+        masked.push(c);
+        word.clear();
+    }
+    masked.pop();
+    masked
+}
+
+fn mask_word(word: &str) -> String {
+    let digits = word.trim_start_matches(['X', 'x', '*']);
+    let all_digits = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+    // account and card numbers, as is or with the start x-ed out
+    if all_digits && (digits.len() >= 8 || (digits.len() >= 4 && digits.len() < word.len())) {
+        return mask_account_id(word);
+    }
+    if let Some((whole, cents)) = word.split_once('.')
+        && !whole.is_empty()
+        && whole.chars().all(|c| c.is_ascii_digit() || c == ',')
+        && cents.len() == 2
+        && cents.chars().all(|c| c.is_ascii_digit())
+    {
+        return String::from("***");
+    }
+    word.to_string()
+}
+
+// parser errors quote the input they choke on, which can be
+// a whole statement row
+fn mask_quoted(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut parts = text.split('\'');
+    masked.push_str(parts.next().unwrap_or_default());
+    let mut quoted = true;
+    let mut open = String::new();
+    for part in parts {
+        if quoted {
+            open = part.to_string();
+        } else {
+            masked.push_str("'***'");
+            masked.push_str(part);
+        }
+        quoted = !quoted;
+    }
+    // an unmatched quote isn't a value
+    if !quoted {
+        masked.push('\'');
+        masked.push_str(&open);
+    }
+    mask_numbers(&masked)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Sensitive {
+    Account,
+    Amount,
+    Token,
+    Text,
+}
+
+// a value that's only logged in full when debug logging is on.
+// log it with %, e.g. info!(account = %redact::account(id), "...")
+pub struct Redacted {
+    kind: Sensitive,
+    value: String,
+}
+
+impl Redacted {
+    fn render(&self, reveal: bool) -> String {
+        if reveal {
+            return self.value.clone();
+        }
+        match self.kind {
+            Sensitive::Account => mask_account_id(&self.value),
+            Sensitive::Amount => String::from("***"),
+            Sensitive::Token => String::from("[redacted]"),
+            Sensitive::Text => mask_quoted(&self.value),
+        }
+    }
+}
+
+impl fmt::Display for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(reveal()))
+    }
+}
+
+pub mod redact {
+    use super::{Redacted, Sensitive};
+    use std::fmt;
+
+    // "...1234"
+    pub fn account(value: &str) -> Redacted {
+        Redacted {
+            kind: Sensitive::Account,
+            value: value.to_string(),
+        }
+    }
+
+    pub fn amount(value: impl fmt::Display) -> Redacted {
+        Redacted {
+            kind: Sensitive::Amount,
+            value: value.to_string(),
+        }
+    }
+
+    pub fn token(value: &str) -> Redacted {
+        Redacted {
+            kind: Sensitive::Token,
+            value: value.to_string(),
+        }
+    }
+
+    // free text like an error message, quoted values and numbers masked
+    pub fn text(value: &str) -> Redacted {
+        Redacted {
+            kind: Sensitive::Text,
+            value: value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let account = redact::account("XXXXXXXXXXXX1234");
+        assert_eq!(account.render(false), "...1234");
+        assert_eq!(account.render(true), "XXXXXXXXXXXX1234");
+        assert_eq!(redact::amount("-42.17").render(false), "***");
+        assert_eq!(redact::amount(-42.17).render(true), "-42.17");
+        assert_eq!(redact::token("eyJhbGciOi").render(false), "[redacted]");
+        // no subscriber in tests, so nothing is revealed
+        assert_eq!(redact::token("eyJhbGciOi").to_string(), "[redacted]");
+    }
+
+    #[test]
+    fn test_masking_free_text() {
+        assert_eq!(
+            mask_numbers("acct 123456789 paid -1,204.50, card XXXXXXXXXXXX1234."),
+            "acct ...6789 paid -***, card ...1234."
+        );
+        // left alone: timestamps, short numbers, hashes and file names
+        let untouched = "2026-10-19T06:02:17.123456Z uploaded 12 of 2025 from a1b2c3d4e5f6a7b8 in stmt_20251101.ofx";
+        assert_eq!(mask_numbers(untouched), untouched);
+
+        assert_eq!(
+            redact::text("could not parse: Invalid date 'GROCERY 4.50' on line 3").render(false),
+            "could not parse: Invalid date '***' on line 3"
+        );
+        assert_eq!(redact::text("it's 12345678").render(false), "it's ...5678");
+    }
+}
@@ -1,5 +1,14 @@
+pub mod accounttransporter;
+pub mod archiver;
+pub mod consumed;
 pub mod csvutil;
+pub mod filescan;
 pub mod globalutil;
+pub mod logging;
 pub mod logintransporter;
+pub mod output;
+pub mod paths;
+pub mod statementmodel;
 pub mod statementtransporter;
 pub mod transactiontransporter;
+pub mod unpack;
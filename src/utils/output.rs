@@ -0,0 +1,197 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    io,
+    sync::atomic::{AtomicI8, Ordering},
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+// -q is -1, nothing is 0, each -v adds one
+static VERBOSITY: AtomicI8 = AtomicI8::new(0);
+
+pub fn set_verbosity(verbosity: i8) {
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> i8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+// progress chatter, silenced by -q. it goes to stderr like the logs,
+// so stdout only ever holds the results
+pub fn note(message: &str) {
+    if verbosity() >= 0 {
+        eprintln!("{}", message);
+    }
+}
+
+pub fn print_records<T: Serialize>(
+    format: OutputFormat,
+    records: &[T],
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(records)?),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            for record in records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Table => print!("{}", render_table(records)?),
+    }
+    Ok(())
+}
+
+// for commands that do something rather than list things:
+// a sentence for people, the record itself for scripts
+pub fn print_outcome<T: Serialize>(
+    format: OutputFormat,
+    record: &T,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Table => println!("{}", message),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(record)?),
+        OutputFormat::Csv => print_records(format, std::slice::from_ref(record))?,
+    }
+    Ok(())
+}
+
+// -450 -> "-4.50", for where amounts are shown to people
+pub fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+// the other way: "-4.5" -> -450, for amounts people typed in
+pub fn parse_cents(text: &str) -> Result<i64, String> {
+    let invalid = || format!("'{}' is not an amount like 12.50", text);
+    let text = text.trim();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (units, cents) = digits.split_once('.').unwrap_or((digits, ""));
+    if units.is_empty()
+        || cents.len() > 2
+        || !(units.chars().chain(cents.chars())).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let units: i64 = units.parse().map_err(|_| invalid())?;
+    let cents: i64 = format!("{:0<2}", cents).parse().map_err(|_| invalid())?;
+    Ok(sign * (units * 100 + cents))
+}
+
+// columns come from the field names, in declaration order
+pub fn render_table<T: Serialize>(records: &[T]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut headers: Vec<String> = Vec::new();
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for record in records {
+        let Value::Object(fields) = serde_json::to_value(record)? else {
+            return Err("Only structs can be printed as a table".into());
+        };
+        if headers.is_empty() {
+            headers = fields.keys().cloned().collect();
+        }
+        rows.push(
+            fields
+                .values()
+                .map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                })
+                .collect(),
+        );
+    }
+    if rows.is_empty() {
+        return Ok(String::from("(nothing found)\n"));
+    }
+
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain([h.len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut table = String::new();
+    for row in [headers].iter().chain(rows.iter()) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        statement_id: i32,
+        description: String,
+        refnum: Option<String>,
+    }
+
+    #[test]
+    fn test_render_table_keeps_field_order() {
+        let rows = vec![
+            Row {
+                statement_id: 7,
+                description: String::from("Coffee"),
+                refnum: None,
+            },
+            Row {
+                statement_id: 12,
+                description: String::from("Groceries"),
+                refnum: Some(String::from("abc")),
+            },
+        ];
+        let table = render_table(&rows).unwrap();
+        assert_eq!(
+            table,
+            "statement_id  description  refnum\n\
+             7             Coffee\n\
+             12            Groceries    abc\n"
+        );
+        assert_eq!(render_table::<Row>(&[]).unwrap(), "(nothing found)\n");
+    }
+
+    #[test]
+    fn test_format_cents() {
+        assert_eq!(format_cents(-450), "-4.50");
+        assert_eq!(format_cents(150000), "1500.00");
+        assert_eq!(format_cents(-5), "-0.05");
+        assert_eq!(format_cents(0), "0.00");
+    }
+
+    #[test]
+    fn test_parse_cents() {
+        assert_eq!(parse_cents("-4.5"), Ok(-450));
+        assert_eq!(parse_cents("+1500"), Ok(150000));
+        assert_eq!(parse_cents(" 0.05 "), Ok(5));
+        assert!(parse_cents("12.345").is_err());
+        assert!(parse_cents("--5").is_err());
+        assert!(parse_cents("").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+pub const CONFIG_FILE: &str = "moneyd-config.toml";
+// where config and state used to live, relative to wherever moneyd ran
+pub const LEGACY_DIR: &str = "./config";
+
+// moneyd-config.toml is looked up in this order:
+//   1. --config <path>
+//   2. $MONEYD_CONFIG
+//   3. $XDG_CONFIG_HOME/moneyd/moneyd-config.toml (~/.config/moneyd/ when unset)
+//   4. ./config/moneyd-config.toml, for setups from before
+// 1 and 2 win whenever they're set. 4 is only used when it exists and 3
+// doesn't, so 3 is what gets reported when there's no config at all
+pub fn config_file(cli: Option<&Path>) -> PathBuf {
+    resolve_config_file(cli, &|k| env::var(k).ok(), &|p| p.exists())
+}
+
+// $XDG_CONFIG_HOME/moneyd, also where a .env can go
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config", &|k| env::var(k).ok())
+}
+
+// $XDG_STATE_HOME/moneyd, for the hashes of uploaded transactions,
+// consumed archive entries and processed emails
+pub fn state_dir() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state", &|k| env::var(k).ok())
+}
+
+// a file in the state dir, which is created if needed. a copy still
+// sitting in ./config from before gets carried over the first time
+pub fn state_file(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = state_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(name);
+    let legacy = Path::new(LEGACY_DIR).join(name);
+    if !path.exists() && legacy.is_file() {
+        fs::copy(&legacy, &path)?;
+    }
+    Ok(path)
+}
+
+fn resolve_config_file(
+    cli: Option<&Path>,
+    env: &dyn Fn(&str) -> Option<String>,
+    exists: &dyn Fn(&Path) -> bool,
+) -> PathBuf {
+    if let Some(path) = cli {
+        return path.to_path_buf();
+    }
+    if let Some(path) = env("MONEYD_CONFIG").filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    let xdg = xdg_dir("XDG_CONFIG_HOME", ".config", env).join(CONFIG_FILE);
+    let legacy = Path::new(LEGACY_DIR).join(CONFIG_FILE);
+    if !exists(&xdg) && exists(&legacy) {
+        return legacy;
+    }
+    xdg
+}
+
+// the spec says relative values are to be ignored
+fn xdg_dir(variable: &str, fallback: &str, env: &dyn Fn(&str) -> Option<String>) -> PathBuf {
+    let base = env(variable)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env("HOME").map(|home| Path::new(&home).join(fallback)));
+    match base {
+        Some(base) => base.join("moneyd"),
+        None => PathBuf::from(LEGACY_DIR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |k| vars.get(k).cloned()
+    }
+
+    #[test]
+    fn test_config_lookup_order() {
+        let env = lookup(&[("HOME", "/home/me"), ("MONEYD_CONFIG", "/etc/moneyd.toml")]);
+        let nothing_exists = |_: &Path| false;
+
+        let cli = Path::new("/tmp/cli.toml");
+        assert_eq!(
+            resolve_config_file(Some(cli), &env, &nothing_exists),
+            PathBuf::from("/tmp/cli.toml")
+        );
+        assert_eq!(
+            resolve_config_file(None, &env, &nothing_exists),
+            PathBuf::from("/etc/moneyd.toml")
+        );
+
+        let env = lookup(&[("HOME", "/home/me")]);
+        assert_eq!(
+            resolve_config_file(None, &env, &nothing_exists),
+            PathBuf::from("/home/me/.config/moneyd/moneyd-config.toml")
+        );
+        // an old ./config setup keeps working until the xdg file exists
+        let only_legacy = |p: &Path| p.starts_with(LEGACY_DIR);
+        assert_eq!(
+            resolve_config_file(None, &env, &only_legacy),
+            PathBuf::from("./config/moneyd-config.toml")
+        );
+        let both = |_: &Path| true;
+        assert_eq!(
+            resolve_config_file(None, &env, &both),
+            PathBuf::from("/home/me/.config/moneyd/moneyd-config.toml")
+        );
+    }
+
+    #[test]
+    fn test_xdg_dirs() {
+        let env = lookup(&[("HOME", "/home/me"), ("XDG_STATE_HOME", "/var/state")]);
+        assert_eq!(
+            xdg_dir("XDG_STATE_HOME", ".local/state", &env),
+            PathBuf::from("/var/state/moneyd")
+        );
+        let env = lookup(&[("HOME", "/home/me"), ("XDG_STATE_HOME", "relative")]);
+        assert_eq!(
+            xdg_dir("XDG_STATE_HOME", ".local/state", &env),
+            PathBuf::from("/home/me/.local/state/moneyd")
+        );
+        assert_eq!(
+            xdg_dir("XDG_STATE_HOME", ".local/state", &lookup(&[])),
+            PathBuf::from("./config")
+        );
+    }
+}
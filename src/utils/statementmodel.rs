@@ -0,0 +1,123 @@
+use crate::quickbooks::parser::TempTranFromXml;
+use crate::utils::transactiontransporter::TransactionTransport;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+// the shape every statement parser hands back to ingestion,
+// regardless of whether the file was OFX, camt or anything else
+#[derive(Debug, Default)]
+pub struct ParsedStatement {
+    pub account_id: Option<String>,
+    pub bank_id: Option<String>,
+    // ofx signon <FI><ORG> and quicken's <INTU.BID>, both name the bank
+    pub fi_org: Option<String>,
+    pub intu_bid: Option<String>,
+    // the header row of a csv export, see csvstatement::parser
+    pub csv_header: Option<String>,
+    // the bank's own name for the statement, mt940 :20: and :28C:
+    // ("STARTUMSE 00001/001")
+    pub reference: Option<String>,
+    pub currency: Option<String>,
+    // rfc3339, already normalized by the parser
+    pub period_start: Option<String>,
+    pub period_end: Option<String>,
+    pub opening_balance: Option<StatementBalance>,
+    pub closing_balance: Option<StatementBalance>,
+    pub transactions: Vec<TempTranFromXml>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementBalance {
+    // signed, two decimal places, e.g. "-1234.56"
+    pub amount: String,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RemittanceInfo {
+    pub unstructured: Vec<String>,
+    pub reference: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub counterparty_account: Option<String>,
+    pub counterparty_bic: Option<String>,
+    // mt940 :86: business transaction code and booking text
+    pub transaction_code: Option<String>,
+    pub posting_text: Option<String>,
+}
+
+// whatever a file says about whose account it is, used to
+// match it against the [[account]] entries in the config
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AccountIdentity {
+    pub account_id: Option<String>,
+    pub bank_id: Option<String>,
+    pub fi_org: Option<String>,
+    pub intu_bid: Option<String>,
+    pub csv_header: Option<String>,
+}
+
+impl ParsedStatement {
+    pub fn identity(&self) -> AccountIdentity {
+        AccountIdentity {
+            account_id: self.account_id.clone(),
+            bank_id: self.bank_id.clone(),
+            fi_org: self.fi_org.clone(),
+            intu_bid: self.intu_bid.clone(),
+            csv_header: self.csv_header.clone(),
+        }
+    }
+
+    // not even a header, so whatever produced it didn't understand the file
+    pub fn is_blank(&self) -> bool {
+        self.transactions.is_empty()
+            && self.account_id.is_none()
+            && self.csv_header.is_none()
+            && self.period_start.is_none()
+            && self.period_end.is_none()
+            && self.opening_balance.is_none()
+            && self.closing_balance.is_none()
+    }
+
+    // statement period, falling back to the range of transaction dates
+    // when the file doesn't carry one
+    pub fn period(&self, txns: &[TransactionTransport]) -> (Option<String>, Option<String>) {
+        let start = self
+            .period_start
+            .clone()
+            .or_else(|| txns.iter().map(|t| t.transaction_date.clone()).min());
+        let end = self
+            .period_end
+            .clone()
+            .or_else(|| txns.iter().map(|t| t.transaction_date.clone()).max());
+        (start, end)
+    }
+}
+
+// "100" / "100.5" / "100.50" -> "100.00" / "100.50" / "100.50"
+// to_transport() turns amounts into cents by dropping the dot,
+// so everything has to come out with exactly two decimals
+pub fn normalize_amount(
+    amount: &str,
+    negative: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let value = Decimal::from_str(amount.trim().replace(',', ".").as_str())?.abs();
+    let sign = if negative && !value.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    Ok(format!("{}{:.2}", sign, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_amount_pads_decimals() {
+        assert_eq!(normalize_amount("100", false).unwrap(), "100.00");
+        assert_eq!(normalize_amount("100.5", true).unwrap(), "-100.50");
+        assert_eq!(normalize_amount("12,34", false).unwrap(), "12.34");
+        assert_eq!(normalize_amount("0", true).unwrap(), "0.00");
+    }
+}
@@ -1,18 +1,40 @@
-use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize)]
 pub struct StatementTransport {
     pub banking_user_id: i32,
     pub institution_id: i32,
+    // the backend account, when the account registry could be synced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<i32>,
     pub period_start: String,
     pub period_end: String,
 }
-#[derive(Debug, Deserialize)]
+// only what's set is sent, the rest of the statement stays as it is
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatementUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_end: Option<String>,
+}
+
+impl StatementUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.account_id.is_none() && self.period_start.is_none() && self.period_end.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct StatementResponse {
     pub statement_id: i32,
     pub banking_user_id: i32,
     pub institution_id: i32,
+    // older statements, and ones without a synced [[account]], have none
+    #[serde(default)]
+    pub account_id: Option<i32>,
     pub period_start: String,
     pub period_end: String,
     pub date_added: String,
@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::num::TryFromIntError;
+
+pub const CREDIT_TYPE_CODE: i32 = 20;
+pub const DEBIT_TYPE_CODE: i32 = 10;
 
 #[derive(Debug, Serialize)]
 pub struct TransactionTransport {
@@ -10,11 +14,89 @@ pub struct TransactionTransport {
     pub transaction_type_lookup_code: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TransactionResponse {
     pub transaction_id: i32,
     pub statement_id: i32,
     pub description: String,
     pub amount: i32,
     pub transaction_date: String,
+    pub transaction_type_lookup_code: i32,
+    // not every backend version has categories
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl TransactionResponse {
+    // amounts are stored unsigned, the type code says which way the money
+    // went. anything that isn't a credit is treated as a debit, like the
+    // parsers do
+    pub fn is_debit(&self) -> bool {
+        self.transaction_type_lookup_code != CREDIT_TYPE_CODE
+    }
+
+    // cents, negative for money going out
+    pub fn signed_amount(&self) -> i64 {
+        let cents = (self.amount as i64).abs();
+        if self.is_debit() { -cents } else { cents }
+    }
+}
+
+// only what's set is sent, the rest of the transaction stays as it is
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransactionUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    // unsigned, with the type code saying which way it went
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_type_lookup_code: Option<i32>,
+}
+
+impl TransactionUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.category.is_none()
+            && self.amount.is_none()
+            && self.transaction_type_lookup_code.is_none()
+    }
+}
+
+// a signed amount the way it's shown and typed, -12.50 for money going
+// out, split into what the backend keeps: the size and the type code
+pub fn unsigned_amount(cents: i64) -> Result<(i32, i32), TryFromIntError> {
+    let code = if cents < 0 {
+        DEBIT_TYPE_CODE
+    } else {
+        CREDIT_TYPE_CODE
+    };
+    Ok((i32::try_from(cents.unsigned_abs())?, code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amounts_round_trip_through_the_type_code() {
+        assert_eq!(unsigned_amount(-1250), Ok((1250, DEBIT_TYPE_CODE)));
+        assert_eq!(unsigned_amount(250000), Ok((250000, CREDIT_TYPE_CODE)));
+        assert!(unsigned_amount(i64::MIN).is_err());
+
+        let (amount, transaction_type_lookup_code) = unsigned_amount(-1250).unwrap();
+        let txn = TransactionResponse {
+            transaction_id: 1,
+            statement_id: 1,
+            description: String::from("Coffee"),
+            amount,
+            transaction_date: String::from("2025-11-03T00:00:00+00:00"),
+            transaction_type_lookup_code,
+            category: None,
+        };
+        assert!(txn.is_debit());
+        assert_eq!(txn.signed_amount(), -1250);
+    }
 }
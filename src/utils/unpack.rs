@@ -0,0 +1,175 @@
+use crate::utils::filescan::{is_hidden, is_temporary};
+use flate2::read::GzDecoder;
+use std::{
+    io::{Cursor, Read},
+    path::Path,
+};
+
+// a statement is a few hundred kb at most, anything bigger than
+// this inside an archive is not something we want to hold in memory
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+pub struct InnerFile {
+    // path inside the archive, e.g. "2024/january.ofx"
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ArchiveKind {
+    Zip,
+    TarGz,
+    Gz,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".gz") {
+        Some(ArchiveKind::Gz)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some()
+}
+
+// every statement-looking file in the archive. folders, hidden files
+// and leftovers like __MACOSX/ are dropped, nested archives are not opened
+pub fn unpack(path: &Path, bytes: &[u8]) -> Result<Vec<InnerFile>, Box<dyn std::error::Error>> {
+    let files = match archive_kind(path) {
+        Some(ArchiveKind::Zip) => unpack_zip(bytes)?,
+        Some(ArchiveKind::TarGz) => unpack_tar(GzDecoder::new(bytes))?,
+        Some(ArchiveKind::Gz) => {
+            // a plain .gz holds one file, named like the archive minus .gz
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            vec![InnerFile {
+                name,
+                bytes: read_limited(GzDecoder::new(bytes))?,
+            }]
+        }
+        None => return Err(format!("{} is not an archive", path.display()).into()),
+    };
+
+    Ok(files
+        .into_iter()
+        .filter(|f| {
+            let inner = Path::new(&f.name);
+            !is_hidden(inner)
+                && !is_temporary(inner)
+                && !is_archive(inner)
+                && !f.name.starts_with("__MACOSX/")
+        })
+        .collect())
+}
+
+fn unpack_zip(bytes: &[u8]) -> Result<Vec<InnerFile>, Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        files.push(InnerFile {
+            name,
+            bytes: read_limited(entry)?,
+        });
+    }
+    Ok(files)
+}
+
+fn unpack_tar<R: Read>(reader: R) -> Result<Vec<InnerFile>, Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        files.push(InnerFile {
+            name,
+            bytes: read_limited(entry)?,
+        });
+    }
+    Ok(files)
+}
+
+fn read_limited<R: Read>(reader: R) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    reader.take(MAX_ENTRY_SIZE + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_ENTRY_SIZE {
+        return Err(format!("Archive entry is larger than {} bytes", MAX_ENTRY_SIZE).into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    const OFX: &[u8] = b"<OFX><STMTTRN></STMTTRN></OFX>";
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_zip() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("2024/", options).unwrap();
+        writer.start_file("2024/january.ofx", options).unwrap();
+        writer.write_all(OFX).unwrap();
+        writer
+            .start_file("__MACOSX/2024/._january.ofx", options)
+            .unwrap();
+        writer.write_all(b"junk").unwrap();
+        writer.start_file("2024/.DS_Store", options).unwrap();
+        writer.write_all(b"junk").unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let files = unpack(Path::new("statements.ZIP"), &bytes).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "2024/january.ofx");
+        assert_eq!(files[0].bytes, OFX);
+    }
+
+    #[test]
+    fn test_unpack_gz_and_tar_gz() {
+        let files = unpack(Path::new("/tmp/january.ofx.gz"), &gzip(OFX)).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "january.ofx");
+        assert_eq!(files[0].bytes, OFX);
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in ["january.ofx", "february.ofx"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(OFX.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, OFX).unwrap();
+        }
+        let tarball = gzip(&builder.into_inner().unwrap());
+        let files = unpack(Path::new("/tmp/2024.tar.gz"), &tarball).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["january.ofx", "february.ofx"]);
+
+        assert!(is_archive(Path::new("2024.tgz")));
+        assert!(!is_archive(Path::new("january.ofx")));
+    }
+}